@@ -0,0 +1,189 @@
+# Message catalog of the English locale.
+#
+# Ids are shared by all locales. Error messages are named after the error codes of
+# the API, see `ErrorKind::code`.
+
+## Errors
+
+user_already_exists = User already exists.
+wrong_password = Wrong password.
+login_required = You cannot access or interact with the content of this page. Please log in to continue.
+session_expired = Session expired. Please, authorize again.
+session_terminated = Session was terminated. Please, authorize again.
+invalid_token = Invalid access token. Please, authorize again.
+account_banned = Your account is banned.
+account_suspended = Your account is suspended until { $until } UTC.
+action_forbidden = You do not have permission to perform this action.
+post_edit_forbidden = You do not have permission to edit this post.
+post_delete_forbidden = You do not have permission to delete this post.
+comment_delete_forbidden = You do not have permission to delete this comment.
+attachment_forbidden = You do not have permission to attach images to this post.
+profile_edit_forbidden = You do not have permission to edit this profile.
+moderation_forbidden = You do not have permission to moderate this user.
+invalid_csrf_token = Invalid CSRF token.
+invalid_cursor = Invalid cursor.
+nothing_to_change = Nothing to change.
+self_follow = You cannot follow yourself.
+empty_search_query = Search query is empty.
+unknown_reaction = Unknown reaction, expected one of: { $kinds }.
+malformed_json = Request body is not valid JSON: { $detail }
+body_too_large = Request body is too large.
+invalid_multipart = Invalid multipart body.
+no_image = No image was uploaded.
+no_images = No images were uploaded.
+unreadable_image = Image can not be read.
+too_many_attachments = A post can not have more than { $max } images.
+image_too_large = Image must not be larger than { $mebibytes } MiB.
+unsupported_image_format = Only PNG, JPEG, GIF and WebP images are supported.
+damaged_image = Image is damaged or larger than { $max }x{ $max } pixels.
+validation_failed = Some fields are invalid.
+too_many_requests = Too many attempts. Please, try again in { $seconds } seconds.
+page_not_found = The requested page does not exist.
+post_not_found = The requested post does not exist.
+user_not_found = User does not exist.
+comment_not_found = The requested comment does not exist.
+export_not_found = The requested export does not exist or has expired.
+export_not_ready = The export archive is not ready.
+internal_error = Something went wrong.
+
+## Invalid fields
+
+invalid_value = Invalid value ({ $rule }).
+blank = Must not be blank.
+username_required = Username is required.
+username_length = Username must be from { $min } to { $max } characters long.
+username_charset = Username may contain only letters, digits, '_' and '-'.
+password_required = Password is required.
+current_password_required = Current password is required.
+password_too_short = Password must be at least { $min } characters long.
+password_too_long = Password must not be longer than { $max } bytes.
+password_too_weak = Password must contain both letters and digits.
+refresh_token_empty = Refresh token must not be empty.
+title_too_long = Title must not be longer than { $max } characters.
+content_too_long = Content must not be longer than { $max } characters.
+comment_too_long = Comment must not be longer than { $max } characters.
+display_name_too_long = Display name must not be longer than { $max } characters.
+bio_too_long = Bio must not be longer than { $max } characters.
+reason_too_long = Reason must not be longer than { $max } characters.
+suspension_days_out_of_range = Suspension must last from { $min } to { $max } days.
+too_many_notification_ids = At most { $max } notifications can be marked at once.
+
+## Results
+
+registered = Registration successful!
+logged_out = Logged out successfully.
+post_deleted = Post deleted successfully.
+post_trashed = Post moved to the trash.
+post_restored = Post restored successfully.
+comment_deleted = Comment deleted successfully.
+avatar_removed = Avatar removed successfully.
+password_changed = Password changed successfully. Please, log in again.
+account_deleted = Account deleted successfully.
+user_suspended = User suspended successfully.
+user_banned = User banned successfully.
+user_unblocked = User unblocked successfully.
+role_changed = Role changed successfully.
+
+## Navigation
+
+nav_home = Home
+nav_posts = Posts
+nav_notifications = Notifications
+nav_login = Log in
+nav_register = Sign up
+load_more = Show more
+
+## Home page
+
+index_title = Home
+index_welcome = Welcome!
+index_intro = In this mini social network you can:
+index_posts_feature = Publish posts
+index_posts_description = share your thoughts.
+index_likes_feature = Like posts
+index_likes_description = support the posts you enjoy.
+index_account_feature = Sign up and log in
+index_account_description = create an account and join the community.
+
+## Login and registration pages
+
+login_title = Log in
+login_hint = Please, fill in the form to log in.
+login_submit = Log in
+register_title = Sign up
+register_hint = Please, fill in the form to sign up.
+register_submit = Sign up
+field_username = Username
+field_password = Password
+
+## Posts
+
+posts_title = Posts
+new_post_heading = Publish a new post
+field_title = Title
+field_content = Content
+field_images = Images
+field_publish_at = Publish at (optional)
+field_draft = Save as draft
+new_post_submit = Publish post
+search_placeholder = Search posts and users
+search_submit = Search
+source_all = All posts
+source_feed = My subscriptions
+source_bookmarks = Bookmarks
+source_drafts = Drafts and scheduled
+sort_newest = Newest first
+sort_most_liked = Most liked
+filter_submit = Show
+no_posts = No posts have been published yet.
+post_author = Author:
+post_edited = Edited:
+post_reposted = reposted
+quote_unavailable = The quoted post is unavailable.
+show_revisions = Edit history
+comments_heading = Comments
+comment_placeholder = Your comment
+comment_submit = Send
+no_comments = No comments yet.
+
+## User page
+
+followers = Followers
+following = Following
+profile_heading = Profile
+field_display_name = Display name
+field_bio = About
+profile_submit = Save
+field_avatar = Avatar
+avatar_submit = Upload
+delete_avatar = Remove avatar
+password_heading = Change password
+field_current_password = Current password
+field_new_password = New password
+password_submit = Change password
+delete_account_heading = Account deletion
+delete_account_warning = All posts, comments and subscriptions will be deleted permanently.
+delete_account_submit = Delete account
+no_user_posts = No posts yet.
+
+## Data export
+
+export_title = Personal data
+export_intro = This archive contains everything stored about your account. The same data is in the JSON files, the images are in the media folder.
+export_created = Exported:
+export_role = Role
+export_registered = Registered:
+export_repost_of = Repost of post
+export_quote_of = Quote of post
+export_trashed = In the trash since:
+export_reactions = Reactions
+export_to_post = Post
+export_empty = Nothing here.
+export_heading = Personal data export
+export_hint = Download an archive with your profile, posts, reactions and comments.
+export_submit = Export my data
+
+## Notifications page
+
+notifications_title = Notifications
+notifications_login_hint = Log in to see your notifications.
@@ -0,0 +1,189 @@
+# Каталог сообщений русской локали.
+#
+# Идентификаторы общие для всех локалей. Сообщения об ошибках названы по кодам ошибок
+# API, см. `ErrorKind::code`.
+
+## Ошибки
+
+user_already_exists = Пользователь уже существует.
+wrong_password = Неверный пароль.
+login_required = Содержимое этой страницы недоступно. Пожалуйста, войдите, чтобы продолжить.
+session_expired = Сессия истекла. Пожалуйста, войдите снова.
+session_terminated = Сессия была завершена. Пожалуйста, войдите снова.
+invalid_token = Недействительный токен доступа. Пожалуйста, войдите снова.
+account_banned = Ваш аккаунт заблокирован.
+account_suspended = Ваш аккаунт приостановлен до { $until } UTC.
+action_forbidden = У вас нет прав на это действие.
+post_edit_forbidden = У вас нет прав на редактирование этого поста.
+post_delete_forbidden = У вас нет прав на удаление этого поста.
+comment_delete_forbidden = У вас нет прав на удаление этого комментария.
+attachment_forbidden = У вас нет прав на добавление изображений к этому посту.
+profile_edit_forbidden = У вас нет прав на редактирование этого профиля.
+moderation_forbidden = У вас нет прав на модерацию этого пользователя.
+invalid_csrf_token = Недействительный CSRF-токен.
+invalid_cursor = Недействительный курсор.
+nothing_to_change = Нечего изменять.
+self_follow = Нельзя подписаться на самого себя.
+empty_search_query = Поисковый запрос пуст.
+unknown_reaction = Неизвестная реакция, ожидается одна из: { $kinds }.
+malformed_json = Тело запроса не является корректным JSON: { $detail }
+body_too_large = Тело запроса слишком большое.
+invalid_multipart = Некорректное multipart-тело запроса.
+no_image = Изображение не загружено.
+no_images = Изображения не загружены.
+unreadable_image = Не удалось прочитать изображение.
+too_many_attachments = К посту можно прикрепить не больше { $max } изображений.
+image_too_large = Изображение должно быть не больше { $mebibytes } МиБ.
+unsupported_image_format = Поддерживаются только изображения PNG, JPEG, GIF и WebP.
+damaged_image = Изображение повреждено или больше { $max }x{ $max } пикселей.
+validation_failed = Некоторые поля заполнены неверно.
+too_many_requests = Слишком много попыток. Пожалуйста, повторите через { $seconds } с.
+page_not_found = Запрошенная страница не существует.
+post_not_found = Запрошенный пост не существует.
+user_not_found = Пользователь не существует.
+comment_not_found = Запрошенный комментарий не существует.
+export_not_found = Запрошенный экспорт не существует или устарел.
+export_not_ready = Архив экспорта ещё не готов.
+internal_error = Что-то пошло не так.
+
+## Неверные поля
+
+invalid_value = Недопустимое значение ({ $rule }).
+blank = Не должно быть пустым.
+username_required = Укажите логин.
+username_length = Логин должен содержать от { $min } до { $max } символов.
+username_charset = Логин может содержать только буквы, цифры, '_' и '-'.
+password_required = Укажите пароль.
+current_password_required = Укажите текущий пароль.
+password_too_short = Пароль должен содержать не меньше { $min } символов.
+password_too_long = Пароль должен быть не длиннее { $max } байт.
+password_too_weak = Пароль должен содержать и буквы, и цифры.
+refresh_token_empty = Refresh-токен не должен быть пустым.
+title_too_long = Название должно быть не длиннее { $max } символов.
+content_too_long = Содержимое должно быть не длиннее { $max } символов.
+comment_too_long = Комментарий должен быть не длиннее { $max } символов.
+display_name_too_long = Отображаемое имя должно быть не длиннее { $max } символов.
+bio_too_long = Поле «О себе» должно быть не длиннее { $max } символов.
+reason_too_long = Причина должна быть не длиннее { $max } символов.
+suspension_days_out_of_range = Срок приостановки должен быть от { $min } до { $max } дней.
+too_many_notification_ids = За раз можно отметить не больше { $max } уведомлений.
+
+## Результаты
+
+registered = Успешная регистрация!
+logged_out = Вы вышли из аккаунта.
+post_deleted = Пост удалён.
+post_trashed = Пост перемещён в корзину.
+post_restored = Пост восстановлен.
+comment_deleted = Комментарий удалён.
+avatar_removed = Аватар удалён.
+password_changed = Пароль изменён. Пожалуйста, войдите снова.
+account_deleted = Аккаунт удалён.
+user_suspended = Пользователь приостановлен.
+user_banned = Пользователь заблокирован.
+user_unblocked = Пользователь разблокирован.
+role_changed = Роль изменена.
+
+## Навигация
+
+nav_home = Главная
+nav_posts = Посты
+nav_notifications = Уведомления
+nav_login = Вход
+nav_register = Регистрация
+load_more = Показать ещё
+
+## Главная страница
+
+index_title = Главная
+index_welcome = Добро пожаловать!
+index_intro = В этой мини-социальной сети вы можете:
+index_posts_feature = Публиковать посты
+index_posts_description = делитесь своими мыслями.
+index_likes_feature = Ставить лайки
+index_likes_description = поддерживайте посты, которые вам нравятся.
+index_account_feature = Регистрироваться и входить
+index_account_description = создайте аккаунт и присоединяйтесь к сообществу.
+
+## Страницы входа и регистрации
+
+login_title = Вход
+login_hint = Пожалуйста, заполните данные чтобы войти.
+login_submit = Войти
+register_title = Регистрация
+register_hint = Пожалуйста, заполните данные чтобы зарегистрироваться.
+register_submit = Зарегистрироваться
+field_username = Логин
+field_password = Пароль
+
+## Посты
+
+posts_title = Посты
+new_post_heading = Опубликовать новый пост
+field_title = Название
+field_content = Содержимое
+field_images = Изображения
+field_publish_at = Опубликовать в (необязательно)
+field_draft = Сохранить как черновик
+new_post_submit = Опубликовать пост
+search_placeholder = Поиск по постам и пользователям
+search_submit = Найти
+source_all = Все посты
+source_feed = Мои подписки
+source_bookmarks = Закладки
+source_drafts = Черновики и отложенные
+sort_newest = Сначала новые
+sort_most_liked = Самые популярные
+filter_submit = Показать
+no_posts = Пока что нет ни одного опубликованного поста.
+post_author = Автор:
+post_edited = Изменено:
+post_reposted = сделал(а) репост
+quote_unavailable = Цитируемый пост недоступен.
+show_revisions = История изменений
+comments_heading = Комментарии
+comment_placeholder = Ваш комментарий
+comment_submit = Отправить
+no_comments = Пока что комментариев нет.
+
+## Страница пользователя
+
+followers = Подписчики
+following = Подписки
+profile_heading = Профиль
+field_display_name = Отображаемое имя
+field_bio = О себе
+profile_submit = Сохранить
+field_avatar = Аватар
+avatar_submit = Загрузить
+delete_avatar = Удалить аватар
+password_heading = Смена пароля
+field_current_password = Текущий пароль
+field_new_password = Новый пароль
+password_submit = Сменить пароль
+delete_account_heading = Удаление аккаунта
+delete_account_warning = Все посты, комментарии и подписки будут удалены без возможности восстановления.
+delete_account_submit = Удалить аккаунт
+no_user_posts = Пока что постов нет.
+
+## Экспорт данных
+
+export_title = Персональные данные
+export_intro = Архив содержит всё, что хранится о вашей учётной записи. Те же данные есть в файлах JSON, изображения — в папке media.
+export_created = Экспортировано:
+export_role = Роль
+export_registered = Зарегистрирован(а):
+export_repost_of = Репост поста
+export_quote_of = Цитата поста
+export_trashed = В корзине с:
+export_reactions = Реакции
+export_to_post = Пост
+export_empty = Здесь пусто.
+export_heading = Экспорт персональных данных
+export_hint = Скачайте архив со своим профилем, постами, реакциями и комментариями.
+export_submit = Экспортировать мои данные
+
+## Страница уведомлений
+
+notifications_title = Уведомления
+notifications_login_hint = Войдите, чтобы увидеть уведомления.
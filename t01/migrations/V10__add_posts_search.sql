@@ -0,0 +1,9 @@
+alter table posts add column if not exists search tsvector
+    generated always as (
+        setweight(to_tsvector('russian'::regconfig, title), 'A') ||
+        setweight(to_tsvector('russian'::regconfig, content), 'B')
+    ) stored;
+
+create index if not exists posts_search_idx on posts using gin (search);
+
+create index if not exists users_username_prefix_idx on users (lower(username) text_pattern_ops);
@@ -0,0 +1,16 @@
+alter table users add column if not exists role text not null default 'user'
+    check (role in ('user', 'moderator', 'admin'));
+alter table users add column if not exists suspended_until timestamp;
+alter table users add column if not exists banned_at timestamp;
+
+create table if not exists moderation_actions (
+         action_id    serial primary key,
+      moderator_id       int references users(user_id) on delete set null,
+            action      text not null,
+    target_user_id       int references users(user_id) on delete set null,
+    target_post_id       int,
+            reason      text,
+        created_at timestamp default current_timestamp
+);
+
+create index if not exists moderation_actions_created_at_idx on moderation_actions (created_at desc);
@@ -0,0 +1,14 @@
+create table if not exists attachments (
+     attachment_id    serial primary key,
+           post_id       int not null references posts(post_id) on delete cascade,
+           user_id       int not null references users(user_id) on delete cascade,
+         file_name      text not null unique,
+    thumbnail_name      text not null unique,
+      content_type      text not null,
+        size_bytes       int not null,
+             width       int not null,
+            height       int not null,
+        created_at timestamp default current_timestamp
+);
+
+create index if not exists attachments_post_id_idx on attachments (post_id, attachment_id);
@@ -0,0 +1,13 @@
+create table if not exists notifications (
+    notification_id    serial primary key,
+            user_id       int not null references users(user_id) on delete cascade,
+           actor_id       int not null references users(user_id) on delete cascade,
+               kind      text not null check (kind in ('like', 'comment', 'reply', 'follow')),
+            post_id       int references posts(post_id) on delete cascade,
+         comment_id       int references comments(comment_id) on delete cascade,
+            read_at timestamp,
+         created_at timestamp default current_timestamp
+);
+
+create index if not exists notifications_user_id_idx on notifications (user_id, notification_id desc);
+create index if not exists notifications_unread_idx on notifications (user_id) where read_at is null;
@@ -0,0 +1,4 @@
+alter table users add column if not exists display_name          text;
+alter table users add column if not exists bio                   text;
+alter table users add column if not exists avatar_file_name      text unique;
+alter table users add column if not exists avatar_thumbnail_name text unique;
@@ -0,0 +1,4 @@
+alter table posts add column if not exists deleted_at timestamp;
+
+create index if not exists posts_user_deleted_idx on posts (user_id, deleted_at desc) where deleted_at is not null;
+create index if not exists posts_deleted_at_idx   on posts (deleted_at)               where deleted_at is not null;
@@ -0,0 +1,33 @@
+create table if not exists reactions (
+    reaction_id    serial primary key,
+        user_id       int not null references users(user_id) on delete cascade,
+        post_id       int not null references posts(post_id) on delete cascade,
+           kind      text not null,
+     created_at timestamp default current_timestamp,
+
+    unique(user_id, post_id, kind)
+);
+
+create index if not exists reactions_post_id_idx on reactions (post_id, kind);
+
+insert into reactions (user_id, post_id, kind, created_at)
+select user_id, post_id, 'like', created_at
+from likes
+where user_id is not null and post_id is not null;
+
+drop table if exists likes;
+
+create or replace function update_post_likes_count() returns trigger as $$
+begin
+    if tg_op = 'INSERT' and new.kind = 'like' then
+        update posts set likes_count = likes_count + 1 where post_id = new.post_id;
+    elsif tg_op = 'DELETE' and old.kind = 'like' then
+        update posts set likes_count = likes_count - 1 where post_id = old.post_id;
+    end if;
+    return null;
+end;
+$$ language plpgsql;
+
+create trigger likes_count_trigger
+after insert or delete on reactions
+for each row execute function update_post_likes_count();
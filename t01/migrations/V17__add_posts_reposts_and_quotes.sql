@@ -0,0 +1,8 @@
+alter table posts add column if not exists repost_of_id int references posts(post_id) on delete cascade;
+alter table posts add column if not exists  quote_of_id int references posts(post_id) on delete set null;
+
+alter table posts add constraint posts_repost_or_quote_check check (repost_of_id is null or quote_of_id is null);
+
+create unique index if not exists posts_user_repost_idx on posts (user_id, repost_of_id) where repost_of_id is not null;
+create        index if not exists posts_repost_of_idx   on posts (repost_of_id)          where repost_of_id is not null;
+create        index if not exists posts_quote_of_idx    on posts (quote_of_id)           where quote_of_id is not null;
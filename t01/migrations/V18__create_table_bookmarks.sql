@@ -0,0 +1,9 @@
+create table if not exists bookmarks (
+        user_id       int references users(user_id) on delete cascade,
+        post_id       int references posts(post_id) on delete cascade,
+     created_at timestamp default current_timestamp,
+
+    primary key (user_id, post_id)
+);
+
+create index if not exists bookmarks_post_id_idx on bookmarks (post_id);
@@ -0,0 +1,8 @@
+alter table posts add column if not exists     status text not null default 'published';
+alter table posts add column if not exists publish_at timestamp;
+
+alter table posts add constraint posts_status_check    check (status in ('draft', 'scheduled', 'published'));
+alter table posts add constraint posts_scheduled_check check (status <> 'scheduled' or publish_at is not null);
+
+create index if not exists posts_scheduled_idx   on posts (publish_at) where status = 'scheduled';
+create index if not exists posts_unpublished_idx on posts (user_id)    where status <> 'published';
@@ -0,0 +1,15 @@
+create table if not exists exports (
+       export_id    serial primary key,
+         user_id       int not null references users(user_id) on delete cascade,
+          status      text not null default 'pending' check (status in ('pending', 'ready', 'failed')),
+       file_name      text unique,
+      created_at timestamp not null default current_timestamp,
+    completed_at timestamp,
+      expires_at timestamp
+);
+
+create index if not exists exports_user_id_idx on exports (user_id, created_at desc);
+create index if not exists exports_expires_at_idx on exports (expires_at);
+
+-- one export is built at a time for a user
+create unique index if not exists exports_pending_idx on exports (user_id) where status = 'pending';
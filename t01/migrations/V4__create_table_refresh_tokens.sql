@@ -0,0 +1,8 @@
+create table if not exists refresh_tokens (
+    refresh_token_id    serial primary key,
+             user_id       int references users(user_id) on delete cascade,
+          token_hash      text unique not null,
+          expires_at timestamp not null,
+          revoked_at timestamp,
+          created_at timestamp default current_timestamp
+);
@@ -0,0 +1,5 @@
+create table if not exists revoked_tokens (
+           jti      text primary key,
+    expires_at timestamp not null,
+    revoked_at timestamp default current_timestamp
+);
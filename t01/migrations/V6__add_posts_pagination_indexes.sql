@@ -0,0 +1,25 @@
+alter table posts add column if not exists likes_count bigint not null default 0;
+
+update posts p
+set likes_count = (select count(*) from likes l where l.post_id = p.post_id);
+
+create or replace function update_post_likes_count() returns trigger as $$
+begin
+    if tg_op = 'INSERT' then
+        update posts set likes_count = likes_count + 1 where post_id = new.post_id;
+    elsif tg_op = 'DELETE' then
+        update posts set likes_count = likes_count - 1 where post_id = old.post_id;
+    end if;
+    return null;
+end;
+$$ language plpgsql;
+
+create trigger likes_count_trigger
+after insert or delete on likes
+for each row execute function update_post_likes_count();
+
+create index if not exists posts_newest_idx          on posts (created_at desc, post_id desc);
+create index if not exists posts_most_liked_idx      on posts (likes_count desc, created_at desc, post_id desc);
+create index if not exists posts_user_newest_idx     on posts (user_id, created_at desc, post_id desc);
+create index if not exists posts_user_most_liked_idx on posts (user_id, likes_count desc, created_at desc, post_id desc);
+create index if not exists likes_post_id_idx         on likes (post_id);
@@ -0,0 +1,27 @@
+create table if not exists comments (
+           comment_id    serial primary key,
+              post_id       int references posts(post_id) on delete cascade,
+              user_id       int references users(user_id) on delete cascade,
+    parent_comment_id       int references comments(comment_id) on delete cascade,
+              content      text not null,
+           created_at timestamp default current_timestamp
+);
+
+create index if not exists comments_post_id_idx on comments (post_id, created_at);
+
+alter table posts add column if not exists comments_count bigint not null default 0;
+
+create or replace function update_post_comments_count() returns trigger as $$
+begin
+    if tg_op = 'INSERT' then
+        update posts set comments_count = comments_count + 1 where post_id = new.post_id;
+    elsif tg_op = 'DELETE' then
+        update posts set comments_count = comments_count - 1 where post_id = old.post_id;
+    end if;
+    return null;
+end;
+$$ language plpgsql;
+
+create trigger comments_count_trigger
+after insert or delete on comments
+for each row execute function update_post_comments_count();
@@ -0,0 +1,11 @@
+alter table posts add column if not exists edited_at timestamp;
+
+create table if not exists post_revisions (
+    revision_id    serial primary key,
+        post_id       int references posts(post_id) on delete cascade,
+          title      text not null,
+        content      text not null,
+     created_at timestamp default current_timestamp
+);
+
+create index if not exists post_revisions_post_id_idx on post_revisions (post_id, revision_id);
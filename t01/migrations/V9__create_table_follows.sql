@@ -0,0 +1,10 @@
+create table if not exists follows (
+    follower_id       int references users(user_id) on delete cascade,
+    followee_id       int references users(user_id) on delete cascade,
+     created_at timestamp default current_timestamp,
+
+    primary key (follower_id, followee_id),
+    check (follower_id <> followee_id)
+);
+
+create index if not exists follows_followee_id_idx on follows (followee_id);
@@ -1,7 +1,14 @@
-use crate::{repository, Config};
+use crate::{
+    events::FeedEvents,
+    model::ReactionKind,
+    repository::PostgresRepository,
+    storage::{ExportStorage, MediaStorage},
+    Config,
+};
 use anyhow::Result;
 use axum::Router;
 use log::info;
+use std::{net::SocketAddr, sync::Arc};
 use tokio::net::TcpListener;
 
 mod routes;
@@ -19,17 +26,58 @@ impl App {
             config.address.0, config.address.1
         );
 
-        let shared_state = repository::Repository::initialize(config.database_config).await?;
+        let reaction_kinds = config.reaction_kinds.iter().map(ReactionKind::as_str);
+        info!(
+            "Reactions offered: {}",
+            reaction_kinds.collect::<Vec<_>>().join(", ")
+        );
+        ReactionKind::enable(config.reaction_kinds);
+
+        let feed_events = FeedEvents::listen(config.database_config.get_pg_config()?);
+
+        let repository = PostgresRepository::initialize(config.database_config).await?;
         info!("Repository initialized");
 
-        let router = routes::initialize_router(shared_state);
+        let repository = Arc::new(repository);
+
+        let media_storage = MediaStorage::initialize(config.upload_dir).await?;
+
+        routes::spawn_purge(
+            repository.clone(),
+            media_storage.clone(),
+            config.trash_retention,
+        );
+        info!(
+            "Trash purge started, retention is {} days",
+            config.trash_retention.num_days()
+        );
+
+        routes::spawn_publisher(repository.clone());
+        info!("Publisher of scheduled posts started");
+
+        let export_storage =
+            ExportStorage::initialize(config.export_dir, config.export_retention).await?;
+
+        routes::spawn_export_cleanup(repository.clone(), export_storage.clone());
+        info!(
+            "Export cleanup started, retention is {} hours",
+            config.export_retention.num_hours()
+        );
+
+        let router =
+            routes::initialize_router(repository, media_storage, export_storage, feed_events);
         info!("Router initialized");
 
         Ok(Self { listener, router })
     }
 
     pub(crate) async fn run(self) -> Result<()> {
-        axum::serve(self.listener, self.router).await?;
+        axum::serve(
+            self.listener,
+            self.router
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .await?;
 
         Ok(())
     }
@@ -1,150 +1,287 @@
 use crate::{
     error::AppError,
-    model::{Claims, CreatePostRequest, LoginRequest, RegisterRequest},
-    repository::{PostDeleteResult, Repository},
+    events::FeedEvents,
+    i18n::Locale,
+    model::{
+        Claims, CreateCommentRequest, CreatePostRequest, EditPostRequest, LoginRequest,
+        LogoutRequest, PostCursor, PostsQuery, RefreshRequest, RegisterRequest, SearchQuery,
+        SessionMode,
+    },
+    repository::{
+        CommentCreateResult, CommentDeleteResult, CommentThread, PostDeleteResult, PostEditResult,
+        PostsPage, RefreshTokenRotation, SharedRepository,
+    },
+    storage::{ExportStorage, MediaStorage},
     utils::PasswordHash,
 };
-use askama::Template;
-use auth::validate_jwt;
-use axum::{extract::Path, Extension};
+use auth::{validate_jwt, RefreshToken};
 use axum::{
     extract::State,
     response::IntoResponse,
-    routing::{get, post},
+    routing::{delete, get, post, put},
     Json, Router,
 };
+use axum::{
+    extract::{DefaultBodyLimit, FromRef, Path, Query},
+    http::{header, HeaderMap, HeaderValue, Method},
+    Extension,
+};
+use axum_extra::extract::cookie::{Cookie, CookieJar};
 use log::{info, warn};
+use rate_limit::{limit_auth_requests, RateLimiter};
 use serde_json::json;
-use tower_http::services::ServeDir;
+use tower_http::{services::ServeDir, set_header::SetResponseHeaderLayer};
+use utoipa::OpenApi;
+use utoipa_swagger_ui::SwaggerUi;
+use validation::ValidJson;
 
+mod attachments;
 mod auth;
-
-/*
-- `POST /register`: register a new user
-- `POST /login`: log in a user
-- `POST /posts`: create a new post
-- `GET /posts/{post_id}`: get a post
-- `DELETE /posts/{post_id}`: delete a post
-- `POST /posts/{post_id}/likes`: like a post.
-*/
-pub(crate) fn initialize_router(state: Repository) -> Router {
-    let router = Router::new()
-        .route("/", get(get_page_index))
-        .route("/register", get(get_page_registration))
-        .route("/api/register", post(register_user))
-        .route("/login", get(get_page_login))
-        .route("/api/login", post(login_user))
-        .route("/posts", get(get_page_posts))
-        .route("/posts/:post_id", get(get_page_post))
-        .route("/users/:user_id", get(get_page_user));
-
-    let secure_router = Router::new()
-        .route("/api/posts/:post_id/likes", post(like_post))
-        .route("/api/posts/:post_id", get(get_post).delete(delete_post))
-        .route("/api/posts", get(get_posts).post(create_post))
-        .route("/api/users/:user_id", get(get_user_posts))
-        .layer(axum::middleware::from_fn(validate_jwt));
-
-    Router::new()
-        .merge(secure_router)
-        .merge(router)
-        .nest_service("/static", ServeDir::new("static"))
-        .fallback(handle_404)
-        .with_state(state)
+mod bookmarks;
+mod drafts;
+mod exports;
+mod locale;
+mod moderation;
+mod notifications;
+mod openapi;
+mod pages;
+mod profiles;
+mod rate_limit;
+mod reactions;
+mod reposts;
+mod session;
+mod stream;
+#[cfg(test)]
+mod tests;
+mod trash;
+mod validation;
+
+pub(super) use drafts::spawn_publisher;
+pub(super) use exports::spawn_export_cleanup;
+pub(super) use trash::spawn_purge;
+
+/// State of the router. Handlers extract only the parts they need.
+#[derive(Clone)]
+pub(crate) struct AppState {
+    repository: SharedRepository,
+    rate_limiter: RateLimiter,
+    media: MediaStorage,
+    exports: ExportStorage,
+    events: FeedEvents,
 }
 
-#[derive(Debug, Template)]
-#[template(path = "index.askama.html")]
-struct IndexTemplate {}
-
-/// `GET /`
-async fn get_page_index(_: State<Repository>) -> Result<impl IntoResponse, AppError> {
-    info!("Index page was requested.");
-    let html = IndexTemplate {};
-
-    Ok(askama_axum::into_response(&html))
+impl FromRef<AppState> for SharedRepository {
+    fn from_ref(state: &AppState) -> Self {
+        state.repository.clone()
+    }
 }
 
-#[derive(Debug, Template)]
-#[template(path = "register.askama.html")]
-struct RegisterTemplate {}
-
-/// `GET /register`
-async fn get_page_registration(_: State<Repository>) -> Result<impl IntoResponse, AppError> {
-    info!("Register page was requested.");
-    let html = RegisterTemplate {};
-
-    Ok(askama_axum::into_response(&html))
+impl FromRef<AppState> for RateLimiter {
+    fn from_ref(state: &AppState) -> Self {
+        state.rate_limiter.clone()
+    }
 }
 
-#[derive(Debug, Template)]
-#[template(path = "login.askama.html")]
-struct LoginTemplate {}
-
-/// `GET /login`
-async fn get_page_login(_: State<Repository>) -> Result<impl IntoResponse, AppError> {
-    info!("Login page was requested.");
-    let html = LoginTemplate {};
-
-    Ok(askama_axum::into_response(&html))
+impl FromRef<AppState> for MediaStorage {
+    fn from_ref(state: &AppState) -> Self {
+        state.media.clone()
+    }
 }
 
-#[derive(Debug, Template)]
-#[template(path = "posts.askama.html")]
-struct PostsTemplate {}
-
-/// `GET /posts`
-async fn get_page_posts(_: State<Repository>) -> Result<impl IntoResponse, AppError> {
-    info!("Posts page was requested.");
-    let html = PostsTemplate {};
-
-    Ok(askama_axum::into_response(&html))
+impl FromRef<AppState> for ExportStorage {
+    fn from_ref(state: &AppState) -> Self {
+        state.exports.clone()
+    }
 }
 
-#[derive(Debug, Template)]
-#[template(path = "post.askama.html")]
-struct PostTemplate {}
+impl FromRef<AppState> for FeedEvents {
+    fn from_ref(state: &AppState) -> Self {
+        state.events.clone()
+    }
+}
 
-/// `GET /posts/{post_id}`
-async fn get_page_post(
-    _: State<Repository>,
-    Path(post_id): Path<i32>,
-) -> Result<impl IntoResponse, AppError> {
-    info!("Post page with id = {post_id} was requested.");
-    let html = PostTemplate {};
+/// Builds the router. The JSON API is described by [`openapi::ApiDoc`].
+pub(crate) fn initialize_router(
+    repository: SharedRepository,
+    media: MediaStorage,
+    exports: ExportStorage,
+    events: FeedEvents,
+) -> Router {
+    // names of uploaded files are unique, so the files never change
+    let media_router = Router::new()
+        .nest_service("/media", ServeDir::new(media.dir()))
+        .layer(SetResponseHeaderLayer::overriding(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static("public, max-age=31536000, immutable"),
+        ))
+        .layer(SetResponseHeaderLayer::overriding(
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        ));
+
+    let state = AppState {
+        repository,
+        rate_limiter: RateLimiter::new(),
+        media,
+        exports,
+        events,
+    };
 
-    Ok(askama_axum::into_response(&html))
-}
+    let auth_router = Router::new()
+        .route("/api/register", post(register_user))
+        .route("/api/login", post(login_user))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            limit_auth_requests,
+        ));
 
-#[derive(Debug, Template)]
-#[template(path = "user.askama.html")]
-struct UserTemplate {}
+    let router = Router::new()
+        .route("/", get(pages::get_page_index))
+        .route("/register", get(pages::get_page_registration))
+        .route("/login", get(pages::get_page_login))
+        .route("/api/token/refresh", post(refresh_token))
+        .route("/posts", get(pages::get_page_posts))
+        .route("/posts/:post_id", get(pages::get_page_post))
+        .route("/users/:user_id", get(pages::get_page_user))
+        .route("/notifications", get(pages::get_page_notifications));
 
-async fn get_page_user(
-    _: State<Repository>,
-    Path(user_id): Path<i32>,
-) -> Result<impl IntoResponse, AppError> {
-    info!("User page with id = {user_id} was requested.");
-    let html = UserTemplate {};
+    let secure_router = Router::new()
+        .route("/api/posts/:post_id/likes", post(like_post))
+        .route(
+            "/api/posts/:post_id/reactions/:kind",
+            post(reactions::add_reaction).delete(reactions::remove_reaction),
+        )
+        .route(
+            "/api/posts/:post_id/bookmark",
+            post(bookmarks::add_bookmark).delete(bookmarks::remove_bookmark),
+        )
+        .route(
+            "/api/posts/:post_id/repost",
+            post(reposts::repost).delete(reposts::remove_repost),
+        )
+        .route(
+            "/api/posts/:post_id",
+            get(get_post).patch(edit_post).delete(delete_post),
+        )
+        .route("/api/posts/:post_id/revisions", get(get_post_revisions))
+        .route(
+            "/api/posts/:post_id/attachments",
+            post(attachments::upload_attachments)
+                .layer(DefaultBodyLimit::max(attachments::MAX_UPLOAD_BODY_SIZE)),
+        )
+        .route("/api/posts", get(get_posts).post(create_post))
+        .route(
+            "/api/posts/:post_id/comments",
+            get(get_comments).post(create_comment),
+        )
+        .route(
+            "/api/posts/:post_id/comments/:comment_id",
+            delete(delete_comment),
+        )
+        .route("/api/users/:user_id", get(get_user_posts))
+        .route(
+            "/api/users/:user_id/follow",
+            post(follow_user).delete(unfollow_user),
+        )
+        .route(
+            "/api/users/:user_id/profile",
+            get(profiles::get_profile).patch(profiles::edit_profile),
+        )
+        .route("/api/users/:user_id/followers", get(get_followers))
+        .route("/api/users/:user_id/following", get(get_following))
+        .route("/api/feed", get(get_feed))
+        .route("/api/stream", get(stream::stream_events))
+        .route("/api/search", get(search))
+        .route(
+            "/api/moderation/posts/:post_id",
+            delete(moderation::delete_any_post),
+        )
+        .route(
+            "/api/moderation/users/:user_id/suspend",
+            post(moderation::suspend_user),
+        )
+        .route(
+            "/api/moderation/users/:user_id/ban",
+            post(moderation::ban_user),
+        )
+        .route(
+            "/api/moderation/users/:user_id/unblock",
+            post(moderation::unblock_user),
+        )
+        .route(
+            "/api/moderation/users/:user_id/role",
+            put(moderation::change_role),
+        )
+        .route("/api/moderation/actions", get(moderation::get_actions))
+        .route("/api/notifications", get(notifications::get_notifications))
+        .route(
+            "/api/notifications/read",
+            post(notifications::read_notifications),
+        )
+        .route("/api/me", delete(profiles::delete_account))
+        .route("/api/me/password", post(profiles::change_password))
+        .route("/api/me/bookmarks", get(bookmarks::get_bookmarks))
+        .route("/api/me/export", post(exports::create_export))
+        .route("/api/me/export/:export_id", get(exports::get_export))
+        .route(
+            "/api/me/export/:export_id/download",
+            get(exports::download_export),
+        )
+        .route("/api/me/drafts", get(drafts::get_drafts))
+        .route(
+            "/api/me/drafts/:post_id/publish",
+            post(drafts::publish_draft),
+        )
+        .route("/api/me/trash", get(trash::get_trash))
+        .route("/api/me/trash/:post_id/restore", post(trash::restore_post))
+        .route(
+            "/api/me/avatar",
+            put(profiles::upload_avatar)
+                .delete(profiles::delete_avatar)
+                .layer(DefaultBodyLimit::max(profiles::MAX_AVATAR_BODY_SIZE)),
+        )
+        .route("/api/logout", post(logout_user))
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            validate_jwt,
+        ));
 
-    Ok(askama_axum::into_response(&html))
+    Router::new()
+        .merge(secure_router)
+        .merge(auth_router)
+        .merge(router)
+        .nest_service("/static", ServeDir::new("static"))
+        .merge(media_router)
+        .merge(SwaggerUi::new("/api/docs").url("/api/openapi.json", openapi::ApiDoc::openapi()))
+        .fallback(handle_404)
+        .layer(axum::middleware::from_fn(locale::negotiate_locale))
+        .with_state(state)
 }
 
 /// `POST /api/register`
+#[utoipa::path(
+    post,
+    path = "/api/register",
+    tag = "auth",
+    summary = "Register a new user",
+    request_body = RegisterRequest,
+    responses(
+        (status = 200, description = "User registered", body = openapi::schemas::MessageResponse),
+        (status = 409, description = "Username is taken", body = openapi::schemas::ErrorResponse),
+        (status = 429, description = "Too many attempts", body = openapi::schemas::ErrorResponse),
+    ),
+    security(()),
+)]
 async fn register_user(
-    State(pool): State<Repository>,
-    Json(payload): Json<RegisterRequest>,
+    State(pool): State<SharedRepository>,
+    Extension(locale): Extension<Locale>,
+    ValidJson(payload): ValidJson<RegisterRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     info!(
         "Register request received for username: '{}'",
         payload.username
     );
 
-    if payload.is_empty() {
-        warn!("Register request failed: Credentials are empty");
-        return Err(AppError::authenthication("Credentials are empty"));
-    }
-
     let RegisterRequest { username, password } = payload;
 
     info!("Hashing password for username: '{}'", username);
@@ -156,23 +293,39 @@ async fn register_user(
     pool.register_user(&username, password_hash).await?;
 
     Ok(Json(
-        json!({ "result": "ok", "message": "Успешная регистрация!" }),
+        json!({ "result": "ok", "message": locale.text("registered") }),
     ))
 }
 
 /// `POST /api/login`
+#[utoipa::path(
+    post,
+    path = "/api/login",
+    tag = "auth",
+    summary = "Log in with a username and a password",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Bearer tokens or a cookie session", body = openapi::schemas::TokensResponse),
+        (status = 401, description = "Wrong password", body = openapi::schemas::ErrorResponse),
+        (status = 403, description = "User is blocked", body = openapi::schemas::ErrorResponse),
+        (status = 404, description = "User not found", body = openapi::schemas::ErrorResponse),
+        (status = 429, description = "Too many attempts", body = openapi::schemas::ErrorResponse),
+    ),
+    security(()),
+)]
 async fn login_user(
-    State(pool): State<Repository>,
-    Json(payload): Json<LoginRequest>,
+    State(pool): State<SharedRepository>,
+    State(rate_limiter): State<RateLimiter>,
+    jar: CookieJar,
+    ValidJson(payload): ValidJson<LoginRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("Login request received for username: {}", payload.username);
 
-    if payload.is_empty() {
-        warn!("Login request failed: Credentials are empty");
-        return Err(AppError::authenthication("Credentials are empty"));
-    }
-
-    let LoginRequest { username, password } = payload;
+    let LoginRequest {
+        username,
+        password,
+        session: session_mode,
+    } = payload;
 
     info!("Fetching login credentials for username: '{}'", username);
 
@@ -185,80 +338,350 @@ async fn login_user(
 
     if !user.password_hash.verify_password(&password)? {
         warn!("Password verification failed for username: '{}'", username);
-        return Err(AppError::authenthication("Wrong password"));
+        rate_limiter.register_failure(&username);
+        return Err(AppError::authenthication("wrong_password"));
     }
 
+    rate_limiter.reset_failures(&username);
+
     info!(
         "Password verified successfully for username: '{}'",
         username
     );
 
+    if let Some(block) = &user.status.block {
+        warn!("Blocked user tried to log in: '{}'", username);
+        return Err(AppError::user_blocked(block));
+    }
+
     let user_id = user.user_id;
+    let role = user.status.role;
+
+    let token = auth::create_access_token(user_id, role)?;
+
+    let refresh_token = RefreshToken::generate();
+    pool.store_refresh_token(user_id, &refresh_token.token_hash, refresh_token.expires_at)
+        .await?;
+
+    info!("Login successful for username: '{}'", username);
 
-    let token = auth::create_access_token(user_id)?;
+    if session_mode == SessionMode::Cookie {
+        let (jar, csrf_token) = session::start(jar, token, &refresh_token);
+        let session = json!({
+            "csrf_token": csrf_token,
+            "username": username,
+            "user_id": user_id,
+            "role": role,
+        });
+
+        return Ok((jar, Json(json!({ "result": "ok", "session": session }))));
+    }
 
     let jwt = json!({
         "token": token,
+        "refresh_token": refresh_token.token,
         "username": username,
         "user_id": user_id,
+        "role": role,
     });
 
-    info!("Login successful for username: '{}'", username);
+    Ok((jar, Json(json!({ "result": "ok", "jwt": jwt }))))
+}
+
+/// `POST /api/token/refresh`
+#[utoipa::path(
+    post,
+    path = "/api/token/refresh",
+    tag = "auth",
+    summary = "Rotate the refresh token and issue a new access token",
+    request_body(content = Option<RefreshRequest>, description = "Omitted in the cookie session mode"),
+    responses(
+        (status = 200, description = "New tokens", body = openapi::schemas::TokensResponse),
+        (status = 401, description = "Refresh token is unknown, revoked or expired", body = openapi::schemas::ErrorResponse),
+        (status = 403, description = "User is blocked", body = openapi::schemas::ErrorResponse),
+    ),
+    security(()),
+)]
+async fn refresh_token(
+    State(pool): State<SharedRepository>,
+    headers: HeaderMap,
+    jar: CookieJar,
+    ValidJson(payload): ValidJson<RefreshRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Token refresh was requested.");
+
+    let (refresh_token, session_mode) = match payload.refresh_token {
+        Some(refresh_token) => (refresh_token, SessionMode::Bearer),
+        None => {
+            let Some(cookie) = jar.get(session::REFRESH_TOKEN_COOKIE) else {
+                warn!("Token refresh failed: no refresh token provided");
+                return Err(AppError::authenthication("session_expired"));
+            };
+            session::verify_csrf(&Method::POST, &headers, &jar)?;
+            (cookie.value().to_owned(), SessionMode::Cookie)
+        }
+    };
+
+    let new_refresh_token = RefreshToken::generate();
+    let rotation = pool
+        .rotate_refresh_token(
+            &auth::hash_refresh_token(&refresh_token),
+            &new_refresh_token.token_hash,
+            new_refresh_token.expires_at,
+            chrono::Utc::now().naive_utc(),
+        )
+        .await?;
+
+    let user_id = match rotation {
+        RefreshTokenRotation::Rotated { user_id } => user_id,
+        RefreshTokenRotation::NotFound | RefreshTokenRotation::Reused => {
+            warn!("Token refresh failed: refresh token is unknown or revoked");
+            return Err(AppError::authenthication("session_terminated"));
+        }
+        RefreshTokenRotation::Expired => {
+            warn!("Token refresh failed: refresh token expired");
+            return Err(AppError::authenthication("session_expired"));
+        }
+    };
+
+    let Some(status) = pool
+        .get_user_status(user_id, chrono::Utc::now().naive_utc())
+        .await?
+    else {
+        return Err(AppError::user_not_found());
+    };
+
+    if let Some(block) = &status.block {
+        warn!("Token refresh failed: user (id = {user_id}) is blocked");
+        return Err(AppError::user_blocked(block));
+    }
+
+    let token = auth::create_access_token(user_id, status.role)?;
+
+    info!("Tokens refreshed for user (id = {user_id})");
+
+    if session_mode == SessionMode::Cookie {
+        let (jar, csrf_token) = session::start(jar, token, &new_refresh_token);
+        let session = json!({
+            "csrf_token": csrf_token,
+            "user_id": user_id,
+            "role": status.role,
+        });
+
+        return Ok((jar, Json(json!({ "result": "ok", "session": session }))));
+    }
+
+    let jwt = json!({
+        "token": token,
+        "refresh_token": new_refresh_token.token,
+        "user_id": user_id,
+        "role": status.role,
+    });
 
-    Ok(Json(json!({ "result": "ok", "jwt": jwt })))
+    Ok((jar, Json(json!({ "result": "ok", "jwt": jwt }))))
+}
+
+/// `POST /api/logout`
+#[utoipa::path(
+    post,
+    path = "/api/logout",
+    tag = "auth",
+    summary = "Revoke the refresh token and end the session",
+    request_body(content = Option<LogoutRequest>, description = "Omitted in the cookie session mode"),
+    responses(
+        (status = 200, description = "Logged out", body = openapi::schemas::MessageResponse),
+    ),
+)]
+async fn logout_user(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
+    jar: CookieJar,
+    ValidJson(payload): ValidJson<LogoutRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Logout was requested.");
+
+    let Claims {
+        sub: user_id,
+        exp,
+        jti,
+        ..
+    } = claims;
+    let refresh_token_hash = payload
+        .refresh_token
+        .as_deref()
+        .or_else(|| jar.get(session::REFRESH_TOKEN_COOKIE).map(Cookie::value))
+        .map(auth::hash_refresh_token);
+
+    pool.revoke_session(
+        user_id,
+        &jti,
+        auth::expiry_to_datetime(exp)?,
+        refresh_token_hash.as_deref(),
+        chrono::Utc::now().naive_utc(),
+    )
+    .await?;
+
+    info!("User (id = {user_id}) logged out");
+
+    Ok((
+        session::end(jar),
+        Json(json!({ "result": "ok", "message": locale.text("logged_out") })),
+    ))
 }
 
-/// `GET /api/posts`
+/// `GET /api/posts?limit=&cursor=&sort=`
+#[utoipa::path(
+    get,
+    path = "/api/posts",
+    tag = "posts",
+    summary = "List posts of all users",
+    params(PostsQuery),
+    responses(
+        (status = 200, description = "Page of posts", body = openapi::schemas::PostsResponse),
+        (status = 400, description = "Invalid cursor", body = openapi::schemas::ErrorResponse),
+    ),
+)]
 async fn get_posts(
-    State(pool): State<Repository>,
-    _: Extension<Claims>,
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<PostsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("List all posts was requested.");
-    let posts = pool.get_posts().await?;
 
-    Ok(Json(json!({ "result": "ok", "posts": posts })))
+    let cursor = decode_cursor(&query)?;
+    let PostsPage {
+        mut posts,
+        next_cursor,
+    } = pool
+        .get_posts(query.sort, cursor.as_ref(), query.limit())
+        .await?;
+    bookmarks::mark_bookmarked(&pool, claims.sub, &mut posts).await?;
+
+    Ok(Json(json!({
+        "result": "ok",
+        "posts": posts,
+        "next_cursor": next_cursor.as_ref().map(PostCursor::encode),
+    })))
+}
+
+fn decode_cursor(query: &PostsQuery) -> Result<Option<PostCursor>, AppError> {
+    let Some(cursor) = &query.cursor else {
+        return Ok(None);
+    };
+
+    match PostCursor::decode(cursor) {
+        Some(cursor) if cursor.sort == query.sort => Ok(Some(cursor)),
+        _ => {
+            warn!("Invalid cursor was provided: '{cursor}'");
+            Err(AppError::bad_request("invalid_cursor"))
+        }
+    }
 }
 
 /// `POST /api/posts`
+#[utoipa::path(
+    post,
+    path = "/api/posts",
+    tag = "posts",
+    summary = "Create a post",
+    request_body = CreatePostRequest,
+    responses(
+        (status = 200, description = "Post created", body = openapi::schemas::PostIdResponse),
+        (status = 404, description = "Quoted post not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
 async fn create_post(
-    State(pool): State<Repository>,
+    State(pool): State<SharedRepository>,
     Extension(claims): Extension<Claims>,
-    Json(payload): Json<CreatePostRequest>,
+    ValidJson(payload): ValidJson<CreatePostRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("Create post was requested.");
 
     let Claims { sub: user_id, .. } = claims;
-    let CreatePostRequest { title, content } = payload;
-
-    let post_id = pool.create_post(user_id, &title, &content).await?;
+    let CreatePostRequest {
+        title,
+        content,
+        quote_of_id,
+        draft,
+        publish_at,
+    } = payload;
+    let publication = drafts::publication(draft, publish_at);
+
+    let post_id = match quote_of_id {
+        Some(quote_of_id) => {
+            let Some(post_id) = pool
+                .create_quote(user_id, quote_of_id, &title, &content, publication)
+                .await?
+            else {
+                return Err(AppError::post_not_found());
+            };
+            post_id
+        }
+        None => {
+            pool.create_post(user_id, &title, &content, publication)
+                .await?
+        }
+    };
 
     Ok(Json(json!({ "result": "ok", "post_id": post_id })))
 }
 
 /// `GET /api/posts/{post_id}`
+#[utoipa::path(
+    get,
+    path = "/api/posts/{post_id}",
+    tag = "posts",
+    summary = "Get a post",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    responses(
+        (status = 200, description = "The post", body = openapi::schemas::PostResponse),
+        (status = 404, description = "Post not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
 async fn get_post(
-    State(pool): State<Repository>,
-    Extension(_): Extension<Claims>,
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
     Path(post_id): Path<i32>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("Get post was requested.");
 
-    let Some(post) = pool.get_post(post_id).await? else {
+    let Some(mut post) = pool.get_post(post_id).await? else {
         return Err(AppError::post_not_found());
     };
+    bookmarks::mark_bookmarked(&pool, claims.sub, [&mut post]).await?;
 
     Ok(Json(json!({ "result": "ok", "post": post })))
 }
 
 /// `POST /api/posts/{post_id}/likes`
+#[utoipa::path(
+    post,
+    path = "/api/posts/{post_id}/likes",
+    tag = "posts",
+    summary = "Like a post or take the like back",
+    description = "Toggles the `like` reaction, kept for the clients written before reactions.",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    responses(
+        (status = 200, description = "Like of the current user and the new count", body = openapi::schemas::LikeResponse),
+        (status = 404, description = "Post not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
 async fn like_post(
-    State(pool): State<Repository>,
+    State(pool): State<SharedRepository>,
     Extension(claims): Extension<Claims>,
     Path(post_id): Path<i32>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("Add/remove like was requested.");
 
     let Claims { sub: user_id, .. } = claims;
+
+    // reposts are liked through the original post
+    let post = pool.get_post(post_id).await?;
+    if post.is_none_or(|post| post.repost_of_id.is_some()) {
+        return Err(AppError::post_not_found());
+    }
+
     let like = pool.like_post(user_id, post_id).await?;
 
     let likes_count = pool.get_like_count(post_id).await?;
@@ -268,42 +691,481 @@ async fn like_post(
     ))
 }
 
-/// `POST /api/posts/{post_id}`
+/// `PATCH /api/posts/{post_id}`
+#[utoipa::path(
+    patch,
+    path = "/api/posts/{post_id}",
+    tag = "posts",
+    summary = "Edit a post of the current user",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    request_body = EditPostRequest,
+    responses(
+        (status = 200, description = "Edited post", body = openapi::schemas::PostResponse),
+        (status = 400, description = "Nothing to change", body = openapi::schemas::ErrorResponse),
+        (status = 403, description = "Post belongs to another user", body = openapi::schemas::ErrorResponse),
+        (status = 404, description = "Post not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
+async fn edit_post(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path(post_id): Path<i32>,
+    ValidJson(payload): ValidJson<EditPostRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Edit post was requested.");
+
+    if payload.is_empty() {
+        warn!("Edit post request failed: nothing to change");
+        return Err(AppError::bad_request("nothing_to_change"));
+    }
+
+    let Claims { sub: user_id, .. } = claims;
+    let EditPostRequest { title, content } = payload;
+
+    match pool
+        .edit_post(post_id, user_id, title.as_deref(), content.as_deref())
+        .await?
+    {
+        PostEditResult::Edited | PostEditResult::Unchanged => {}
+        PostEditResult::NotFound => return Err(AppError::post_not_found()),
+        PostEditResult::NotOwned => return Err(AppError::forbidden("post_edit_forbidden")),
+    }
+
+    // drafts are edited too, but shown to their author only
+    let post = match pool.get_post(post_id).await? {
+        Some(post) => Some(post),
+        None => pool.get_draft(post_id, user_id).await?,
+    };
+    let Some(mut post) = post else {
+        return Err(AppError::post_not_found());
+    };
+    bookmarks::mark_bookmarked(&pool, user_id, [&mut post]).await?;
+
+    Ok(Json(json!({ "result": "ok", "post": post })))
+}
+
+/// `GET /api/posts/{post_id}/revisions`
+#[utoipa::path(
+    get,
+    path = "/api/posts/{post_id}/revisions",
+    tag = "posts",
+    summary = "List previous versions of a post",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    responses(
+        (status = 200, description = "Revisions, newest first", body = openapi::schemas::RevisionsResponse),
+        (status = 404, description = "Post not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
+async fn get_post_revisions(
+    State(pool): State<SharedRepository>,
+    _: Extension<Claims>,
+    Path(post_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Revisions of post with id = {post_id} were requested.");
+
+    if pool.get_post(post_id).await?.is_none() {
+        return Err(AppError::post_not_found());
+    }
+
+    let revisions = pool.get_post_revisions(post_id).await?;
+
+    Ok(Json(json!({ "result": "ok", "revisions": revisions })))
+}
+
+/// `DELETE /api/posts/{post_id}`
+#[utoipa::path(
+    delete,
+    path = "/api/posts/{post_id}",
+    tag = "posts",
+    summary = "Move a post of the current user to the trash",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    responses(
+        (status = 200, description = "Post moved to the trash", body = openapi::schemas::MessageResponse),
+        (status = 403, description = "Post belongs to another user", body = openapi::schemas::ErrorResponse),
+        (status = 404, description = "Post not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
 async fn delete_post(
-    State(pool): State<Repository>,
+    State(pool): State<SharedRepository>,
     Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
     Path(post_id): Path<i32>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("Delete post was requested.");
 
     let Claims { sub: user_id, .. } = claims;
 
+    // attachment files stay until the post is purged from the trash
     match pool.delete_post(post_id, user_id).await? {
         PostDeleteResult::Deleted => Ok(Json(json!({
             "result": "ok",
-            "message": "Post deleted successfully."
+            "message": locale.text("post_trashed")
         }))),
         PostDeleteResult::NotFound => Err(AppError::post_not_found()),
-        PostDeleteResult::NotOwned => Err(AppError::forbidden(
-            "You do not have permission to delete this post.",
-        )),
+        PostDeleteResult::NotOwned => Err(AppError::forbidden("post_delete_forbidden")),
     }
 }
 
-async fn get_user_posts(
-    State(pool): State<Repository>,
+/// `POST /api/posts/{post_id}/comments`
+#[utoipa::path(
+    post,
+    path = "/api/posts/{post_id}/comments",
+    tag = "comments",
+    summary = "Comment a post or reply to a comment",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    request_body = CreateCommentRequest,
+    responses(
+        (status = 200, description = "Comment created", body = openapi::schemas::CommentIdResponse),
+        (status = 404, description = "Post or parent comment not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
+async fn create_comment(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path(post_id): Path<i32>,
+    ValidJson(payload): ValidJson<CreateCommentRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Create comment was requested.");
+
+    let Claims { sub: user_id, .. } = claims;
+    let CreateCommentRequest {
+        content,
+        parent_comment_id,
+    } = payload;
+
+    match pool
+        .create_comment(post_id, user_id, parent_comment_id, &content)
+        .await?
+    {
+        CommentCreateResult::Created(comment_id) => {
+            Ok(Json(json!({ "result": "ok", "comment_id": comment_id })))
+        }
+        CommentCreateResult::PostNotFound => Err(AppError::post_not_found()),
+        CommentCreateResult::ParentNotFound => Err(AppError::comment_not_found()),
+    }
+}
+
+/// `GET /api/posts/{post_id}/comments`
+#[utoipa::path(
+    get,
+    path = "/api/posts/{post_id}/comments",
+    tag = "comments",
+    summary = "Get comment threads of a post",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    responses(
+        (status = 200, description = "Top-level comments with nested replies", body = openapi::schemas::CommentsResponse),
+        (status = 404, description = "Post not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
+async fn get_comments(
+    State(pool): State<SharedRepository>,
     _: Extension<Claims>,
+    Path(post_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Comments of post with id = {post_id} were requested.");
+
+    if pool.get_post(post_id).await?.is_none() {
+        return Err(AppError::post_not_found());
+    }
+
+    let comments = pool.get_comments(post_id).await?;
+    let comments = CommentThread::from_comments(comments);
+
+    Ok(Json(json!({ "result": "ok", "comments": comments })))
+}
+
+/// `DELETE /api/posts/{post_id}/comments/{comment_id}`
+#[utoipa::path(
+    delete,
+    path = "/api/posts/{post_id}/comments/{comment_id}",
+    tag = "comments",
+    summary = "Delete a comment of the current user",
+    params(
+        ("post_id" = i32, Path, description = "Id of the post"),
+        ("comment_id" = i32, Path, description = "Id of the comment"),
+    ),
+    responses(
+        (status = 200, description = "Comment deleted", body = openapi::schemas::MessageResponse),
+        (status = 403, description = "Comment belongs to another user", body = openapi::schemas::ErrorResponse),
+        (status = 404, description = "Comment not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
+async fn delete_comment(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
+    Path((post_id, comment_id)): Path<(i32, i32)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Delete comment was requested.");
+
+    let Claims { sub: user_id, .. } = claims;
+
+    match pool.delete_comment(post_id, comment_id, user_id).await? {
+        CommentDeleteResult::Deleted => Ok(Json(json!({
+            "result": "ok",
+            "message": locale.text("comment_deleted")
+        }))),
+        CommentDeleteResult::NotFound => Err(AppError::comment_not_found()),
+        CommentDeleteResult::NotOwned => Err(AppError::forbidden("comment_delete_forbidden")),
+    }
+}
+
+/// `GET /api/users/{user_id}?limit=&cursor=&sort=`
+#[utoipa::path(
+    get,
+    path = "/api/users/{user_id}",
+    tag = "users",
+    summary = "Get a user with their posts",
+    params(("user_id" = i32, Path, description = "Id of the user"), PostsQuery),
+    responses(
+        (status = 200, description = "User and a page of their posts", body = openapi::schemas::UserPostsResponse),
+        (status = 400, description = "Invalid cursor", body = openapi::schemas::ErrorResponse),
+        (status = 404, description = "User not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
+async fn get_user_posts(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
     Path(user_id): Path<i32>,
+    Query(query): Query<PostsQuery>,
 ) -> Result<impl IntoResponse, AppError> {
     info!("User posts list was requested.");
 
     let Some(username) = pool.get_username_by_user_id(user_id).await? else {
         return Err(AppError::user_not_found());
     };
-    let posts = pool.get_user_posts(user_id).await?;
+
+    let cursor = decode_cursor(&query)?;
+    let PostsPage {
+        mut posts,
+        next_cursor,
+    } = pool
+        .get_user_posts(user_id, query.sort, cursor.as_ref(), query.limit())
+        .await?;
+    bookmarks::mark_bookmarked(&pool, claims.sub, &mut posts).await?;
+
+    let follow_counts = pool.get_follow_counts(user_id, Some(claims.sub)).await?;
+
+    Ok(Json(json!({
+        "result": "ok",
+        "username": username,
+        "followers_count": follow_counts.followers_count,
+        "following_count": follow_counts.following_count,
+        "is_followed": follow_counts.is_followed,
+        "posts": posts,
+        "next_cursor": next_cursor.as_ref().map(PostCursor::encode),
+    })))
+}
+
+/// `POST /api/users/{user_id}/follow`
+#[utoipa::path(
+    post,
+    path = "/api/users/{user_id}/follow",
+    tag = "users",
+    summary = "Follow a user",
+    params(("user_id" = i32, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "New follower count", body = openapi::schemas::FollowResponse),
+        (status = 400, description = "Users cannot follow themselves", body = openapi::schemas::ErrorResponse),
+        (status = 404, description = "User not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
+async fn follow_user(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path(user_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Follow user with id = {user_id} was requested.");
+
+    let Claims {
+        sub: follower_id, ..
+    } = claims;
+
+    if follower_id == user_id {
+        warn!("User (id = {user_id}) tried to follow themselves");
+        return Err(AppError::bad_request("self_follow"));
+    }
+
+    if pool.get_username_by_user_id(user_id).await?.is_none() {
+        return Err(AppError::user_not_found());
+    }
+
+    pool.follow_user(follower_id, user_id).await?;
+
+    let follow_counts = pool.get_follow_counts(user_id, Some(follower_id)).await?;
+
+    Ok(Json(json!({
+        "result": "ok",
+        "followers_count": follow_counts.followers_count,
+        "is_followed": follow_counts.is_followed,
+    })))
+}
+
+/// `DELETE /api/users/{user_id}/follow`
+#[utoipa::path(
+    delete,
+    path = "/api/users/{user_id}/follow",
+    tag = "users",
+    summary = "Unfollow a user",
+    params(("user_id" = i32, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "New follower count", body = openapi::schemas::FollowResponse),
+        (status = 404, description = "User not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
+async fn unfollow_user(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path(user_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Unfollow user with id = {user_id} was requested.");
+
+    let Claims {
+        sub: follower_id, ..
+    } = claims;
+
+    if pool.get_username_by_user_id(user_id).await?.is_none() {
+        return Err(AppError::user_not_found());
+    }
+
+    pool.unfollow_user(follower_id, user_id).await?;
+
+    let follow_counts = pool.get_follow_counts(user_id, Some(follower_id)).await?;
+
+    Ok(Json(json!({
+        "result": "ok",
+        "followers_count": follow_counts.followers_count,
+        "is_followed": follow_counts.is_followed,
+    })))
+}
+
+/// `GET /api/users/{user_id}/followers`
+#[utoipa::path(
+    get,
+    path = "/api/users/{user_id}/followers",
+    tag = "users",
+    summary = "List followers of a user",
+    params(("user_id" = i32, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "Followers", body = openapi::schemas::UsersResponse),
+        (status = 404, description = "User not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
+async fn get_followers(
+    State(pool): State<SharedRepository>,
+    _: Extension<Claims>,
+    Path(user_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Followers of user with id = {user_id} were requested.");
+
+    if pool.get_username_by_user_id(user_id).await?.is_none() {
+        return Err(AppError::user_not_found());
+    }
+
+    let users = pool.get_followers(user_id).await?;
+
+    Ok(Json(json!({ "result": "ok", "users": users })))
+}
+
+/// `GET /api/users/{user_id}/following`
+#[utoipa::path(
+    get,
+    path = "/api/users/{user_id}/following",
+    tag = "users",
+    summary = "List users a user follows",
+    params(("user_id" = i32, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "Followed users", body = openapi::schemas::UsersResponse),
+        (status = 404, description = "User not found", body = openapi::schemas::ErrorResponse),
+    ),
+)]
+async fn get_following(
+    State(pool): State<SharedRepository>,
+    _: Extension<Claims>,
+    Path(user_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Users followed by user with id = {user_id} were requested.");
+
+    if pool.get_username_by_user_id(user_id).await?.is_none() {
+        return Err(AppError::user_not_found());
+    }
+
+    let users = pool.get_following(user_id).await?;
+
+    Ok(Json(json!({ "result": "ok", "users": users })))
+}
+
+/// `GET /api/feed?limit=&cursor=&sort=`
+#[utoipa::path(
+    get,
+    path = "/api/feed",
+    tag = "feed",
+    summary = "List posts of followed users",
+    params(PostsQuery),
+    responses(
+        (status = 200, description = "Page of posts", body = openapi::schemas::PostsResponse),
+        (status = 400, description = "Invalid cursor", body = openapi::schemas::ErrorResponse),
+    ),
+)]
+async fn get_feed(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<PostsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Feed was requested.");
+
+    let cursor = decode_cursor(&query)?;
+    let PostsPage {
+        mut posts,
+        next_cursor,
+    } = pool
+        .get_feed(claims.sub, query.sort, cursor.as_ref(), query.limit())
+        .await?;
+    bookmarks::mark_bookmarked(&pool, claims.sub, &mut posts).await?;
+
+    Ok(Json(json!({
+        "result": "ok",
+        "posts": posts,
+        "next_cursor": next_cursor.as_ref().map(PostCursor::encode),
+    })))
+}
+
+/// `GET /api/search?q=&limit=`
+#[utoipa::path(
+    get,
+    path = "/api/search",
+    tag = "feed",
+    summary = "Search posts and users",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Matching posts and users", body = openapi::schemas::SearchResponse),
+        (status = 400, description = "Search query is empty", body = openapi::schemas::ErrorResponse),
+    ),
+)]
+async fn search(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<SearchQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Search was requested.");
+
+    let text = query.q.trim();
+    if text.is_empty() {
+        warn!("Search request failed: query is empty");
+        return Err(AppError::bad_request("empty_search_query"));
+    }
+
+    let mut posts = pool.search_posts(text, query.limit()).await?;
+    bookmarks::mark_bookmarked(
+        &pool,
+        claims.sub,
+        posts.iter_mut().map(|found| &mut found.post),
+    )
+    .await?;
+    let users = pool.search_users(text, query.limit()).await?;
 
     Ok(Json(
-        json!({ "result": "ok", "username": username, "posts": posts }),
+        json!({ "result": "ok", "posts": posts, "users": users }),
     ))
 }
 
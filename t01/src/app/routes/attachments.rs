@@ -0,0 +1,138 @@
+use super::{AppError, Claims, SharedRepository};
+use crate::{
+    i18n::Message,
+    repository::{AttachmentCreateResult, DatabaseAttachment, MAX_ATTACHMENTS_PER_POST},
+    storage::{MediaStorage, StoredImage, MAX_IMAGE_SIZE},
+};
+use axum::{
+    extract::{Multipart, Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use log::{info, warn};
+use serde_json::json;
+
+/// Whole upload request: every image of a post at once plus the multipart framing.
+pub(super) const MAX_UPLOAD_BODY_SIZE: usize =
+    MAX_IMAGE_SIZE * MAX_ATTACHMENTS_PER_POST as usize + 64 * 1024;
+
+/// Multipart field with an image, may be repeated.
+const IMAGES_FIELD: &str = "images";
+
+/// `POST /api/posts/{post_id}/attachments`
+#[utoipa::path(
+    post,
+    path = "/api/posts/{post_id}/attachments",
+    tag = "posts",
+    summary = "Attach images to a post of the current user",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    request_body(content = super::openapi::schemas::ImagesUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "All attachments of the post", body = super::openapi::schemas::AttachmentsResponse),
+        (status = 400, description = "No images or an unsupported format", body = super::openapi::schemas::ErrorResponse),
+        (status = 403, description = "Post belongs to another user", body = super::openapi::schemas::ErrorResponse),
+        (status = 404, description = "Post not found", body = super::openapi::schemas::ErrorResponse),
+        (status = 413, description = "Image or the whole upload is too large", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn upload_attachments(
+    State(pool): State<SharedRepository>,
+    State(media): State<MediaStorage>,
+    Extension(claims): Extension<Claims>,
+    Path(post_id): Path<i32>,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Upload of attachments to post with id = {post_id} was requested.");
+
+    let Claims { sub: user_id, .. } = claims;
+
+    let mut images = Vec::new();
+    let stored = loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => break Ok(()),
+            Err(err) => {
+                warn!("Upload request failed: invalid multipart body: {err}");
+                break Err(AppError::bad_request("invalid_multipart"));
+            }
+        };
+
+        if field.name() != Some(IMAGES_FIELD) {
+            continue;
+        }
+
+        if images.len() as i64 >= MAX_ATTACHMENTS_PER_POST {
+            break Err(too_many_attachments());
+        }
+
+        let bytes = match field.bytes().await {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                warn!("Upload request failed: image can not be read: {err}");
+                break Err(AppError::bad_request("unreadable_image"));
+            }
+        };
+
+        match media.store_image(bytes.to_vec()).await {
+            Ok(image) => images.push(image),
+            Err(err) => break Err(err),
+        }
+    };
+
+    // files are written before the rows, so they are removed on any failure
+    if let Err(err) = stored {
+        remove_stored(&media, &images).await;
+        return Err(err);
+    }
+
+    if images.is_empty() {
+        warn!("Upload request failed: no images");
+        return Err(AppError::bad_request("no_images"));
+    }
+
+    let result = pool.create_attachments(post_id, user_id, &images).await;
+    let attachments = match result {
+        Ok(AttachmentCreateResult::Created(attachments)) => attachments,
+        Ok(AttachmentCreateResult::PostNotFound) => {
+            remove_stored(&media, &images).await;
+            return Err(AppError::post_not_found());
+        }
+        Ok(AttachmentCreateResult::NotOwned) => {
+            remove_stored(&media, &images).await;
+            return Err(AppError::forbidden("attachment_forbidden"));
+        }
+        Ok(AttachmentCreateResult::TooMany) => {
+            remove_stored(&media, &images).await;
+            return Err(too_many_attachments());
+        }
+        Err(err) => {
+            remove_stored(&media, &images).await;
+            return Err(err.into());
+        }
+    };
+
+    Ok(Json(json!({ "result": "ok", "attachments": attachments })))
+}
+
+/// Removes files of attachments whose rows were deleted with their post.
+pub(super) async fn remove_files(media: &MediaStorage, attachments: Vec<DatabaseAttachment>) {
+    let file_names = attachments
+        .into_iter()
+        .flat_map(|attachment| [attachment.file_name, attachment.thumbnail_name])
+        .collect::<Vec<_>>();
+
+    media.remove(&file_names).await;
+}
+
+async fn remove_stored(media: &MediaStorage, images: &[StoredImage]) {
+    let file_names = images
+        .iter()
+        .flat_map(|image| [image.file_name.clone(), image.thumbnail_name.clone()])
+        .collect::<Vec<_>>();
+
+    media.remove(&file_names).await;
+}
+
+fn too_many_attachments() -> AppError {
+    AppError::bad_request(Message::new("too_many_attachments").arg("max", MAX_ATTACHMENTS_PER_POST))
+}
@@ -1,9 +1,22 @@
-use super::{AppError, Claims};
-use axum::{extract::Request, middleware::Next, response::Response};
+use super::{session, AppError, Claims, SharedRepository};
+use crate::{model::Role, repository::AccessCheck};
+use axum::{
+    extract::{Request, State},
+    middleware::Next,
+    response::Response,
+};
+use axum_extra::extract::cookie::CookieJar;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::NaiveDateTime;
 use jsonwebtoken::{encode, DecodingKey, EncodingKey};
 use log::{error, info, warn};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
 use std::sync::LazyLock;
 
+pub(crate) const ACCESS_TOKEN_LIFETIME: chrono::TimeDelta = chrono::TimeDelta::minutes(15);
+const REFRESH_TOKEN_LIFETIME: chrono::TimeDelta = chrono::TimeDelta::days(30);
+
 static KEYS: LazyLock<Keys> = LazyLock::new(|| {
     let secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
     Keys::new(secret.as_bytes())
@@ -23,22 +36,29 @@ impl Keys {
     }
 }
 
-pub(crate) async fn validate_jwt(mut req: Request, next: Next) -> Result<Response, AppError> {
+pub(crate) async fn validate_jwt(
+    State(pool): State<SharedRepository>,
+    mut req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
     info!("Starting JWT validation for request.");
 
     let jwt_token = req
         .headers()
         .get(http::header::AUTHORIZATION)
         .and_then(|header| header.to_str().ok());
+    let jar = CookieJar::from_headers(req.headers());
 
     let jwt_token = if let Some(jwt_token) = jwt_token {
         info!("JWT token found in the Authorization header.");
         jwt_token.replace("Bearer ", "")
+    } else if let Some(cookie) = jar.get(session::ACCESS_TOKEN_COOKIE) {
+        info!("JWT token found in the session cookie.");
+        session::verify_csrf(req.method(), req.headers(), &jar)?;
+        cookie.value().to_owned()
     } else {
-        warn!("No JWT token found in the Authorization header.");
-        return Err(AppError::authenthication(
-            "You cannot access or interact with the content of this page. Please log in to continue.",
-        ));
+        warn!("No JWT token found in the Authorization header or cookies.");
+        return Err(AppError::authenthication("login_required"));
     };
 
     info!("Decoding and validating JWT token.");
@@ -48,23 +68,55 @@ pub(crate) async fn validate_jwt(mut req: Request, next: Next) -> Result<Respons
         &jsonwebtoken::Validation::default(),
     );
 
-    match token_payload {
-        Ok(token_payload) => {
-            info!("JWT token successfully validated.");
-            req.extensions_mut().insert(token_payload.claims);
-            Ok(next.run(req).await)
-        }
+    let mut claims = match token_payload {
+        Ok(token_payload) => token_payload.claims,
         Err(err) => {
             error!("JWT token validation failed: {:?}", err);
-            Err(AppError::jwt_token(err))
+            return Err(AppError::jwt_token(err));
+        }
+    };
+
+    match pool
+        .check_access(claims.sub, &claims.jti, chrono::Utc::now().naive_utc())
+        .await?
+    {
+        // a role changed after the login takes effect at once
+        AccessCheck::Allowed { role } => claims.role = role,
+        AccessCheck::Revoked => {
+            warn!("JWT token with jti = '{}' was revoked", claims.jti);
+            return Err(AppError::authenthication("session_terminated"));
+        }
+        AccessCheck::Blocked(block) => {
+            warn!("Blocked user (id = {}) tried to access", claims.sub);
+            return Err(AppError::user_blocked(&block));
         }
     }
+
+    info!("JWT token successfully validated.");
+    req.extensions_mut().insert(claims);
+    Ok(next.run(req).await)
+}
+
+/// Rejects users whose role is lower than `role`.
+pub(crate) fn require_role(claims: &Claims, role: Role) -> Result<(), AppError> {
+    if claims.role < role {
+        warn!(
+            "User (id = {}) with role '{}' tried to act as '{}'",
+            claims.sub,
+            claims.role.as_str(),
+            role.as_str()
+        );
+        return Err(AppError::forbidden("action_forbidden"));
+    }
+
+    Ok(())
 }
 
-pub(crate) fn create_access_token(user_id: i32) -> Result<String, AppError> {
+pub(crate) fn create_access_token(user_id: i32, role: Role) -> Result<String, AppError> {
     info!("Creating access token for user_id: {}", user_id);
 
-    let expires = if let Some(expiry) = chrono::Utc::now().checked_add_days(chrono::Days::new(1)) {
+    let expires = if let Some(expiry) = chrono::Utc::now().checked_add_signed(ACCESS_TOKEN_LIFETIME)
+    {
         info!(
             "Token expiry calculated successfully for user_id: '{}'",
             user_id
@@ -83,6 +135,8 @@ pub(crate) fn create_access_token(user_id: i32) -> Result<String, AppError> {
     let claims = Claims {
         sub: user_id,
         exp: expires,
+        jti: ulid::Ulid::new().to_string(),
+        role,
     };
 
     info!("JWT claims created for user_id: '{}'", user_id);
@@ -106,3 +160,37 @@ pub(crate) fn create_access_token(user_id: i32) -> Result<String, AppError> {
 
     Ok(token)
 }
+
+/// Opaque refresh token. Only `token_hash` is stored in the database.
+pub(crate) struct RefreshToken {
+    pub(crate) token: String,
+    pub(crate) token_hash: String,
+    pub(crate) expires_at: NaiveDateTime,
+}
+
+impl RefreshToken {
+    pub(crate) fn generate() -> Self {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token = URL_SAFE_NO_PAD.encode(bytes);
+
+        Self {
+            token_hash: hash_refresh_token(&token),
+            token,
+            expires_at: chrono::Utc::now().naive_utc() + REFRESH_TOKEN_LIFETIME,
+        }
+    }
+}
+
+pub(crate) fn hash_refresh_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Converts `exp` claim into the timestamp stored alongside revoked tokens.
+pub(crate) fn expiry_to_datetime(exp: usize) -> Result<NaiveDateTime, AppError> {
+    i64::try_from(exp)
+        .ok()
+        .and_then(|exp| chrono::DateTime::from_timestamp(exp, 0))
+        .map(|expiry| expiry.naive_utc())
+        .ok_or_else(|| AppError::other(anyhow::anyhow!("Invalid token expiry: {exp}")))
+}
@@ -0,0 +1,139 @@
+use super::{decode_cursor, AppError, Claims, SharedRepository};
+use crate::{
+    model::{PostCursor, PostsQuery},
+    repository::{DatabasePost, PostsPage},
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use log::info;
+use serde_json::json;
+use std::collections::HashSet;
+
+/// `POST /api/posts/{post_id}/bookmark`
+#[utoipa::path(
+    post,
+    path = "/api/posts/{post_id}/bookmark",
+    tag = "posts",
+    summary = "Bookmark a post, bookmarking twice changes nothing",
+    description = "Bookmarking a repost saves the original post.",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    responses(
+        (status = 200, description = "Post bookmarked", body = super::openapi::schemas::BookmarkResponse),
+        (status = 404, description = "Post not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn add_bookmark(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path(post_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Bookmark of post with id = {post_id} was requested.");
+
+    if !pool.add_bookmark(claims.sub, post_id).await? {
+        return Err(AppError::post_not_found());
+    }
+
+    Ok(Json(json!({ "result": "ok", "bookmarked": true })))
+}
+
+/// `DELETE /api/posts/{post_id}/bookmark`
+#[utoipa::path(
+    delete,
+    path = "/api/posts/{post_id}/bookmark",
+    tag = "posts",
+    summary = "Remove the bookmark of a post",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    responses(
+        (status = 200, description = "Bookmark removed", body = super::openapi::schemas::BookmarkResponse),
+        (status = 404, description = "Post not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn remove_bookmark(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path(post_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Removal of bookmark of post with id = {post_id} was requested.");
+
+    if !pool.remove_bookmark(claims.sub, post_id).await? {
+        return Err(AppError::post_not_found());
+    }
+
+    Ok(Json(json!({ "result": "ok", "bookmarked": false })))
+}
+
+/// `GET /api/me/bookmarks?limit=&cursor=&sort=`
+#[utoipa::path(
+    get,
+    path = "/api/me/bookmarks",
+    tag = "posts",
+    summary = "List posts bookmarked by the current user",
+    params(PostsQuery),
+    responses(
+        (status = 200, description = "Page of posts", body = super::openapi::schemas::PostsResponse),
+        (status = 400, description = "Invalid cursor", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn get_bookmarks(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<PostsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Bookmarks were requested.");
+
+    let cursor = decode_cursor(&query)?;
+    let PostsPage {
+        mut posts,
+        next_cursor,
+    } = pool
+        .get_bookmarks(claims.sub, query.sort, cursor.as_ref(), query.limit())
+        .await?;
+    mark_bookmarked(&pool, claims.sub, &mut posts).await?;
+
+    Ok(Json(json!({
+        "result": "ok",
+        "posts": posts,
+        "next_cursor": next_cursor.as_ref().map(PostCursor::encode),
+    })))
+}
+
+/// Sets `bookmarked` of the posts, and of the posts they repost or quote, for `user_id`.
+pub(super) async fn mark_bookmarked<'a>(
+    pool: &SharedRepository,
+    user_id: i32,
+    posts: impl IntoIterator<Item = &'a mut DatabasePost>,
+) -> Result<(), AppError> {
+    let mut posts = posts.into_iter().collect::<Vec<_>>();
+
+    let mut post_ids = Vec::new();
+    for post in &posts {
+        collect_ids(post, &mut post_ids);
+    }
+    if post_ids.is_empty() {
+        return Ok(());
+    }
+
+    let bookmarked = pool.get_bookmarked(user_id, &post_ids).await?;
+    for post in &mut posts {
+        mark(post, &bookmarked);
+    }
+
+    Ok(())
+}
+
+fn collect_ids(post: &DatabasePost, post_ids: &mut Vec<i32>) {
+    post_ids.push(post.post_id);
+    if let Some(original) = &post.original {
+        collect_ids(original, post_ids);
+    }
+}
+
+fn mark(post: &mut DatabasePost, bookmarked: &HashSet<i32>) {
+    post.bookmarked = bookmarked.contains(&post.post_id);
+    if let Some(original) = &mut post.original {
+        mark(original, bookmarked);
+    }
+}
@@ -0,0 +1,99 @@
+use super::{validation::ValidJson, AppError, Claims, SharedRepository};
+use crate::{model::PublishDraftRequest, repository::Publication};
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use serde_json::json;
+use std::time::Duration;
+
+/// How often scheduled posts are checked, they go out at most this late.
+const PUBLISH_INTERVAL: Duration = Duration::from_secs(30);
+
+/// `GET /api/me/drafts`
+#[utoipa::path(
+    get,
+    path = "/api/me/drafts",
+    tag = "posts",
+    summary = "List drafts and scheduled posts of the current user",
+    description = "Scheduled posts come first, the next to be published first.",
+    responses(
+        (status = 200, description = "Unpublished posts", body = super::openapi::schemas::DraftsResponse),
+    ),
+)]
+pub(super) async fn get_drafts(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Drafts were requested.");
+
+    let mut posts = pool.get_drafts(claims.sub).await?;
+    super::bookmarks::mark_bookmarked(&pool, claims.sub, &mut posts).await?;
+
+    Ok(Json(json!({ "result": "ok", "posts": posts })))
+}
+
+/// `POST /api/me/drafts/{post_id}/publish`
+#[utoipa::path(
+    post,
+    path = "/api/me/drafts/{post_id}/publish",
+    tag = "posts",
+    summary = "Publish, reschedule or unschedule a draft or scheduled post of the current user",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    request_body = PublishDraftRequest,
+    responses(
+        (status = 200, description = "New status of the post", body = super::openapi::schemas::PublishDraftResponse),
+        (status = 404, description = "Post is not a draft of the current user", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn publish_draft(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path(post_id): Path<i32>,
+    ValidJson(payload): ValidJson<PublishDraftRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Publication of draft with id = {post_id} was requested.");
+
+    let publication = publication(payload.draft, payload.publish_at);
+
+    if !pool.publish_draft(post_id, claims.sub, publication).await? {
+        return Err(AppError::post_not_found());
+    }
+
+    Ok(Json(
+        json!({ "result": "ok", "status": publication.status() }),
+    ))
+}
+
+/// Publication asked for by the author, a time that has already come means now.
+pub(super) fn publication(draft: bool, publish_at: Option<DateTime<Utc>>) -> Publication {
+    if draft {
+        return Publication::Draft;
+    }
+
+    match publish_at {
+        Some(publish_at) if publish_at > Utc::now() => Publication::At(publish_at.naive_utc()),
+        _ => Publication::Now,
+    }
+}
+
+/// Publishes the scheduled posts once they are due, every [`PUBLISH_INTERVAL`].
+pub(crate) fn spawn_publisher(pool: SharedRepository) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PUBLISH_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            match pool.publish_due_posts(Utc::now().naive_utc()).await {
+                Ok(post_ids) if !post_ids.is_empty() => {
+                    info!("Published scheduled posts: {post_ids:?}");
+                }
+                Ok(_) => {}
+                Err(err) => error!("Publishing scheduled posts failed: {err:?}"),
+            }
+        }
+    });
+}
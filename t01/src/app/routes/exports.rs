@@ -0,0 +1,339 @@
+use super::{pages::filters, AppError, Claims, SharedRepository};
+use crate::{
+    i18n::Locale,
+    model::ExportStatus,
+    repository::{ExportCreateResult, UserData},
+    storage::{ExportStorage, MediaStorage},
+};
+use anyhow::anyhow;
+use askama::Template;
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::NaiveDateTime;
+use log::{error, info, warn};
+use serde_json::json;
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    sync::atomic::{AtomicBool, Ordering},
+    time::Duration,
+};
+use tokio_util::io::ReaderStream;
+use zip::{write::SimpleFileOptions, CompressionMethod, ZipWriter};
+
+/// How often expired exports are deleted with their archives.
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// How long a build may take, a pending export older than that was abandoned.
+const BUILD_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// `POST /api/me/export`
+#[utoipa::path(
+    post,
+    path = "/api/me/export",
+    tag = "users",
+    summary = "Start an export of the personal data of the current user",
+    description = "The archive is built in the background, poll the export until it is \
+                   `ready`. While an export is being built, it is returned instead of \
+                   starting another one.",
+    responses(
+        (status = 202, description = "Export started", body = super::openapi::schemas::ExportResponse),
+    ),
+)]
+pub(super) async fn create_export(
+    State(pool): State<SharedRepository>,
+    State(media): State<MediaStorage>,
+    State(exports): State<ExportStorage>,
+    Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Personal data export was requested.");
+
+    let export = match pool.create_export(claims.sub).await? {
+        ExportCreateResult::Created(export) => {
+            spawn_export(pool, media, exports, claims.sub, export.export_id, locale);
+            export
+        }
+        ExportCreateResult::Pending(export) => export,
+    };
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(json!({ "result": "ok", "export": export })),
+    ))
+}
+
+/// `GET /api/me/export/{export_id}`
+#[utoipa::path(
+    get,
+    path = "/api/me/export/{export_id}",
+    tag = "users",
+    summary = "Status of an export of the current user",
+    params(("export_id" = i32, Path, description = "Id of the export")),
+    responses(
+        (status = 200, description = "The export", body = super::openapi::schemas::ExportResponse),
+        (status = 404, description = "Export not found or expired", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn get_export(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path(export_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Export with id = {export_id} was requested.");
+
+    let Some(export) = pool.get_export(export_id, claims.sub).await? else {
+        return Err(AppError::export_not_found());
+    };
+
+    Ok(Json(json!({ "result": "ok", "export": export })))
+}
+
+/// `GET /api/me/export/{export_id}/download`
+#[utoipa::path(
+    get,
+    path = "/api/me/export/{export_id}/download",
+    tag = "users",
+    summary = "Download the archive of a ready export",
+    params(("export_id" = i32, Path, description = "Id of the export")),
+    responses(
+        (status = 200, description = "ZIP archive", content_type = "application/zip", body = Vec<u8>),
+        (status = 404, description = "Export not found or expired", body = super::openapi::schemas::ErrorResponse),
+        (status = 409, description = "Export is not ready", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn download_export(
+    State(pool): State<SharedRepository>,
+    State(exports): State<ExportStorage>,
+    Extension(claims): Extension<Claims>,
+    Path(export_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Download of export with id = {export_id} was requested.");
+
+    let Some(export) = pool.get_export(export_id, claims.sub).await? else {
+        return Err(AppError::export_not_found());
+    };
+    let Some(file_name) = export.file_name else {
+        return Err(AppError::export_not_ready());
+    };
+
+    let file = exports.open_archive(claims.sub, &file_name).await?;
+    let size = file.metadata().await?.len();
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/zip".to_owned()),
+            (header::CONTENT_LENGTH, size.to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"export-{export_id}.zip\""),
+            ),
+            (header::CACHE_CONTROL, "no-store".to_owned()),
+        ],
+        Body::from_stream(ReaderStream::new(file)),
+    ))
+}
+
+/// Builds the archive in the background and marks the export as ready, or as failed.
+/// Either way the export expires after the retention of the storage.
+fn spawn_export(
+    pool: SharedRepository,
+    media: MediaStorage,
+    exports: ExportStorage,
+    user_id: i32,
+    export_id: i32,
+    locale: Locale,
+) {
+    tokio::spawn(async move {
+        // a task of its own, so that a panic of the build still finishes the export
+        let mut build = tokio::spawn({
+            let pool = pool.clone();
+            let exports = exports.clone();
+            async move { build_export(&pool, &media, &exports, user_id, export_id, locale).await }
+        });
+        let result = match tokio::time::timeout(BUILD_TIMEOUT, &mut build).await {
+            Ok(Ok(result)) => result,
+            Ok(Err(err)) => Err(anyhow!("Build task failed: {err}")),
+            Err(_) => {
+                // the writer of the archive gives up too, see `ExportStorage::store_archive`
+                build.abort();
+                Err(anyhow!("Build took longer than {BUILD_TIMEOUT:?}"))
+            }
+        };
+        let file_name = match result {
+            Ok(file_name) => Some(file_name),
+            Err(err) => {
+                error!("Export (id = {export_id}) of user (id = {user_id}) failed: {err:?}");
+                None
+            }
+        };
+
+        let expires_at = chrono::Utc::now().naive_utc() + exports.retention();
+        let finished = match pool
+            .finish_export(export_id, file_name.as_deref(), expires_at)
+            .await
+        {
+            Ok(true) => true,
+            Ok(false) => {
+                warn!("Export (id = {export_id}) of user (id = {user_id}) is no longer pending");
+                false
+            }
+            Err(err) => {
+                error!("Finishing export (id = {export_id}) failed: {err:?}");
+                false
+            }
+        };
+        // no export refers to the archive, the cleanup would never delete it
+        if let Some(file_name) = file_name.filter(|_| !finished) {
+            exports.remove_archive(user_id, &file_name).await;
+        }
+    });
+}
+
+/// Deletes expired exports with their archives, every [`CLEANUP_INTERVAL`]. Exports left
+/// pending are failed too, so that they do not block the next export of their user: at
+/// startup all of them, builds do not survive a restart, later those older than
+/// [`BUILD_TIMEOUT`].
+pub(crate) fn spawn_export_cleanup(pool: SharedRepository, exports: ExportStorage) {
+    let started_at = chrono::Utc::now().naive_utc();
+
+    tokio::spawn(async move {
+        fail_stale_exports(&pool, &exports, started_at).await;
+
+        let mut interval = tokio::time::interval(CLEANUP_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let now = chrono::Utc::now().naive_utc();
+            fail_stale_exports(&pool, &exports, now - BUILD_TIMEOUT).await;
+
+            match pool.purge_expired_exports(now).await {
+                Ok(expired) => {
+                    for export in expired {
+                        if let Some(file_name) = export.file_name {
+                            exports.remove_archive(export.user_id, &file_name).await;
+                        }
+                    }
+                }
+                Err(err) => error!("Purging expired exports failed: {err:?}"),
+            }
+        }
+    });
+}
+
+async fn fail_stale_exports(
+    pool: &SharedRepository,
+    exports: &ExportStorage,
+    started_before: NaiveDateTime,
+) {
+    let expires_at = chrono::Utc::now().naive_utc() + exports.retention();
+    if let Err(err) = pool.fail_stale_exports(started_before, expires_at).await {
+        error!("Failing stale exports failed: {err:?}");
+    }
+}
+
+#[derive(Debug, Template)]
+#[template(path = "export.askama.html")]
+struct ExportIndexTemplate<'a> {
+    locale: Locale,
+    data: &'a UserData,
+    exported_at: NaiveDateTime,
+}
+
+async fn build_export(
+    pool: &SharedRepository,
+    media: &MediaStorage,
+    exports: &ExportStorage,
+    user_id: i32,
+    export_id: i32,
+    locale: Locale,
+) -> anyhow::Result<String> {
+    let Some(data) = pool.get_user_data(user_id).await? else {
+        return Err(anyhow!("User (id = {user_id}) does not exist"));
+    };
+
+    let index = ExportIndexTemplate {
+        locale,
+        data: &data,
+        exported_at: chrono::Utc::now().naive_utc(),
+    }
+    .render()?;
+
+    // the avatar and the images of the posts
+    let mut file_names = Vec::new();
+    file_names.extend(data.profile.avatar_file_name.clone());
+    for exported in &data.posts {
+        for attachment in &exported.post.attachments {
+            file_names.push(attachment.file_name.clone());
+        }
+    }
+
+    // the user may have been deleted, or the export failed as stale, in the meantime
+    let pending = pool
+        .get_export(export_id, user_id)
+        .await?
+        .is_some_and(|export| export.status == ExportStatus::Pending);
+    if !pending {
+        return Err(anyhow!("Export (id = {export_id}) is no longer pending"));
+    }
+
+    let media_dir = media.dir().to_path_buf();
+    exports
+        .store_archive(user_id, move |file, cancelled| {
+            write_archive(file, cancelled, &data, &index, &media_dir, &file_names)
+        })
+        .await
+}
+
+/// ZIP archive with the data as JSON, the media files and `index.html` showing both.
+/// The media files are copied from `media_dir` one by one, a missing file is left out,
+/// until the build is `cancelled`.
+fn write_archive(
+    file: File,
+    cancelled: &AtomicBool,
+    data: &UserData,
+    index: &str,
+    media_dir: &std::path::Path,
+    file_names: &[String],
+) -> anyhow::Result<()> {
+    let mut zip = ZipWriter::new(BufWriter::new(file));
+    let deflated = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    // images are compressed already
+    let stored = SimpleFileOptions::default().compression_method(CompressionMethod::Stored);
+
+    zip.start_file("index.html", deflated)?;
+    zip.write_all(index.as_bytes())?;
+
+    zip.start_file("profile.json", deflated)?;
+    serde_json::to_writer_pretty(&mut zip, &data.profile)?;
+    zip.start_file("posts.json", deflated)?;
+    serde_json::to_writer_pretty(&mut zip, &data.posts)?;
+    zip.start_file("reactions.json", deflated)?;
+    serde_json::to_writer_pretty(&mut zip, &data.reactions)?;
+    zip.start_file("comments.json", deflated)?;
+    serde_json::to_writer_pretty(&mut zip, &data.comments)?;
+
+    for file_name in file_names {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(anyhow!("Writing of the archive was cancelled"));
+        }
+        let mut media_file = match File::open(media_dir.join(file_name)) {
+            Ok(media_file) => media_file,
+            Err(err) => {
+                warn!("Media file '{file_name}' left out of the export: {err}");
+                continue;
+            }
+        };
+        zip.start_file(format!("media/{file_name}"), stored)?;
+        std::io::copy(&mut media_file, &mut zip)?;
+    }
+
+    let file = zip.finish()?.into_inner()?;
+    file.sync_all()?;
+
+    Ok(())
+}
@@ -0,0 +1,31 @@
+use crate::i18n::Locale;
+use axum::{
+    extract::Request,
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+/// Picks the locale of a request from its `Accept-Language` header.
+///
+/// Handlers receive it as `Extension<Locale>`. Errors are turned into responses without
+/// access to the request, so they read it from [`Locale::current`].
+pub(super) async fn negotiate_locale(mut request: Request, next: Next) -> Response {
+    let locale = request
+        .headers()
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|header| header.to_str().ok())
+        .map(Locale::negotiate)
+        .unwrap_or_default();
+    request.extensions_mut().insert(locale);
+
+    let mut response = locale.scope(next.run(request)).await;
+
+    let headers = response.headers_mut();
+    headers.insert(
+        header::CONTENT_LANGUAGE,
+        HeaderValue::from_static(locale.as_str()),
+    );
+    headers.append(header::VARY, HeaderValue::from_static("accept-language"));
+    response
+}
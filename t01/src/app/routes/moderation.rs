@@ -0,0 +1,253 @@
+use super::{auth::require_role, validation::ValidJson, AppError, Claims, SharedRepository};
+use crate::{
+    i18n::Locale,
+    model::{ChangeRoleRequest, ModerationRequest, Role, SuspendRequest},
+    repository::ModerationResult,
+    storage::MediaStorage,
+};
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use log::info;
+use serde::Deserialize;
+use serde_json::json;
+use utoipa::IntoParams;
+
+/// `DELETE /api/moderation/posts/{post_id}`
+///
+/// The only delete that skips the trash: the post is removed for good with its
+/// attachment files, also from the trash of its author, and can not be restored.
+#[utoipa::path(
+    delete,
+    path = "/api/moderation/posts/{post_id}",
+    tag = "moderation",
+    summary = "Delete a post of any user",
+    description = "The post is deleted for good, skipping the trash, and can not be restored.",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    request_body = ModerationRequest,
+    responses(
+        (status = 200, description = "Post deleted", body = super::openapi::schemas::MessageResponse),
+        (status = 403, description = "Not a moderator", body = super::openapi::schemas::ErrorResponse),
+        (status = 404, description = "Post not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn delete_any_post(
+    State(pool): State<SharedRepository>,
+    State(media): State<MediaStorage>,
+    Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
+    Path(post_id): Path<i32>,
+    ValidJson(payload): ValidJson<ModerationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Moderator delete of post with id = {post_id} was requested.");
+
+    require_role(&claims, Role::Moderator)?;
+
+    let attachments = pool.get_attachments(post_id).await?;
+
+    if !pool
+        .moderate_delete_post(claims.sub, post_id, payload.reason.as_deref())
+        .await?
+    {
+        return Err(AppError::post_not_found());
+    }
+
+    super::attachments::remove_files(&media, attachments).await;
+
+    Ok(Json(json!({
+        "result": "ok",
+        "message": locale.text("post_deleted")
+    })))
+}
+
+/// `POST /api/moderation/users/{user_id}/suspend`
+#[utoipa::path(
+    post,
+    path = "/api/moderation/users/{user_id}/suspend",
+    tag = "moderation",
+    summary = "Suspend a user for a while",
+    params(("user_id" = i32, Path, description = "Id of the user")),
+    request_body = SuspendRequest,
+    responses(
+        (status = 200, description = "Done", body = super::openapi::schemas::MessageResponse),
+        (status = 403, description = "Not permitted to moderate the user", body = super::openapi::schemas::ErrorResponse),
+        (status = 404, description = "User not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn suspend_user(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
+    Path(user_id): Path<i32>,
+    ValidJson(payload): ValidJson<SuspendRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Suspension of user with id = {user_id} was requested.");
+
+    require_role(&claims, Role::Moderator)?;
+
+    let SuspendRequest { days, reason } = payload;
+
+    let now = chrono::Utc::now().naive_utc();
+    let until = now + chrono::TimeDelta::days(days);
+
+    let result = pool
+        .block_user(
+            claims.sub,
+            claims.role,
+            user_id,
+            Some(until),
+            reason.as_deref(),
+            now,
+        )
+        .await?;
+
+    moderation_response(result, &locale.text("user_suspended"))
+}
+
+/// `POST /api/moderation/users/{user_id}/ban`
+#[utoipa::path(
+    post,
+    path = "/api/moderation/users/{user_id}/ban",
+    tag = "moderation",
+    summary = "Ban a user",
+    params(("user_id" = i32, Path, description = "Id of the user")),
+    request_body = ModerationRequest,
+    responses(
+        (status = 200, description = "Done", body = super::openapi::schemas::MessageResponse),
+        (status = 403, description = "Not permitted to moderate the user", body = super::openapi::schemas::ErrorResponse),
+        (status = 404, description = "User not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn ban_user(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
+    Path(user_id): Path<i32>,
+    ValidJson(payload): ValidJson<ModerationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Ban of user with id = {user_id} was requested.");
+
+    require_role(&claims, Role::Moderator)?;
+
+    let result = pool
+        .block_user(
+            claims.sub,
+            claims.role,
+            user_id,
+            None,
+            payload.reason.as_deref(),
+            chrono::Utc::now().naive_utc(),
+        )
+        .await?;
+
+    moderation_response(result, &locale.text("user_banned"))
+}
+
+/// `POST /api/moderation/users/{user_id}/unblock`
+#[utoipa::path(
+    post,
+    path = "/api/moderation/users/{user_id}/unblock",
+    tag = "moderation",
+    summary = "Lift a suspension or a ban",
+    params(("user_id" = i32, Path, description = "Id of the user")),
+    request_body = ModerationRequest,
+    responses(
+        (status = 200, description = "Done", body = super::openapi::schemas::MessageResponse),
+        (status = 403, description = "Not permitted to moderate the user", body = super::openapi::schemas::ErrorResponse),
+        (status = 404, description = "User not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn unblock_user(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
+    Path(user_id): Path<i32>,
+    ValidJson(payload): ValidJson<ModerationRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Unblock of user with id = {user_id} was requested.");
+
+    require_role(&claims, Role::Moderator)?;
+
+    let result = pool
+        .unblock_user(claims.sub, claims.role, user_id, payload.reason.as_deref())
+        .await?;
+
+    moderation_response(result, &locale.text("user_unblocked"))
+}
+
+/// `PUT /api/moderation/users/{user_id}/role`
+#[utoipa::path(
+    put,
+    path = "/api/moderation/users/{user_id}/role",
+    tag = "moderation",
+    summary = "Change the role of a user",
+    params(("user_id" = i32, Path, description = "Id of the user")),
+    request_body = ChangeRoleRequest,
+    responses(
+        (status = 200, description = "Role changed", body = super::openapi::schemas::MessageResponse),
+        (status = 403, description = "Not an admin", body = super::openapi::schemas::ErrorResponse),
+        (status = 404, description = "User not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn change_role(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
+    Path(user_id): Path<i32>,
+    ValidJson(payload): ValidJson<ChangeRoleRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Role change of user with id = {user_id} was requested.");
+
+    require_role(&claims, Role::Admin)?;
+
+    let result = pool.change_role(claims.sub, user_id, payload.role).await?;
+
+    moderation_response(result, &locale.text("role_changed"))
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(super) struct ActionsQuery {
+    /// Number of actions, 50 by default and at most 500.
+    limit: Option<i64>,
+}
+
+/// `GET /api/moderation/actions?limit=`
+#[utoipa::path(
+    get,
+    path = "/api/moderation/actions",
+    tag = "moderation",
+    summary = "List recent moderation actions",
+    params(ActionsQuery),
+    responses(
+        (status = 200, description = "Actions, newest first", body = super::openapi::schemas::ActionsResponse),
+        (status = 403, description = "Not a moderator", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn get_actions(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<ActionsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Moderation actions were requested.");
+
+    require_role(&claims, Role::Moderator)?;
+
+    let limit = query.limit.unwrap_or(50).clamp(1, 500);
+    let actions = pool.get_moderation_actions(limit).await?;
+
+    Ok(Json(json!({ "result": "ok", "actions": actions })))
+}
+
+fn moderation_response(
+    result: ModerationResult,
+    message: &str,
+) -> Result<Json<serde_json::Value>, AppError> {
+    match result {
+        ModerationResult::Done => Ok(Json(json!({ "result": "ok", "message": message }))),
+        ModerationResult::UserNotFound => Err(AppError::user_not_found()),
+        ModerationResult::NotPermitted => Err(AppError::forbidden("moderation_forbidden")),
+    }
+}
@@ -0,0 +1,75 @@
+use super::{validation::ValidJson, AppError, Claims, SharedRepository};
+use crate::{
+    model::{NotificationsQuery, ReadNotificationsRequest},
+    repository::NotificationsPage,
+};
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use log::info;
+use serde_json::json;
+
+/// `GET /api/notifications?limit=&before=&unread=`
+#[utoipa::path(
+    get,
+    path = "/api/notifications",
+    tag = "notifications",
+    summary = "List notifications, newest first",
+    params(NotificationsQuery),
+    responses(
+        (status = 200, description = "Page of notifications", body = super::openapi::schemas::NotificationsResponse),
+    ),
+)]
+pub(super) async fn get_notifications(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Query(query): Query<NotificationsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Notifications were requested.");
+
+    let NotificationsPage {
+        notifications,
+        unread_count,
+        next_before,
+    } = pool
+        .get_notifications(claims.sub, query.before, query.unread, query.limit())
+        .await?;
+
+    Ok(Json(json!({
+        "result": "ok",
+        "notifications": notifications,
+        "unread_count": unread_count,
+        "next_before": next_before,
+    })))
+}
+
+/// `POST /api/notifications/read`
+#[utoipa::path(
+    post,
+    path = "/api/notifications/read",
+    tag = "notifications",
+    summary = "Mark notifications as read",
+    request_body = ReadNotificationsRequest,
+    responses(
+        (status = 200, description = "Number of marked notifications", body = super::openapi::schemas::MarkedResponse),
+    ),
+)]
+pub(super) async fn read_notifications(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    ValidJson(payload): ValidJson<ReadNotificationsRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Marking notifications as read was requested.");
+
+    let marked = pool
+        .mark_notifications_read(
+            claims.sub,
+            payload.notification_ids.as_deref(),
+            chrono::Utc::now().naive_utc(),
+        )
+        .await?;
+
+    Ok(Json(json!({ "result": "ok", "marked": marked })))
+}
@@ -0,0 +1,414 @@
+use utoipa::{
+    openapi::{
+        security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+        Content, OpenApi as OpenApiDocument, Ref, RefOr, Response,
+    },
+    Modify, OpenApi,
+};
+
+/// OpenAPI document of the JSON API, served at `/api/openapi.json`.
+///
+/// Every route under `/api` has to be listed in `paths`, the router tests fail otherwise.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "t01",
+        description = "JSON API of the blog. Authorized requests carry either a bearer \
+                       access token or the session cookies together with the `X-CSRF-Token` \
+                       header."
+    ),
+    paths(
+        super::register_user,
+        super::login_user,
+        super::refresh_token,
+        super::logout_user,
+        super::get_posts,
+        super::create_post,
+        super::get_post,
+        super::edit_post,
+        super::delete_post,
+        super::bookmarks::get_bookmarks,
+        super::drafts::get_drafts,
+        super::drafts::publish_draft,
+        super::trash::get_trash,
+        super::trash::restore_post,
+        super::like_post,
+        super::reactions::add_reaction,
+        super::reactions::remove_reaction,
+        super::reposts::repost,
+        super::reposts::remove_repost,
+        super::bookmarks::add_bookmark,
+        super::bookmarks::remove_bookmark,
+        super::get_post_revisions,
+        super::attachments::upload_attachments,
+        super::create_comment,
+        super::get_comments,
+        super::delete_comment,
+        super::get_user_posts,
+        super::follow_user,
+        super::unfollow_user,
+        super::get_followers,
+        super::get_following,
+        super::profiles::get_profile,
+        super::profiles::edit_profile,
+        super::profiles::upload_avatar,
+        super::profiles::delete_avatar,
+        super::profiles::change_password,
+        super::profiles::delete_account,
+        super::exports::create_export,
+        super::exports::get_export,
+        super::exports::download_export,
+        super::get_feed,
+        super::stream::stream_events,
+        super::search,
+        super::notifications::get_notifications,
+        super::notifications::read_notifications,
+        super::moderation::delete_any_post,
+        super::moderation::suspend_user,
+        super::moderation::ban_user,
+        super::moderation::unblock_user,
+        super::moderation::change_role,
+        super::moderation::get_actions,
+    ),
+    components(schemas(schemas::ErrorResponse)),
+    modifiers(&CommonResponses),
+    security(("bearer" = [])),
+    tags(
+        (name = "auth", description = "Registration, login and sessions"),
+        (name = "posts", description = "Posts, reactions, revisions and attachments"),
+        (name = "comments", description = "Comment threads of posts"),
+        (name = "users", description = "User pages, follows and profiles"),
+        (name = "feed", description = "Feed, live updates and search"),
+        (name = "notifications", description = "Notifications of the current user"),
+        (name = "moderation", description = "Moderation, available to moderators and admins"),
+    )
+)]
+pub(crate) struct ApiDoc;
+
+/// Adds the bearer scheme and the error responses every handler may return, so the
+/// `responses` of a handler list only the errors specific to it.
+struct CommonResponses;
+
+impl Modify for CommonResponses {
+    fn modify(&self, openapi: &mut OpenApiDocument) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+
+        for item in openapi.paths.paths.values_mut() {
+            let operations = [
+                &mut item.get,
+                &mut item.post,
+                &mut item.put,
+                &mut item.patch,
+                &mut item.delete,
+            ];
+
+            for operation in operations.into_iter().flatten() {
+                // public operations override the global requirement with an empty one
+                let is_public = operation
+                    .security
+                    .as_ref()
+                    .is_some_and(|security| !security.is_empty());
+                let has_json_body = operation
+                    .request_body
+                    .as_ref()
+                    .is_some_and(|body| body.content.contains_key("application/json"));
+
+                let responses = &mut operation.responses.responses;
+                if !is_public {
+                    responses
+                        .entry("401".to_string())
+                        .or_insert_with(|| error_response("Missing, invalid or expired token"));
+                }
+                if has_json_body {
+                    responses
+                        .entry("422".to_string())
+                        .or_insert_with(|| error_response("Request body failed validation"));
+                }
+                responses
+                    .entry("500".to_string())
+                    .or_insert_with(|| error_response("Internal server error"));
+            }
+        }
+    }
+}
+
+fn error_response(description: &str) -> RefOr<Response> {
+    let mut response = Response::new(description);
+    response.content.insert(
+        "application/json".to_string(),
+        Content::new(Some(Ref::from_schema_name("ErrorResponse"))),
+    );
+    RefOr::T(response)
+}
+
+/// Bodies the handlers build with `json!`. They exist for the document only.
+#[allow(dead_code)]
+pub(super) mod schemas {
+    use crate::{
+        model::{PostStatus, Role},
+        repository::{
+            CommentThread, DatabaseAttachment, DatabaseExport, DatabaseFollow,
+            DatabaseNotification, DatabasePost, DatabaseRevision, DeletedPost, Like,
+            ModerationAction, ReactionCounts, SearchedPost, SearchedUser, UserProfile,
+        },
+    };
+    use std::collections::HashMap;
+    use utoipa::ToSchema;
+
+    /// Body of every error response.
+    #[derive(ToSchema)]
+    pub(crate) struct ErrorResponse {
+        /// Always `err`.
+        #[schema(example = "err")]
+        result: String,
+        /// Stable code of the error, like `post_not_found`.
+        #[schema(example = "post_not_found")]
+        code: String,
+        /// Message in the language picked by `Accept-Language`.
+        message: String,
+        /// Validation messages by field, present on `422` only.
+        errors: Option<HashMap<String, Vec<String>>>,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct MessageResponse {
+        #[schema(example = "ok")]
+        result: String,
+        message: String,
+    }
+
+    /// Exactly one of `jwt` and `session` is present, depending on the requested session mode.
+    #[derive(ToSchema)]
+    pub(crate) struct TokensResponse {
+        #[schema(example = "ok")]
+        result: String,
+        jwt: Option<BearerTokens>,
+        session: Option<CookieSession>,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct BearerTokens {
+        token: String,
+        refresh_token: String,
+        /// Present on login only.
+        username: Option<String>,
+        user_id: i32,
+        role: Role,
+    }
+
+    /// The tokens themselves are set as `HttpOnly` cookies.
+    #[derive(ToSchema)]
+    pub(crate) struct CookieSession {
+        /// Value of the `X-CSRF-Token` header for unsafe requests.
+        csrf_token: String,
+        /// Present on login only.
+        username: Option<String>,
+        user_id: i32,
+        role: Role,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct PostsResponse {
+        #[schema(example = "ok")]
+        result: String,
+        posts: Vec<DatabasePost>,
+        /// Cursor of the next page, `null` on the last page.
+        next_cursor: Option<String>,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct PostIdResponse {
+        #[schema(example = "ok")]
+        result: String,
+        post_id: i32,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct PostResponse {
+        #[schema(example = "ok")]
+        result: String,
+        post: DatabasePost,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct TrashResponse {
+        #[schema(example = "ok")]
+        result: String,
+        posts: Vec<DeletedPost>,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct DraftsResponse {
+        #[schema(example = "ok")]
+        result: String,
+        posts: Vec<DatabasePost>,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct PublishDraftResponse {
+        #[schema(example = "ok")]
+        result: String,
+        status: PostStatus,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct LikeResponse {
+        #[schema(example = "ok")]
+        result: String,
+        like: Like,
+        likes_count: i64,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct ReactionsResponse {
+        #[schema(example = "ok")]
+        result: String,
+        /// Number of reactions of every kind.
+        reactions: ReactionCounts,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct ExportResponse {
+        #[schema(example = "ok")]
+        result: String,
+        export: DatabaseExport,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct BookmarkResponse {
+        #[schema(example = "ok")]
+        result: String,
+        /// Whether the post is bookmarked by the current user now.
+        bookmarked: bool,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct RepostResponse {
+        #[schema(example = "ok")]
+        result: String,
+        /// Id of the repost of the current user, `null` once it is removed.
+        repost_id: Option<i32>,
+        /// Number of reposts of the original post.
+        reposts_count: i64,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct RevisionsResponse {
+        #[schema(example = "ok")]
+        result: String,
+        revisions: Vec<DatabaseRevision>,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct AttachmentsResponse {
+        #[schema(example = "ok")]
+        result: String,
+        attachments: Vec<DatabaseAttachment>,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct CommentIdResponse {
+        #[schema(example = "ok")]
+        result: String,
+        comment_id: i32,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct CommentsResponse {
+        #[schema(example = "ok")]
+        result: String,
+        comments: Vec<CommentThread>,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct UserPostsResponse {
+        #[schema(example = "ok")]
+        result: String,
+        username: String,
+        followers_count: i64,
+        following_count: i64,
+        is_followed: bool,
+        posts: Vec<DatabasePost>,
+        next_cursor: Option<String>,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct FollowResponse {
+        #[schema(example = "ok")]
+        result: String,
+        followers_count: i64,
+        is_followed: bool,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct UsersResponse {
+        #[schema(example = "ok")]
+        result: String,
+        users: Vec<DatabaseFollow>,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct ProfileResponse {
+        #[schema(example = "ok")]
+        result: String,
+        profile: UserProfile,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct SearchResponse {
+        #[schema(example = "ok")]
+        result: String,
+        posts: Vec<SearchedPost>,
+        users: Vec<SearchedUser>,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct NotificationsResponse {
+        #[schema(example = "ok")]
+        result: String,
+        notifications: Vec<DatabaseNotification>,
+        unread_count: i64,
+        /// Id to pass as `before` for the next page, `null` on the last page.
+        next_before: Option<i32>,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct MarkedResponse {
+        #[schema(example = "ok")]
+        result: String,
+        /// Number of notifications marked as read.
+        marked: u64,
+    }
+
+    #[derive(ToSchema)]
+    pub(crate) struct ActionsResponse {
+        #[schema(example = "ok")]
+        result: String,
+        actions: Vec<ModerationAction>,
+    }
+
+    /// Multipart body of an attachment upload.
+    #[derive(ToSchema)]
+    pub(crate) struct ImagesUpload {
+        /// JPEG, PNG, GIF or WebP images, the field may be repeated.
+        #[schema(value_type = Vec<String>, format = Binary)]
+        images: Vec<Vec<u8>>,
+    }
+
+    /// Multipart body of an avatar upload.
+    #[derive(ToSchema)]
+    pub(crate) struct AvatarUpload {
+        /// JPEG, PNG, GIF or WebP image.
+        #[schema(value_type = String, format = Binary)]
+        avatar: Vec<u8>,
+    }
+}
@@ -0,0 +1,232 @@
+use super::{decode_cursor, AppError, SharedRepository};
+use crate::{
+    i18n::Locale,
+    model::{PostSort, PostsQuery},
+    repository::{
+        CommentThread, DatabaseComment, DatabasePost, FollowCounts, PostsPage, UserProfile,
+    },
+};
+use askama::Template;
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Redirect},
+    Extension,
+};
+use log::info;
+
+/// Helpers available in every page template.
+pub(super) mod filters {
+    use chrono::NaiveDateTime;
+
+    /// Same format as `formatDateGMT3` in `script.js`: `2024/12/31, 11:59:59 PM`.
+    pub(crate) fn datetime(datetime: &NaiveDateTime) -> askama::Result<String> {
+        let datetime = *datetime + chrono::TimeDelta::hours(3);
+        Ok(datetime.format("%Y/%m/%d, %I:%M:%S %p").to_string())
+    }
+}
+
+#[derive(Debug, Template)]
+#[template(path = "index.askama.html")]
+struct IndexTemplate {
+    locale: Locale,
+}
+
+/// `GET /`
+pub(super) async fn get_page_index(
+    _: State<SharedRepository>,
+    Extension(locale): Extension<Locale>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Index page was requested.");
+    let html = IndexTemplate { locale };
+
+    Ok(askama_axum::into_response(&html))
+}
+
+#[derive(Debug, Template)]
+#[template(path = "register.askama.html")]
+struct RegisterTemplate {
+    locale: Locale,
+}
+
+/// `GET /register`
+pub(super) async fn get_page_registration(
+    _: State<SharedRepository>,
+    Extension(locale): Extension<Locale>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Register page was requested.");
+    let html = RegisterTemplate { locale };
+
+    Ok(askama_axum::into_response(&html))
+}
+
+#[derive(Debug, Template)]
+#[template(path = "login.askama.html")]
+struct LoginTemplate {
+    locale: Locale,
+}
+
+/// `GET /login`
+pub(super) async fn get_page_login(
+    _: State<SharedRepository>,
+    Extension(locale): Extension<Locale>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Login page was requested.");
+    let html = LoginTemplate { locale };
+
+    Ok(askama_axum::into_response(&html))
+}
+
+#[derive(Debug, Template)]
+#[template(path = "posts.askama.html")]
+struct PostsTemplate {
+    locale: Locale,
+    posts: Vec<DatabasePost>,
+    sort: PostSort,
+    next_cursor: Option<String>,
+}
+
+/// `GET /posts`
+pub(super) async fn get_page_posts(
+    State(pool): State<SharedRepository>,
+    Extension(locale): Extension<Locale>,
+    Query(query): Query<PostsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Posts page was requested.");
+
+    let cursor = decode_cursor(&query)?;
+    let PostsPage { posts, next_cursor } = pool
+        .get_posts(query.sort, cursor.as_ref(), query.limit())
+        .await?;
+
+    let html = PostsTemplate {
+        locale,
+        posts,
+        sort: query.sort,
+        next_cursor: next_cursor.map(|cursor| cursor.encode()),
+    };
+
+    Ok(askama_axum::into_response(&html))
+}
+
+/// Comment of a thread flattened for the template, which cannot recurse.
+#[derive(Debug)]
+struct CommentEntry {
+    comment: DatabaseComment,
+    /// How many comments end after this one: the comment itself, if it has no replies,
+    /// and its ancestors, whose last reply it is.
+    closing: usize,
+}
+
+impl CommentEntry {
+    fn flatten(threads: Vec<CommentThread>, entries: &mut Vec<Self>) {
+        for CommentThread { comment, replies } in threads {
+            entries.push(Self {
+                comment,
+                closing: 0,
+            });
+            Self::flatten(replies, entries);
+
+            if let Some(last) = entries.last_mut() {
+                last.closing += 1;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Template)]
+#[template(path = "post.askama.html")]
+struct PostTemplate {
+    locale: Locale,
+    post: DatabasePost,
+    comments: Vec<CommentEntry>,
+}
+
+/// `GET /posts/{post_id}`
+pub(super) async fn get_page_post(
+    State(pool): State<SharedRepository>,
+    Extension(locale): Extension<Locale>,
+    Path(post_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Post page with id = {post_id} was requested.");
+
+    let Some(post) = pool.get_post(post_id).await? else {
+        return Err(AppError::post_not_found());
+    };
+
+    // reposts have no page of their own, comments and reactions go to the original
+    if let Some(original_id) = post.repost_of_id {
+        return Ok(Redirect::to(&format!("/posts/{original_id}")).into_response());
+    }
+
+    let threads = CommentThread::from_comments(pool.get_comments(post_id).await?);
+    let mut comments = Vec::new();
+    CommentEntry::flatten(threads, &mut comments);
+
+    let html = PostTemplate {
+        locale,
+        post,
+        comments,
+    };
+
+    Ok(askama_axum::into_response(&html))
+}
+
+#[derive(Debug, Template)]
+#[template(path = "user.askama.html")]
+struct UserTemplate {
+    locale: Locale,
+    profile: UserProfile,
+    follow_counts: FollowCounts,
+    posts: Vec<DatabasePost>,
+    sort: PostSort,
+    next_cursor: Option<String>,
+}
+
+/// `GET /users/{user_id}`
+pub(super) async fn get_page_user(
+    State(pool): State<SharedRepository>,
+    Extension(locale): Extension<Locale>,
+    Path(user_id): Path<i32>,
+    Query(query): Query<PostsQuery>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("User page with id = {user_id} was requested.");
+
+    let Some(profile) = pool.get_profile(user_id).await? else {
+        return Err(AppError::user_not_found());
+    };
+
+    let cursor = decode_cursor(&query)?;
+    let PostsPage { posts, next_cursor } = pool
+        .get_user_posts(user_id, query.sort, cursor.as_ref(), query.limit())
+        .await?;
+
+    let follow_counts = pool.get_follow_counts(user_id, None).await?;
+
+    let html = UserTemplate {
+        locale,
+        profile,
+        follow_counts,
+        posts,
+        sort: query.sort,
+        next_cursor: next_cursor.map(|cursor| cursor.encode()),
+    };
+
+    Ok(askama_axum::into_response(&html))
+}
+
+#[derive(Debug, Template)]
+#[template(path = "notifications.askama.html")]
+struct NotificationsTemplate {
+    locale: Locale,
+}
+
+/// `GET /notifications`
+pub(super) async fn get_page_notifications(
+    _: State<SharedRepository>,
+    Extension(locale): Extension<Locale>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Notifications page was requested.");
+    let html = NotificationsTemplate { locale };
+
+    Ok(askama_axum::into_response(&html))
+}
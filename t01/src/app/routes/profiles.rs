@@ -0,0 +1,312 @@
+use super::{auth, session, validation::ValidJson, AppError, Claims, SharedRepository};
+use crate::{
+    i18n::Locale,
+    model::{ChangePasswordRequest, DeleteAccountRequest, EditProfileRequest},
+    storage::{ExportStorage, MediaStorage, MAX_IMAGE_SIZE},
+    utils::PasswordHash,
+};
+use axum::{
+    extract::{Multipart, Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use axum_extra::extract::cookie::CookieJar;
+use log::{info, warn};
+use serde_json::json;
+
+/// Avatar upload: a single image plus the multipart framing.
+pub(super) const MAX_AVATAR_BODY_SIZE: usize = MAX_IMAGE_SIZE + 64 * 1024;
+
+const AVATAR_FIELD: &str = "avatar";
+
+/// `GET /api/users/{user_id}/profile`
+#[utoipa::path(
+    get,
+    path = "/api/users/{user_id}/profile",
+    tag = "users",
+    summary = "Get the profile of a user",
+    params(("user_id" = i32, Path, description = "Id of the user")),
+    responses(
+        (status = 200, description = "The profile", body = super::openapi::schemas::ProfileResponse),
+        (status = 404, description = "User not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn get_profile(
+    State(pool): State<SharedRepository>,
+    _: Extension<Claims>,
+    Path(user_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Profile of user with id = {user_id} was requested.");
+
+    let Some(profile) = pool.get_profile(user_id).await? else {
+        return Err(AppError::user_not_found());
+    };
+
+    Ok(Json(json!({ "result": "ok", "profile": profile })))
+}
+
+/// `PATCH /api/users/{user_id}/profile`
+#[utoipa::path(
+    patch,
+    path = "/api/users/{user_id}/profile",
+    tag = "users",
+    summary = "Edit the profile of the current user",
+    params(("user_id" = i32, Path, description = "Id of the user")),
+    request_body = EditProfileRequest,
+    responses(
+        (status = 200, description = "Edited profile", body = super::openapi::schemas::ProfileResponse),
+        (status = 400, description = "Nothing to change", body = super::openapi::schemas::ErrorResponse),
+        (status = 403, description = "Profile belongs to another user", body = super::openapi::schemas::ErrorResponse),
+        (status = 404, description = "User not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn edit_profile(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path(user_id): Path<i32>,
+    ValidJson(payload): ValidJson<EditProfileRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Edit of profile of user with id = {user_id} was requested.");
+
+    if claims.sub != user_id {
+        warn!(
+            "User (id = {}) tried to edit profile of user (id = {user_id})",
+            claims.sub
+        );
+        return Err(AppError::forbidden("profile_edit_forbidden"));
+    }
+
+    if payload.is_empty() {
+        warn!("Edit profile request failed: nothing to change");
+        return Err(AppError::bad_request("nothing_to_change"));
+    }
+
+    let EditProfileRequest { display_name, bio } = payload;
+
+    if !pool
+        .update_profile(
+            user_id,
+            display_name.as_deref().map(str::trim),
+            bio.as_deref().map(str::trim),
+        )
+        .await?
+    {
+        return Err(AppError::user_not_found());
+    }
+
+    let Some(profile) = pool.get_profile(user_id).await? else {
+        return Err(AppError::user_not_found());
+    };
+
+    Ok(Json(json!({ "result": "ok", "profile": profile })))
+}
+
+/// `PUT /api/me/avatar`
+#[utoipa::path(
+    put,
+    path = "/api/me/avatar",
+    tag = "users",
+    summary = "Replace the avatar of the current user",
+    request_body(content = super::openapi::schemas::AvatarUpload, content_type = "multipart/form-data"),
+    responses(
+        (status = 200, description = "Profile with the new avatar", body = super::openapi::schemas::ProfileResponse),
+        (status = 400, description = "No image or an unsupported format", body = super::openapi::schemas::ErrorResponse),
+        (status = 413, description = "Image is too large", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn upload_avatar(
+    State(pool): State<SharedRepository>,
+    State(media): State<MediaStorage>,
+    Extension(claims): Extension<Claims>,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Avatar upload was requested.");
+
+    let user_id = claims.sub;
+
+    let bytes = loop {
+        let field = match multipart.next_field().await {
+            Ok(Some(field)) => field,
+            Ok(None) => {
+                warn!("Avatar upload failed: no image");
+                return Err(AppError::bad_request("no_image"));
+            }
+            Err(err) => {
+                warn!("Avatar upload failed: invalid multipart body: {err}");
+                return Err(AppError::bad_request("invalid_multipart"));
+            }
+        };
+
+        if field.name() != Some(AVATAR_FIELD) {
+            continue;
+        }
+
+        match field.bytes().await {
+            Ok(bytes) => break bytes,
+            Err(err) => {
+                warn!("Avatar upload failed: image can not be read: {err}");
+                return Err(AppError::bad_request("unreadable_image"));
+            }
+        }
+    };
+
+    let avatar = media.store_image(bytes.to_vec()).await?;
+    let stored = [avatar.file_name.clone(), avatar.thumbnail_name.clone()];
+
+    let previous = match pool.set_avatar(user_id, Some(&avatar)).await {
+        Ok(Some(previous)) => previous,
+        Ok(None) => {
+            media.remove(&stored).await;
+            return Err(AppError::user_not_found());
+        }
+        Err(err) => {
+            media.remove(&stored).await;
+            return Err(err.into());
+        }
+    };
+    media.remove(&previous).await;
+
+    let Some(profile) = pool.get_profile(user_id).await? else {
+        return Err(AppError::user_not_found());
+    };
+
+    Ok(Json(json!({ "result": "ok", "profile": profile })))
+}
+
+/// `DELETE /api/me/avatar`
+#[utoipa::path(
+    delete,
+    path = "/api/me/avatar",
+    tag = "users",
+    summary = "Remove the avatar of the current user",
+    responses(
+        (status = 200, description = "Avatar removed", body = super::openapi::schemas::MessageResponse),
+    ),
+)]
+pub(super) async fn delete_avatar(
+    State(pool): State<SharedRepository>,
+    State(media): State<MediaStorage>,
+    Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Avatar removal was requested.");
+
+    let Some(previous) = pool.set_avatar(claims.sub, None).await? else {
+        return Err(AppError::user_not_found());
+    };
+    media.remove(&previous).await;
+
+    Ok(Json(json!({
+        "result": "ok",
+        "message": locale.text("avatar_removed")
+    })))
+}
+
+/// `POST /api/me/password`
+#[utoipa::path(
+    post,
+    path = "/api/me/password",
+    tag = "users",
+    summary = "Change the password and end all sessions",
+    request_body = ChangePasswordRequest,
+    responses(
+        (status = 200, description = "Password changed", body = super::openapi::schemas::MessageResponse),
+        (status = 401, description = "Wrong password or an invalid token", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn change_password(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
+    jar: CookieJar,
+    ValidJson(payload): ValidJson<ChangePasswordRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Password change was requested.");
+
+    let user_id = claims.sub;
+    verify_password(&pool, user_id, &payload.current_password).await?;
+
+    let password_hash = PasswordHash::from_password(&payload.new_password)?;
+    let now = chrono::Utc::now().naive_utc();
+    pool.change_password(user_id, password_hash, now).await?;
+
+    // the current access token is revoked too, every session logs in with the new password
+    pool.revoke_session(
+        user_id,
+        &claims.jti,
+        auth::expiry_to_datetime(claims.exp)?,
+        None,
+        now,
+    )
+    .await?;
+
+    info!("Password of user (id = {user_id}) changed");
+
+    Ok((
+        session::end(jar),
+        Json(json!({
+            "result": "ok",
+            "message": locale.text("password_changed")
+        })),
+    ))
+}
+
+/// `DELETE /api/me`
+#[utoipa::path(
+    delete,
+    path = "/api/me",
+    tag = "users",
+    summary = "Delete the account of the current user",
+    request_body = DeleteAccountRequest,
+    responses(
+        (status = 200, description = "Account deleted", body = super::openapi::schemas::MessageResponse),
+        (status = 401, description = "Wrong password or an invalid token", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn delete_account(
+    State(pool): State<SharedRepository>,
+    State(media): State<MediaStorage>,
+    State(exports): State<ExportStorage>,
+    Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
+    jar: CookieJar,
+    ValidJson(payload): ValidJson<DeleteAccountRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Account deletion was requested.");
+
+    let user_id = claims.sub;
+    verify_password(&pool, user_id, &payload.password).await?;
+
+    let Some(files) = pool.delete_user(user_id).await? else {
+        return Err(AppError::user_not_found());
+    };
+    media.remove(&files).await;
+    exports.remove_user_archives(user_id).await;
+
+    info!("User (id = {user_id}) deleted their account");
+
+    Ok((
+        session::end(jar),
+        Json(json!({
+            "result": "ok",
+            "message": locale.text("account_deleted")
+        })),
+    ))
+}
+
+async fn verify_password(
+    pool: &SharedRepository,
+    user_id: i32,
+    password: &str,
+) -> Result<(), AppError> {
+    let Some(password_hash) = pool.get_password_hash(user_id).await? else {
+        return Err(AppError::user_not_found());
+    };
+
+    if !password_hash.verify_password(password)? {
+        warn!("Password verification failed for user (id = {user_id})");
+        return Err(AppError::authenthication("wrong_password"));
+    }
+
+    Ok(())
+}
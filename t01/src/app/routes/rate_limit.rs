@@ -0,0 +1,198 @@
+use super::AppError;
+use axum::{
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    middleware::Next,
+    response::Response,
+};
+use log::warn;
+use lru::LruCache;
+use serde::Deserialize;
+use std::{
+    net::{IpAddr, SocketAddr},
+    num::NonZeroUsize,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+/// Upper bound of tracked IP addresses and usernames, the least recently seen are forgotten.
+const TRACKED_KEYS: NonZeroUsize = NonZeroUsize::new(10_000).unwrap();
+
+/// Burst of 10 requests, then one request every 6 seconds.
+const IP_LIMIT: BucketConfig = BucketConfig {
+    capacity: 10.0,
+    refill_interval: Duration::from_secs(6),
+};
+
+/// Burst of 5 requests, then one request every 12 seconds.
+const USERNAME_LIMIT: BucketConfig = BucketConfig {
+    capacity: 5.0,
+    refill_interval: Duration::from_secs(12),
+};
+
+/// Consecutive wrong passwords allowed before the account is locked.
+const LOCKOUT_THRESHOLD: u32 = 5;
+const LOCKOUT_BASE: Duration = Duration::from_secs(30);
+const LOCKOUT_MAX: Duration = Duration::from_secs(60 * 60);
+
+/// Only credentials are expected in the limited requests.
+const MAX_BODY_SIZE: usize = 16 * 1024;
+
+struct BucketConfig {
+    capacity: f64,
+    refill_interval: Duration,
+}
+
+struct TokenBucket {
+    tokens: f64,
+    updated_at: Instant,
+}
+
+impl TokenBucket {
+    fn new(config: &BucketConfig, now: Instant) -> Self {
+        Self {
+            tokens: config.capacity,
+            updated_at: now,
+        }
+    }
+
+    /// Takes one token, or returns time until the next one appears.
+    fn try_acquire(&mut self, config: &BucketConfig, now: Instant) -> Result<(), Duration> {
+        let elapsed = now.duration_since(self.updated_at);
+        let refilled = elapsed.as_secs_f64() / config.refill_interval.as_secs_f64();
+        self.tokens = (self.tokens + refilled).min(config.capacity);
+        self.updated_at = now;
+
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(config.refill_interval.mul_f64(1.0 - self.tokens))
+        }
+    }
+}
+
+struct Lockout {
+    failures: u32,
+    locked_until: Option<Instant>,
+}
+
+struct Limits {
+    by_ip: LruCache<IpAddr, TokenBucket>,
+    by_username: LruCache<String, TokenBucket>,
+    lockouts: LruCache<String, Lockout>,
+}
+
+/// Limits of authentication endpoints, shared between requests.
+#[derive(Clone)]
+pub(crate) struct RateLimiter {
+    limits: Arc<Mutex<Limits>>,
+}
+
+impl RateLimiter {
+    pub(crate) fn new() -> Self {
+        Self {
+            limits: Arc::new(Mutex::new(Limits {
+                by_ip: LruCache::new(TRACKED_KEYS),
+                by_username: LruCache::new(TRACKED_KEYS),
+                lockouts: LruCache::new(TRACKED_KEYS),
+            })),
+        }
+    }
+
+    fn check_ip(&self, ip: IpAddr, now: Instant) -> Result<(), Duration> {
+        let mut limits = self.limits.lock().unwrap();
+        limits
+            .by_ip
+            .get_or_insert_mut(ip, || TokenBucket::new(&IP_LIMIT, now))
+            .try_acquire(&IP_LIMIT, now)
+    }
+
+    fn check_username(&self, username: &str, now: Instant) -> Result<(), Duration> {
+        let mut limits = self.limits.lock().unwrap();
+
+        if let Some(locked_until) = limits
+            .lockouts
+            .get(username)
+            .and_then(|lockout| lockout.locked_until)
+        {
+            if locked_until > now {
+                return Err(locked_until - now);
+            }
+        }
+
+        limits
+            .by_username
+            .get_or_insert_mut(username.to_owned(), || {
+                TokenBucket::new(&USERNAME_LIMIT, now)
+            })
+            .try_acquire(&USERNAME_LIMIT, now)
+    }
+
+    /// Counts a wrong password. Starting from [`LOCKOUT_THRESHOLD`] failures in a row the
+    /// account is locked, and every next failure doubles the lock duration.
+    pub(crate) fn register_failure(&self, username: &str) {
+        let now = Instant::now();
+        let mut limits = self.limits.lock().unwrap();
+
+        let lockout = limits
+            .lockouts
+            .get_or_insert_mut(username.to_owned(), || Lockout {
+                failures: 0,
+                locked_until: None,
+            });
+        lockout.failures += 1;
+
+        if lockout.failures >= LOCKOUT_THRESHOLD {
+            let exponent = (lockout.failures - LOCKOUT_THRESHOLD).min(16);
+            let duration = LOCKOUT_BASE.saturating_mul(1 << exponent).min(LOCKOUT_MAX);
+            warn!(
+                "Account '{username}' is locked for {} seconds after {} failed logins",
+                duration.as_secs(),
+                lockout.failures
+            );
+            lockout.locked_until = Some(now + duration);
+        }
+    }
+
+    pub(crate) fn reset_failures(&self, username: &str) {
+        self.limits.lock().unwrap().lockouts.pop(username);
+    }
+}
+
+#[derive(Deserialize)]
+struct Credentials {
+    username: String,
+}
+
+/// Applies per-IP and per-username limits to the authentication endpoints.
+pub(crate) async fn limit_auth_requests(
+    State(limiter): State<RateLimiter>,
+    req: Request,
+    next: Next,
+) -> Result<Response, AppError> {
+    let now = Instant::now();
+
+    if let Some(ConnectInfo(address)) = req.extensions().get::<ConnectInfo<SocketAddr>>() {
+        if let Err(retry_after) = limiter.check_ip(address.ip(), now) {
+            warn!("Rate limit exceeded for IP: {}", address.ip());
+            return Err(AppError::too_many_requests(retry_after));
+        }
+    }
+
+    let (parts, body) = req.into_parts();
+    let bytes = axum::body::to_bytes(body, MAX_BODY_SIZE)
+        .await
+        .map_err(|_| AppError::bad_request("body_too_large"))?;
+
+    if let Ok(Credentials { username }) = serde_json::from_slice(&bytes) {
+        if let Err(retry_after) = limiter.check_username(&username, now) {
+            warn!("Rate limit exceeded for username: '{username}'");
+            return Err(AppError::too_many_requests(retry_after));
+        }
+    }
+
+    Ok(next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await)
+}
@@ -0,0 +1,89 @@
+use super::{AppError, Claims, SharedRepository};
+use crate::{i18n::Message, model::ReactionKind};
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use log::info;
+use serde_json::json;
+
+/// `POST /api/posts/{post_id}/reactions/{kind}`
+#[utoipa::path(
+    post,
+    path = "/api/posts/{post_id}/reactions/{kind}",
+    tag = "posts",
+    summary = "React to a post, reacting twice with the same kind changes nothing",
+    params(
+        ("post_id" = i32, Path, description = "Id of the post"),
+        ("kind" = ReactionKind, Path, description = "Kind of the reaction"),
+    ),
+    responses(
+        (status = 200, description = "Reaction counts of the post", body = super::openapi::schemas::ReactionsResponse),
+        (status = 400, description = "Unknown kind of reaction", body = super::openapi::schemas::ErrorResponse),
+        (status = 404, description = "Post not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn add_reaction(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path((post_id, kind)): Path<(i32, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Reaction '{kind}' to post with id = {post_id} was requested.");
+
+    let kind = parse_kind(&kind)?;
+
+    let Some(reactions) = pool.add_reaction(claims.sub, post_id, kind).await? else {
+        return Err(AppError::post_not_found());
+    };
+
+    Ok(Json(json!({ "result": "ok", "reactions": reactions })))
+}
+
+/// `DELETE /api/posts/{post_id}/reactions/{kind}`
+#[utoipa::path(
+    delete,
+    path = "/api/posts/{post_id}/reactions/{kind}",
+    tag = "posts",
+    summary = "Take a reaction to a post back",
+    params(
+        ("post_id" = i32, Path, description = "Id of the post"),
+        ("kind" = ReactionKind, Path, description = "Kind of the reaction"),
+    ),
+    responses(
+        (status = 200, description = "Reaction counts of the post", body = super::openapi::schemas::ReactionsResponse),
+        (status = 400, description = "Unknown kind of reaction", body = super::openapi::schemas::ErrorResponse),
+        (status = 404, description = "Post not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn remove_reaction(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path((post_id, kind)): Path<(i32, String)>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Removal of reaction '{kind}' to post with id = {post_id} was requested.");
+
+    let kind = parse_kind(&kind)?;
+
+    let Some(reactions) = pool.remove_reaction(claims.sub, post_id, kind).await? else {
+        return Err(AppError::post_not_found());
+    };
+
+    Ok(Json(json!({ "result": "ok", "reactions": reactions })))
+}
+
+fn parse_kind(kind: &str) -> Result<ReactionKind, AppError> {
+    match kind.parse::<ReactionKind>() {
+        Ok(kind) if ReactionKind::enabled().contains(&kind) => Ok(kind),
+        _ => {
+            let kinds = ReactionKind::enabled()
+                .iter()
+                .map(ReactionKind::as_str)
+                .collect::<Vec<_>>()
+                .join(", ");
+            Err(AppError::bad_request(
+                Message::new("unknown_reaction").arg("kinds", kinds),
+            ))
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use super::{AppError, Claims, SharedRepository};
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use log::info;
+use serde_json::json;
+
+/// `POST /api/posts/{post_id}/repost`
+#[utoipa::path(
+    post,
+    path = "/api/posts/{post_id}/repost",
+    tag = "posts",
+    summary = "Repost a post, reposting twice changes nothing",
+    description = "Reposting a repost shares the original post.",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    responses(
+        (status = 200, description = "Repost of the current user and the new count", body = super::openapi::schemas::RepostResponse),
+        (status = 404, description = "Post not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn repost(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path(post_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Repost of post with id = {post_id} was requested.");
+
+    let Some(state) = pool.repost(claims.sub, post_id).await? else {
+        return Err(AppError::post_not_found());
+    };
+
+    Ok(Json(json!({
+        "result": "ok",
+        "repost_id": state.repost_id,
+        "reposts_count": state.reposts_count,
+    })))
+}
+
+/// `DELETE /api/posts/{post_id}/repost`
+#[utoipa::path(
+    delete,
+    path = "/api/posts/{post_id}/repost",
+    tag = "posts",
+    summary = "Remove the repost of a post",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    responses(
+        (status = 200, description = "Repost removed and the new count", body = super::openapi::schemas::RepostResponse),
+        (status = 404, description = "Post not found", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn remove_repost(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Path(post_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Removal of repost of post with id = {post_id} was requested.");
+
+    let Some(state) = pool.remove_repost(claims.sub, post_id).await? else {
+        return Err(AppError::post_not_found());
+    };
+
+    Ok(Json(json!({
+        "result": "ok",
+        "repost_id": state.repost_id,
+        "reposts_count": state.reposts_count,
+    })))
+}
@@ -0,0 +1,90 @@
+use super::{auth::RefreshToken, AppError};
+use axum::http::{HeaderMap, Method};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use log::warn;
+use rand::RngCore;
+
+pub(crate) const ACCESS_TOKEN_COOKIE: &str = "access_token";
+pub(crate) const REFRESH_TOKEN_COOKIE: &str = "refresh_token";
+/// Readable by scripts, which echo it in [`CSRF_TOKEN_HEADER`].
+pub(crate) const CSRF_TOKEN_COOKIE: &str = "csrf_token";
+pub(crate) const CSRF_TOKEN_HEADER: &str = "x-csrf-token";
+
+/// Refresh token is needed only to refresh tokens and to log out.
+const REFRESH_TOKEN_PATH: &str = "/api";
+
+/// Adds cookies of a new session. Returns the jar and the CSRF token.
+pub(crate) fn start(
+    jar: CookieJar,
+    access_token: String,
+    refresh_token: &RefreshToken,
+) -> (CookieJar, String) {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    let csrf_token = URL_SAFE_NO_PAD.encode(bytes);
+
+    let refresh_lifetime = time::Duration::seconds(
+        (refresh_token.expires_at - chrono::Utc::now().naive_utc()).num_seconds(),
+    );
+
+    let jar = jar
+        .add(
+            Cookie::build((ACCESS_TOKEN_COOKIE, access_token))
+                .path("/")
+                .http_only(true)
+                .secure(true)
+                .same_site(SameSite::Strict)
+                .max_age(time::Duration::seconds(
+                    super::auth::ACCESS_TOKEN_LIFETIME.num_seconds(),
+                )),
+        )
+        .add(
+            Cookie::build((REFRESH_TOKEN_COOKIE, refresh_token.token.clone()))
+                .path(REFRESH_TOKEN_PATH)
+                .http_only(true)
+                .secure(true)
+                .same_site(SameSite::Strict)
+                .max_age(refresh_lifetime),
+        )
+        .add(
+            Cookie::build((CSRF_TOKEN_COOKIE, csrf_token.clone()))
+                .path("/")
+                .secure(true)
+                .same_site(SameSite::Strict)
+                .max_age(refresh_lifetime),
+        );
+
+    (jar, csrf_token)
+}
+
+pub(crate) fn end(jar: CookieJar) -> CookieJar {
+    jar.remove(Cookie::build(ACCESS_TOKEN_COOKIE).path("/"))
+        .remove(Cookie::build(REFRESH_TOKEN_COOKIE).path(REFRESH_TOKEN_PATH))
+        .remove(Cookie::build(CSRF_TOKEN_COOKIE).path("/"))
+}
+
+/// Double-submit check: a state-changing request authorized by cookies must repeat
+/// the CSRF cookie in a header, which other sites cannot read and therefore cannot forge.
+pub(crate) fn verify_csrf(
+    method: &Method,
+    headers: &HeaderMap,
+    jar: &CookieJar,
+) -> Result<(), AppError> {
+    if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+        return Ok(());
+    }
+
+    let cookie = jar.get(CSRF_TOKEN_COOKIE).map(Cookie::value);
+    let header = headers
+        .get(CSRF_TOKEN_HEADER)
+        .and_then(|header| header.to_str().ok());
+
+    match (cookie, header) {
+        (Some(cookie), Some(header)) if !cookie.is_empty() && cookie == header => Ok(()),
+        _ => {
+            warn!("CSRF token is missing or does not match for {method} request");
+            Err(AppError::forbidden("invalid_csrf_token"))
+        }
+    }
+}
@@ -0,0 +1,56 @@
+use super::{AppError, Claims};
+use crate::events::FeedEvents;
+use axum::{
+    extract::State,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse,
+    },
+    Extension,
+};
+use futures_util::{stream, StreamExt};
+use log::{info, warn};
+use tokio::sync::broadcast::error::RecvError;
+
+/// `GET /api/stream`
+///
+/// The stream ends when the access token expires, so a revoked session stops receiving
+/// events. Clients refresh the session and reconnect.
+#[utoipa::path(
+    get,
+    path = "/api/stream",
+    tag = "feed",
+    summary = "Receive post and like updates as Server-Sent Events",
+    responses(
+        (status = 200, description = "Stream of `post_created`, `post_deleted` and `likes_changed` events", content_type = "text/event-stream", body = String),
+    ),
+)]
+pub(super) async fn stream_events(
+    State(events): State<FeedEvents>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Event stream was requested by user (id = {}).", claims.sub);
+
+    let expires_in = super::auth::expiry_to_datetime(claims.exp)?
+        .signed_duration_since(chrono::Utc::now().naive_utc())
+        .to_std()
+        .unwrap_or_default();
+
+    let events = stream::unfold(events.subscribe(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let sse_event = Event::default().event(event.name()).json_data(&event);
+                    return Some((sse_event, receiver));
+                }
+                Err(RecvError::Lagged(skipped)) => {
+                    warn!("Event stream subscriber lagged behind, {skipped} events skipped");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+    .take_until(tokio::time::sleep(expires_in));
+
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
@@ -0,0 +1,1868 @@
+//! End-to-end tests of the router over [`MemoryRepository`]: requests go through
+//! the same middleware, extractors and handlers as in production, only the storage
+//! is replaced.
+
+use super::{initialize_router, openapi::ApiDoc, session};
+use crate::{
+    events::{FeedEvent, FeedEvents},
+    i18n::Locale,
+    model::{ReactionKind, Role},
+    repository::{DraftRepository, ExportRepository, MemoryRepository, PostRepository},
+    storage::{ExportStorage, MediaStorage, MAX_IMAGE_SIZE},
+};
+use axum::{
+    body::Body,
+    http::{header, Method, Request, StatusCode},
+    Router,
+};
+use serde_json::{json, Value};
+use std::{
+    collections::BTreeSet,
+    sync::{Arc, Once},
+};
+use tempfile::TempDir;
+use tower::ServiceExt;
+use utoipa::OpenApi;
+
+const PASSWORD: &str = "password123";
+
+struct TestApp {
+    router: Router,
+    repository: Arc<MemoryRepository>,
+    events: FeedEvents,
+    /// Uploads of the test, the directory is deleted with the app.
+    upload_dir: TempDir,
+    /// Export archives of the test, deleted the same way.
+    export_dir: TempDir,
+}
+
+struct Session {
+    user_id: i32,
+    token: String,
+    refresh_token: String,
+}
+
+struct Response {
+    status: StatusCode,
+    headers: axum::http::HeaderMap,
+    body: Value,
+}
+
+impl TestApp {
+    async fn new() -> Self {
+        static JWT_SECRET: Once = Once::new();
+        JWT_SECRET.call_once(|| std::env::set_var("JWT_SECRET", "secret for tests"));
+
+        let upload_dir = TempDir::with_prefix("t01-tests-").unwrap();
+        let media = MediaStorage::initialize(upload_dir.path().to_path_buf())
+            .await
+            .unwrap();
+        let export_dir = TempDir::with_prefix("t01-exports-").unwrap();
+        let exports =
+            ExportStorage::initialize(export_dir.path().to_path_buf(), chrono::TimeDelta::hours(1))
+                .await
+                .unwrap();
+
+        let events = FeedEvents::local();
+        let repository = Arc::new(MemoryRepository::new(events.clone()));
+        let router = initialize_router(repository.clone(), media, exports, events.clone());
+
+        Self {
+            router,
+            repository,
+            events,
+            upload_dir,
+            export_dir,
+        }
+    }
+
+    async fn send(&self, request: Request<Body>) -> Response {
+        let response = self.router.clone().oneshot(request).await.unwrap();
+
+        let status = response.status();
+        let headers = response.headers().clone();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = serde_json::from_slice(&bytes).unwrap_or(Value::Null);
+
+        Response {
+            status,
+            headers,
+            body,
+        }
+    }
+
+    async fn request(
+        &self,
+        method: Method,
+        uri: &str,
+        token: Option<&str>,
+        body: Option<Value>,
+    ) -> Response {
+        let mut request = Request::builder().method(method).uri(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {token}"));
+        }
+        let request = match body {
+            Some(body) => request
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string())),
+            None => request.body(Body::empty()),
+        };
+
+        self.send(request.unwrap()).await
+    }
+
+    async fn register(&self, username: &str) -> Session {
+        let response = self
+            .request(
+                Method::POST,
+                "/api/register",
+                None,
+                Some(json!({ "username": username, "password": PASSWORD })),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+        self.login(username).await
+    }
+
+    async fn login(&self, username: &str) -> Session {
+        let response = self
+            .request(
+                Method::POST,
+                "/api/login",
+                None,
+                Some(json!({ "username": username, "password": PASSWORD })),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+        let jwt = &response.body["jwt"];
+        Session {
+            user_id: jwt["user_id"].as_i64().unwrap() as i32,
+            token: jwt["token"].as_str().unwrap().to_owned(),
+            refresh_token: jwt["refresh_token"].as_str().unwrap().to_owned(),
+        }
+    }
+
+    async fn create_post(&self, session: &Session, title: &str) -> i32 {
+        let response = self
+            .request(
+                Method::POST,
+                "/api/posts",
+                Some(&session.token),
+                Some(json!({ "title": title, "content": "Some **content**" })),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+        response.body["post_id"].as_i64().unwrap() as i32
+    }
+
+    /// Uploads `files` as the images of the post, each with its file name.
+    async fn upload(&self, session: &Session, post_id: i32, files: &[(&str, &[u8])]) -> Response {
+        const BOUNDARY: &str = "t01-test-boundary";
+
+        let mut body = Vec::new();
+        for (file_name, bytes) in files {
+            body.extend_from_slice(
+                format!(
+                    "--{BOUNDARY}\r\nContent-Disposition: form-data; name=\"images\"; \
+                     filename=\"{file_name}\"\r\nContent-Type: image/png\r\n\r\n"
+                )
+                .as_bytes(),
+            );
+            body.extend_from_slice(bytes);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{BOUNDARY}--\r\n").as_bytes());
+
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(format!("/api/posts/{post_id}/attachments"))
+            .header(header::AUTHORIZATION, format!("Bearer {}", session.token))
+            .header(
+                header::CONTENT_TYPE,
+                format!("multipart/form-data; boundary={BOUNDARY}"),
+            )
+            .body(Body::from(body))
+            .unwrap();
+
+        self.send(request).await
+    }
+
+    /// Names of the files in the upload directory.
+    fn uploaded_files(&self) -> BTreeSet<String> {
+        std::fs::read_dir(self.upload_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect()
+    }
+
+    /// Status of the export once its archive is no longer being built in the background.
+    async fn wait_for_export(&self, session: &Session, export_id: i64) -> Value {
+        let uri = format!("/api/me/export/{export_id}");
+        let mut status = Value::Null;
+        for _ in 0..100 {
+            let response = self
+                .request(Method::GET, &uri, Some(&session.token), None)
+                .await;
+            assert_eq!(response.status, StatusCode::OK);
+            status = response.body["export"]["status"].clone();
+            if status != "pending" {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        status
+    }
+}
+
+#[tokio::test]
+async fn creates_and_lists_posts() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+
+    let post_id = app.create_post(&alice, "First post").await;
+
+    let response = app
+        .request(
+            Method::GET,
+            &format!("/api/posts/{post_id}"),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["post"]["title"], "First post");
+    assert_eq!(response.body["post"]["username"], "alice");
+    assert_eq!(
+        response.body["post"]["content_html"],
+        "<p>Some <strong>content</strong></p>\n"
+    );
+
+    let response = app
+        .request(Method::GET, "/api/posts", Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["posts"].as_array().unwrap().len(), 1);
+    assert_eq!(response.body["posts"][0]["post_id"], post_id);
+}
+
+#[tokio::test]
+async fn paginates_posts_with_cursor() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    for title in ["One", "Two", "Three"] {
+        app.create_post(&alice, title).await;
+    }
+
+    let response = app
+        .request(Method::GET, "/api/posts?limit=2", Some(&alice.token), None)
+        .await;
+    let titles = |body: &Value| {
+        body["posts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|post| post["title"].as_str().unwrap().to_owned())
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(titles(&response.body), ["Three", "Two"]);
+    let cursor = response.body["next_cursor"].as_str().unwrap();
+
+    let response = app
+        .request(
+            Method::GET,
+            &format!("/api/posts?limit=2&cursor={cursor}"),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(titles(&response.body), ["One"]);
+    assert!(response.body["next_cursor"].is_null());
+}
+
+#[tokio::test]
+async fn rejects_requests_without_token() {
+    let app = TestApp::new().await;
+
+    let response = app.request(Method::GET, "/api/posts", None, None).await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert_eq!(response.body["result"], "err");
+
+    let response = app
+        .request(Method::GET, "/api/posts", Some("not a token"), None)
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn validates_registration() {
+    let app = TestApp::new().await;
+    app.register("alice").await;
+
+    let response = app
+        .request(
+            Method::POST,
+            "/api/register",
+            None,
+            Some(json!({ "username": "alice", "password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::CONFLICT);
+
+    let response = app
+        .request(
+            Method::POST,
+            "/api/register",
+            None,
+            Some(json!({ "username": "bad name!", "password": "short" })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert!(response.body["errors"]["username"].is_array());
+    assert!(response.body["errors"]["password"].is_array());
+}
+
+#[tokio::test]
+async fn rejects_wrong_credentials() {
+    let app = TestApp::new().await;
+    app.register("alice").await;
+
+    let response = app
+        .request(
+            Method::POST,
+            "/api/login",
+            None,
+            Some(json!({ "username": "alice", "password": "wrong123" })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .request(
+            Method::POST,
+            "/api/login",
+            None,
+            Some(json!({ "username": "nobody", "password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn limits_login_attempts_per_username() {
+    let app = TestApp::new().await;
+
+    let mut statuses = Vec::new();
+    for _ in 0..6 {
+        let response = app
+            .request(
+                Method::POST,
+                "/api/login",
+                None,
+                Some(json!({ "username": "nobody", "password": PASSWORD })),
+            )
+            .await;
+        statuses.push(response.status);
+    }
+
+    assert_eq!(statuses[..5], [StatusCode::NOT_FOUND; 5]);
+    assert_eq!(statuses[5], StatusCode::TOO_MANY_REQUESTS);
+}
+
+#[tokio::test]
+async fn only_author_edits_and_deletes_post() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let post_id = app.create_post(&alice, "Title").await;
+    let uri = format!("/api/posts/{post_id}");
+
+    let response = app
+        .request(
+            Method::PATCH,
+            &uri,
+            Some(&bob.token),
+            Some(json!({ "title": "Stolen" })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    let response = app
+        .request(Method::DELETE, &uri, Some(&bob.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    let response = app
+        .request(
+            Method::PATCH,
+            &uri,
+            Some(&alice.token),
+            Some(json!({ "title": "Edited" })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["post"]["title"], "Edited");
+    assert!(!response.body["post"]["edited_at"].is_null());
+
+    let response = app
+        .request(
+            Method::GET,
+            &format!("{uri}/revisions"),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["revisions"][0]["title"], "Title");
+
+    let response = app
+        .request(Method::DELETE, &uri, Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app
+        .request(Method::GET, &uri, Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn likes_toggle_and_notify_author() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let post_id = app.create_post(&alice, "Title").await;
+    let uri = format!("/api/posts/{post_id}/likes");
+
+    let response = app
+        .request(Method::POST, &uri, Some(&bob.token), None)
+        .await;
+    assert_eq!(response.body["like"], "Added");
+    assert_eq!(response.body["likes_count"], 1);
+
+    let response = app
+        .request(Method::GET, "/api/notifications", Some(&alice.token), None)
+        .await;
+    assert_eq!(response.body["unread_count"], 1);
+    assert_eq!(response.body["notifications"][0]["kind"], "like");
+    assert_eq!(response.body["notifications"][0]["actor_username"], "bob");
+
+    let response = app
+        .request(Method::POST, &uri, Some(&bob.token), None)
+        .await;
+    assert_eq!(response.body["like"], "Removed");
+    assert_eq!(response.body["likes_count"], 0);
+
+    // an unread notification about an undone like is withdrawn
+    let response = app
+        .request(Method::GET, "/api/notifications", Some(&alice.token), None)
+        .await;
+    assert_eq!(response.body["unread_count"], 0);
+}
+
+#[tokio::test]
+async fn reactions_are_counted_by_kind() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let post_id = app.create_post(&alice, "Title").await;
+    let uri = format!("/api/posts/{post_id}/reactions");
+
+    for token in [&alice.token, &bob.token, &bob.token] {
+        let response = app
+            .request(Method::POST, &format!("{uri}/heart"), Some(token), None)
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+    }
+    let response = app
+        .request(Method::POST, &format!("{uri}/like"), Some(&bob.token), None)
+        .await;
+    assert_eq!(response.body["reactions"]["heart"], 2);
+    assert_eq!(response.body["reactions"]["like"], 1);
+    assert_eq!(response.body["reactions"]["sad"], 0);
+
+    let response = app
+        .request(
+            Method::POST,
+            &format!("{uri}/unicorn"),
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.body["code"], "unknown_reaction");
+
+    // the like endpoint toggles the same reaction
+    let response = app
+        .request(
+            Method::POST,
+            &format!("/api/posts/{post_id}/likes"),
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["like"], "Removed");
+
+    let response = app
+        .request(
+            Method::DELETE,
+            &format!("{uri}/heart"),
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["reactions"]["heart"], 1);
+    assert_eq!(response.body["reactions"]["like"], 0);
+
+    let response = app
+        .request(
+            Method::GET,
+            &format!("/api/posts/{post_id}"),
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["post"]["reactions"]["heart"], 1);
+    assert_eq!(response.body["post"]["likes_count"], 0);
+}
+
+#[test]
+fn reaction_kinds_are_validated() {
+    let kinds = ReactionKind::parse_list(" heart, like,heart ").unwrap();
+    assert_eq!(kinds, [ReactionKind::Like, ReactionKind::Heart]);
+
+    assert!(ReactionKind::parse_list("like,unicorn").is_err());
+    // likes can not be disabled
+    assert!(ReactionKind::parse_list("heart,sad").is_err());
+}
+
+#[tokio::test]
+async fn reposts_and_quotes_follow_original() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let post_id = app.create_post(&alice, "Original").await;
+    let uri = format!("/api/posts/{post_id}/repost");
+
+    let response = app
+        .request(Method::POST, &uri, Some(&bob.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["reposts_count"], 1);
+    let repost_id = response.body["repost_id"].as_i64().unwrap();
+
+    // reposting twice, or reposting the repost, changes nothing
+    for post_id in [post_id as i64, repost_id] {
+        let response = app
+            .request(
+                Method::POST,
+                &format!("/api/posts/{post_id}/repost"),
+                Some(&bob.token),
+                None,
+            )
+            .await;
+        assert_eq!(response.body["repost_id"], repost_id);
+        assert_eq!(response.body["reposts_count"], 1);
+    }
+
+    let response = app
+        .request(
+            Method::POST,
+            "/api/posts",
+            Some(&bob.token),
+            Some(json!({ "title": "Quote", "content": "See", "quote_of_id": repost_id })),
+        )
+        .await;
+    let quote_id = response.body["post_id"].as_i64().unwrap();
+
+    let response = app
+        .request(
+            Method::GET,
+            &format!("/api/posts/{quote_id}"),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["post"]["quote_of_id"], post_id);
+    assert_eq!(response.body["post"]["original"]["title"], "Original");
+    assert_eq!(response.body["post"]["original"]["reposts_count"], 1);
+
+    let response = app
+        .request(
+            Method::GET,
+            &format!("/api/posts/{repost_id}"),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["post"]["repost_of_id"], post_id);
+    assert_eq!(response.body["post"]["username"], "bob");
+    assert_eq!(response.body["post"]["original"]["title"], "Original");
+
+    // reposts are liked through the original post
+    let response = app
+        .request(
+            Method::POST,
+            &format!("/api/posts/{repost_id}/likes"),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    // while the original is in the trash the repost is hidden and the quote loses it
+    let response = app
+        .request(
+            Method::DELETE,
+            &format!("/api/posts/{post_id}"),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app
+        .request(Method::GET, "/api/posts", Some(&alice.token), None)
+        .await;
+    let posts = response.body["posts"].as_array().unwrap();
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0]["post_id"], quote_id);
+    assert!(posts[0]["original"].is_null());
+
+    let response = app
+        .request(Method::POST, &uri, Some(&bob.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    let response = app
+        .request(
+            Method::POST,
+            &format!("/api/me/trash/{post_id}/restore"),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app
+        .request(Method::GET, "/api/posts", Some(&alice.token), None)
+        .await;
+    assert_eq!(response.body["posts"].as_array().unwrap().len(), 3);
+
+    let response = app
+        .request(Method::DELETE, &uri, Some(&bob.token), None)
+        .await;
+    assert!(response.body["repost_id"].is_null());
+    assert_eq!(response.body["reposts_count"], 0);
+}
+
+#[tokio::test]
+async fn bookmarks_are_listed_and_flagged() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let first_id = app.create_post(&alice, "First").await;
+    let second_id = app.create_post(&alice, "Second").await;
+
+    for post_id in [first_id, second_id, second_id] {
+        let response = app
+            .request(
+                Method::POST,
+                &format!("/api/posts/{post_id}/bookmark"),
+                Some(&bob.token),
+                None,
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::OK);
+        assert_eq!(response.body["bookmarked"], true);
+    }
+
+    let response = app
+        .request(
+            Method::GET,
+            "/api/me/bookmarks?limit=1",
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["posts"][0]["post_id"], second_id);
+    assert_eq!(response.body["posts"][0]["bookmarked"], true);
+    let cursor = response.body["next_cursor"].as_str().unwrap().to_owned();
+
+    let response = app
+        .request(
+            Method::GET,
+            &format!("/api/me/bookmarks?limit=1&cursor={cursor}"),
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["posts"][0]["post_id"], first_id);
+    assert!(response.body["next_cursor"].is_null());
+
+    // the flag belongs to the current user
+    let response = app
+        .request(
+            Method::GET,
+            &format!("/api/posts/{first_id}"),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["post"]["bookmarked"], false);
+
+    let response = app
+        .request(
+            Method::DELETE,
+            &format!("/api/posts/{first_id}/bookmark"),
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["bookmarked"], false);
+
+    let response = app
+        .request(Method::GET, "/api/posts", Some(&bob.token), None)
+        .await;
+    let posts = response.body["posts"].as_array().unwrap();
+    assert_eq!(posts[0]["post_id"], second_id);
+    assert_eq!(posts[0]["bookmarked"], true);
+    assert_eq!(posts[1]["bookmarked"], false);
+
+    let response = app
+        .request(
+            Method::POST,
+            "/api/posts/999/bookmark",
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn drafts_stay_private_until_published() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+
+    let response = app
+        .request(
+            Method::POST,
+            "/api/posts",
+            Some(&alice.token),
+            Some(json!({ "title": "Draft", "content": "Later", "draft": true })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let draft_id = response.body["post_id"].as_i64().unwrap();
+
+    let publish_at = chrono::Utc::now() + chrono::TimeDelta::hours(1);
+    let response = app
+        .request(
+            Method::POST,
+            "/api/posts",
+            Some(&alice.token),
+            Some(json!({ "title": "Scheduled", "content": "Soon", "publish_at": publish_at })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let scheduled_id = response.body["post_id"].as_i64().unwrap();
+
+    let response = app
+        .request(Method::GET, "/api/posts", Some(&bob.token), None)
+        .await;
+    assert!(response.body["posts"].as_array().unwrap().is_empty());
+
+    let response = app
+        .request(
+            Method::GET,
+            &format!("/api/posts/{draft_id}"),
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    let response = app
+        .request(
+            Method::POST,
+            &format!("/api/posts/{draft_id}/likes"),
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    // the next to be published comes first
+    let response = app
+        .request(Method::GET, "/api/me/drafts", Some(&alice.token), None)
+        .await;
+    let posts = response.body["posts"].as_array().unwrap();
+    assert_eq!(posts.len(), 2);
+    assert_eq!(posts[0]["post_id"], scheduled_id);
+    assert_eq!(posts[0]["status"], "scheduled");
+    assert_eq!(posts[1]["post_id"], draft_id);
+    assert_eq!(posts[1]["status"], "draft");
+
+    let response = app
+        .request(Method::GET, "/api/me/drafts", Some(&bob.token), None)
+        .await;
+    assert!(response.body["posts"].as_array().unwrap().is_empty());
+
+    // only the author publishes
+    let publish_uri = format!("/api/me/drafts/{draft_id}/publish");
+    let response = app
+        .request(
+            Method::POST,
+            &publish_uri,
+            Some(&bob.token),
+            Some(json!({})),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    let response = app
+        .request(
+            Method::POST,
+            &publish_uri,
+            Some(&alice.token),
+            Some(json!({})),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["status"], "published");
+
+    let response = app
+        .request(
+            Method::POST,
+            &publish_uri,
+            Some(&alice.token),
+            Some(json!({})),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    let response = app
+        .request(Method::GET, "/api/posts", Some(&bob.token), None)
+        .await;
+    let posts = response.body["posts"].as_array().unwrap();
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0]["post_id"], draft_id);
+
+    // scheduled posts wait for their time
+    let published = app
+        .repository
+        .publish_due_posts(chrono::Utc::now().naive_utc())
+        .await
+        .unwrap();
+    assert!(published.is_empty());
+
+    let published = app
+        .repository
+        .publish_due_posts((publish_at + chrono::TimeDelta::seconds(1)).naive_utc())
+        .await
+        .unwrap();
+    assert_eq!(published, vec![scheduled_id as i32]);
+
+    let response = app
+        .request(
+            Method::GET,
+            &format!("/api/posts/{scheduled_id}"),
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["post"]["status"], "published");
+
+    // dated by its publication, ahead of the draft published before
+    let response = app
+        .request(Method::GET, "/api/posts", Some(&bob.token), None)
+        .await;
+    let posts = response.body["posts"].as_array().unwrap();
+    assert_eq!(posts.len(), 2);
+    assert_eq!(posts[0]["post_id"], scheduled_id);
+
+    let response = app
+        .request(Method::GET, "/api/me/drafts", Some(&alice.token), None)
+        .await;
+    assert!(response.body["posts"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn personal_data_export_builds_archive() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let post_id = app.create_post(&alice, "Exported title").await;
+    app.request(
+        Method::POST,
+        &format!("/api/posts/{post_id}/likes"),
+        Some(&alice.token),
+        None,
+    )
+    .await;
+    app.request(
+        Method::POST,
+        &format!("/api/posts/{post_id}/comments"),
+        Some(&alice.token),
+        Some(json!({ "content": "Exported comment" })),
+    )
+    .await;
+
+    let response = app
+        .request(Method::POST, "/api/me/export", Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::ACCEPTED);
+    let export_id = response.body["export"]["export_id"].as_i64().unwrap();
+    let uri = format!("/api/me/export/{export_id}");
+
+    // the archive is built in the background
+    assert_eq!(app.wait_for_export(&alice, export_id).await, "ready");
+
+    // exports are private to their owner
+    let response = app.request(Method::GET, &uri, Some(&bob.token), None).await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    let request = Request::builder()
+        .uri(format!("{uri}/download"))
+        .header(header::AUTHORIZATION, format!("Bearer {}", alice.token))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.router.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[header::CONTENT_TYPE], "application/zip");
+
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+    let read = |archive: &mut zip::ZipArchive<_>, name: &str| {
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut archive.by_name(name).unwrap(), &mut text).unwrap();
+        text
+    };
+    let profile: Value = serde_json::from_str(&read(&mut archive, "profile.json")).unwrap();
+    assert_eq!(profile["username"], "alice");
+    let posts: Value = serde_json::from_str(&read(&mut archive, "posts.json")).unwrap();
+    assert_eq!(posts[0]["title"], "Exported title");
+    let reactions: Value = serde_json::from_str(&read(&mut archive, "reactions.json")).unwrap();
+    assert_eq!(reactions[0]["kind"], "like");
+    let comments: Value = serde_json::from_str(&read(&mut archive, "comments.json")).unwrap();
+    assert_eq!(comments[0]["content"], "Exported comment");
+    assert!(read(&mut archive, "index.html").contains("Exported comment"));
+
+    // expired exports are gone
+    let expired = app
+        .repository
+        .purge_expired_exports(chrono::Utc::now().naive_utc() + chrono::TimeDelta::hours(2))
+        .await
+        .unwrap();
+    assert_eq!(expired.len(), 1);
+
+    let response = app
+        .request(Method::GET, &uri, Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.body["code"], "export_not_found");
+}
+
+#[tokio::test]
+async fn abandoned_export_does_not_block_next_one() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+
+    // started, but its build is gone, as after a restart
+    app.repository.create_export(alice.user_id).await.unwrap();
+
+    let response = app
+        .request(Method::POST, "/api/me/export", Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::ACCEPTED);
+    let abandoned_id = response.body["export"]["export_id"].as_i64().unwrap();
+    assert_eq!(response.body["export"]["status"], "pending");
+
+    let now = chrono::Utc::now().naive_utc();
+    let failed = app
+        .repository
+        .fail_stale_exports(now, now + chrono::TimeDelta::hours(1))
+        .await
+        .unwrap();
+    assert_eq!(failed, 1);
+
+    // a build finishing late leaves the failed export alone
+    let finished = app
+        .repository
+        .finish_export(
+            abandoned_id as i32,
+            Some("late.zip"),
+            now + chrono::TimeDelta::hours(1),
+        )
+        .await
+        .unwrap();
+    assert!(!finished);
+
+    let response = app
+        .request(
+            Method::GET,
+            &format!("/api/me/export/{abandoned_id}"),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["export"]["status"], "failed");
+
+    let response = app
+        .request(Method::POST, "/api/me/export", Some(&alice.token), None)
+        .await;
+    let export_id = response.body["export"]["export_id"].as_i64().unwrap();
+    assert_ne!(export_id, abandoned_id);
+    assert_eq!(app.wait_for_export(&alice, export_id).await, "ready");
+}
+
+#[tokio::test]
+async fn aborted_export_leaves_no_file() {
+    let export_dir = TempDir::with_prefix("t01-exports-").unwrap();
+    let exports =
+        ExportStorage::initialize(export_dir.path().to_path_buf(), chrono::TimeDelta::hours(1))
+            .await
+            .unwrap();
+
+    // the writer runs on until it is told that its build was aborted
+    let (started, writing) = tokio::sync::oneshot::channel();
+    let store = tokio::spawn(async move {
+        exports
+            .store_archive(1, move |_, cancelled| {
+                started.send(()).unwrap();
+                while !cancelled.load(std::sync::atomic::Ordering::Relaxed) {
+                    std::thread::sleep(std::time::Duration::from_millis(5));
+                }
+                Err(anyhow::anyhow!("cancelled"))
+            })
+            .await
+    });
+    writing.await.unwrap();
+    store.abort();
+
+    let user_dir = export_dir.path().join("1");
+    let mut left = usize::MAX;
+    for _ in 0..100 {
+        left = std::fs::read_dir(&user_dir).unwrap().count();
+        if left == 0 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert_eq!(left, 0);
+}
+
+/// PNG image filled with a single color, with an alpha channel or without.
+fn png(width: u32, height: u32, alpha: bool) -> Vec<u8> {
+    let image = if alpha {
+        image::DynamicImage::ImageRgba8(image::RgbaImage::new(width, height))
+    } else {
+        image::DynamicImage::ImageRgb8(image::RgbImage::new(width, height))
+    };
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image.write_to(&mut bytes, image::ImageFormat::Png).unwrap();
+    bytes.into_inner()
+}
+
+#[tokio::test]
+async fn uploads_images_with_thumbnails() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let post_id = app.create_post(&alice, "Title").await;
+
+    let transparent = png(640, 480, true);
+    let opaque = png(100, 50, false);
+    let response = app
+        .upload(
+            &alice,
+            post_id,
+            &[("transparent.png", &transparent), ("opaque.png", &opaque)],
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK, "{}", response.body);
+
+    let attachments = response.body["attachments"].as_array().unwrap();
+    assert_eq!(attachments.len(), 2);
+    assert_eq!(attachments[0]["content_type"], "image/png");
+    assert_eq!(attachments[0]["width"], 640);
+    assert_eq!(attachments[0]["height"], 480);
+    // thumbnails keep transparency as PNG, the rest is JPEG
+    let thumbnail = attachments[0]["thumbnail_name"].as_str().unwrap();
+    assert!(thumbnail.ends_with(".png"), "{thumbnail}");
+    let thumbnail = attachments[1]["thumbnail_name"].as_str().unwrap();
+    assert!(thumbnail.ends_with(".jpg"), "{thumbnail}");
+
+    let files = app.uploaded_files();
+    assert_eq!(files.len(), 4);
+    for attachment in attachments {
+        assert!(files.contains(attachment["file_name"].as_str().unwrap()));
+        assert!(files.contains(attachment["thumbnail_name"].as_str().unwrap()));
+    }
+
+    let thumbnail = attachments[0]["thumbnail_name"].as_str().unwrap();
+    let thumbnail = image::open(app.upload_dir.path().join(thumbnail)).unwrap();
+    assert_eq!((thumbnail.width(), thumbnail.height()), (320, 240));
+}
+
+#[tokio::test]
+async fn rejects_uploads_that_are_not_valid_images() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let post_id = app.create_post(&alice, "Title").await;
+
+    // the format is sniffed from the content, the name does not count
+    let response = app
+        .upload(&alice, post_id, &[("image.png", b"just some text")])
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.body["code"], "unsupported_image_format");
+
+    let oversized = vec![0; MAX_IMAGE_SIZE + 1];
+    let response = app
+        .upload(&alice, post_id, &[("large.png", &oversized)])
+        .await;
+    assert_eq!(response.status, StatusCode::PAYLOAD_TOO_LARGE);
+    assert_eq!(response.body["code"], "image_too_large");
+
+    let too_wide = png(8193, 1, false);
+    let response = app
+        .upload(&alice, post_id, &[("wide.png", &too_wide)])
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.body["code"], "damaged_image");
+
+    // images stored before a rejected one are removed
+    let valid = png(10, 10, false);
+    let response = app
+        .upload(
+            &alice,
+            post_id,
+            &[("valid.png", &valid), ("image.png", b"just some text")],
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert!(app.uploaded_files().is_empty());
+
+    let response = app
+        .request(
+            Method::GET,
+            &format!("/api/posts/{post_id}"),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert!(response.body["post"]["attachments"]
+        .as_array()
+        .unwrap()
+        .is_empty());
+}
+
+#[tokio::test]
+async fn search_highlights_matches_in_escaped_snippets() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let response = app
+        .request(
+            Method::POST,
+            "/api/posts",
+            Some(&alice.token),
+            Some(json!({
+                "title": "Hello <b>there</b>",
+                "content": "<script>alert('hello')</script> & bye",
+            })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    app.create_post(&alice, "Unrelated").await;
+
+    let response = app
+        .request(Method::GET, "/api/search?q=hello", Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let posts = response.body["posts"].as_array().unwrap();
+    assert_eq!(posts.len(), 1);
+    // the markup of the post is escaped, only the matches are wrapped into `<mark>`
+    assert_eq!(
+        posts[0]["title_snippet"],
+        "<mark>Hello</mark> &lt;b&gt;there&lt;/b&gt;"
+    );
+    assert_eq!(
+        posts[0]["content_snippet"],
+        "&lt;script&gt;alert(&#x27;<mark>hello</mark>&#x27;)&lt;/script&gt; &amp; bye"
+    );
+
+    let response = app
+        .request(Method::GET, "/api/search?q=%20", Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+    assert_eq!(response.body["code"], "empty_search_query");
+}
+
+#[tokio::test]
+async fn comments_form_threads() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let post_id = app.create_post(&alice, "Title").await;
+    let uri = format!("/api/posts/{post_id}/comments");
+
+    let response = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&bob.token),
+            Some(json!({ "content": "Question" })),
+        )
+        .await;
+    let comment_id = response.body["comment_id"].as_i64().unwrap();
+
+    let response = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&alice.token),
+            Some(json!({ "content": "Answer", "parent_comment_id": comment_id })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&alice.token),
+            Some(json!({ "content": "Lost", "parent_comment_id": 9999 })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    let response = app.request(Method::GET, &uri, Some(&bob.token), None).await;
+    let comments = response.body["comments"].as_array().unwrap();
+    assert_eq!(comments.len(), 1);
+    assert_eq!(comments[0]["content"], "Question");
+    assert_eq!(comments[0]["replies"][0]["content"], "Answer");
+
+    // the post owner may delete comments of others, replies go with them
+    let response = app
+        .request(
+            Method::DELETE,
+            &format!("{uri}/{comment_id}"),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app.request(Method::GET, &uri, Some(&bob.token), None).await;
+    assert!(response.body["comments"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn feed_contains_followed_users() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let carol = app.register("carol").await;
+    app.create_post(&bob, "By bob").await;
+    app.create_post(&carol, "By carol").await;
+
+    let response = app
+        .request(
+            Method::POST,
+            &format!("/api/users/{}/follow", bob.user_id),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body["is_followed"], true);
+    assert_eq!(response.body["followers_count"], 1);
+
+    let response = app
+        .request(
+            Method::POST,
+            &format!("/api/users/{}/follow", alice.user_id),
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::BAD_REQUEST);
+
+    let response = app
+        .request(Method::GET, "/api/feed", Some(&alice.token), None)
+        .await;
+    let posts = response.body["posts"].as_array().unwrap();
+    assert_eq!(posts.len(), 1);
+    assert_eq!(posts[0]["title"], "By bob");
+}
+
+#[tokio::test]
+async fn refresh_token_reuse_ends_all_sessions() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+
+    let response = app
+        .request(
+            Method::POST,
+            "/api/token/refresh",
+            None,
+            Some(json!({ "refresh_token": alice.refresh_token })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let rotated = response.body["jwt"]["refresh_token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    for refresh_token in [&alice.refresh_token, &rotated] {
+        let response = app
+            .request(
+                Method::POST,
+                "/api/token/refresh",
+                None,
+                Some(json!({ "refresh_token": refresh_token })),
+            )
+            .await;
+        assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    }
+}
+
+#[tokio::test]
+async fn logout_revokes_access_token() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+
+    let response = app
+        .request(
+            Method::POST,
+            "/api/logout",
+            Some(&alice.token),
+            Some(json!({ "refresh_token": alice.refresh_token })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app
+        .request(Method::GET, "/api/posts", Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn cookie_session_requires_csrf_token() {
+    let app = TestApp::new().await;
+    app.register("alice").await;
+
+    let response = app
+        .request(
+            Method::POST,
+            "/api/login",
+            None,
+            Some(json!({ "username": "alice", "password": PASSWORD, "session": "cookie" })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    let csrf_token = response.body["session"]["csrf_token"].as_str().unwrap();
+    let cookies = response
+        .headers
+        .get_all(header::SET_COOKIE)
+        .iter()
+        .map(|cookie| cookie.to_str().unwrap().split(';').next().unwrap())
+        .collect::<Vec<_>>()
+        .join("; ");
+
+    let create_post = |csrf_token: Option<&str>| {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/posts")
+            .header(header::COOKIE, &cookies)
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(csrf_token) = csrf_token {
+            request = request.header(session::CSRF_TOKEN_HEADER, csrf_token);
+        }
+        request
+            .body(Body::from(
+                json!({ "title": "Title", "content": "Content" }).to_string(),
+            ))
+            .unwrap()
+    };
+
+    let response = app.send(create_post(None)).await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    let response = app.send(create_post(Some("forged"))).await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    let response = app.send(create_post(Some(csrf_token))).await;
+    assert_eq!(response.status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn banned_user_loses_access() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let uri = format!("/api/moderation/users/{}/ban", bob.user_id);
+
+    let response = app
+        .request(Method::POST, &uri, Some(&alice.token), Some(json!({})))
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    // the role is checked on every request, the token of alice stays valid
+    app.repository.set_role(alice.user_id, Role::Moderator);
+
+    let response = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&alice.token),
+            Some(json!({ "reason": "Spam" })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app
+        .request(Method::GET, "/api/posts", Some(&bob.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    let response = app
+        .request(
+            Method::POST,
+            "/api/login",
+            None,
+            Some(json!({ "username": "bob", "password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn moderator_delete_skips_trash() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    app.repository.set_role(alice.user_id, Role::Moderator);
+    let post_id = app.create_post(&bob, "Spam").await;
+    let image = png(10, 10, false);
+    let response = app.upload(&bob, post_id, &[("spam.png", &image)]).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app
+        .request(
+            Method::DELETE,
+            &format!("/api/moderation/posts/{post_id}"),
+            Some(&alice.token),
+            Some(json!({ "reason": "Spam" })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    // deleted for good: not in the trash, not restorable, the files are gone
+    let response = app
+        .request(Method::GET, "/api/me/trash", Some(&bob.token), None)
+        .await;
+    assert!(response.body["posts"].as_array().unwrap().is_empty());
+
+    let response = app
+        .request(
+            Method::POST,
+            &format!("/api/me/trash/{post_id}/restore"),
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert!(app.uploaded_files().is_empty());
+
+    let response = app
+        .request(
+            Method::GET,
+            "/api/moderation/actions",
+            Some(&alice.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.body["actions"][0]["action"], "delete_post");
+    assert_eq!(response.body["actions"][0]["target_post_id"], post_id);
+}
+
+#[tokio::test]
+async fn demoted_moderator_loses_powers() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    app.repository.set_role(alice.user_id, Role::Moderator);
+    let alice = app.login("alice").await;
+    let uri = format!("/api/moderation/users/{}/suspend", bob.user_id);
+
+    // the token still says moderator, the demotion applies regardless
+    app.repository.set_role(alice.user_id, Role::User);
+
+    let response = app
+        .request(
+            Method::POST,
+            &uri,
+            Some(&alice.token),
+            Some(json!({ "days": 1, "reason": "Spam" })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    assert_eq!(response.body["code"], "action_forbidden");
+}
+
+#[tokio::test]
+async fn publishes_feed_events() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let mut events = app.events.subscribe();
+
+    let post_id = app.create_post(&alice, "Title").await;
+    app.request(
+        Method::POST,
+        &format!("/api/posts/{post_id}/likes"),
+        Some(&alice.token),
+        None,
+    )
+    .await;
+
+    assert_eq!(
+        events.recv().await.unwrap(),
+        FeedEvent::PostCreated {
+            post_id,
+            user_id: alice.user_id
+        }
+    );
+    assert_eq!(
+        events.recv().await.unwrap(),
+        FeedEvent::LikesChanged {
+            post_id,
+            likes_count: 1
+        }
+    );
+}
+
+#[tokio::test]
+async fn deleted_account_takes_posts_along() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let post_id = app.create_post(&alice, "Title").await;
+
+    let response = app
+        .request(
+            Method::DELETE,
+            "/api/me",
+            Some(&alice.token),
+            Some(json!({ "password": "wrong123" })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+
+    let response = app
+        .request(
+            Method::DELETE,
+            "/api/me",
+            Some(&alice.token),
+            Some(json!({ "password": PASSWORD })),
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app
+        .request(
+            Method::GET,
+            &format!("/api/posts/{post_id}"),
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    let response = app
+        .request(Method::GET, "/api/posts", Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn deleted_posts_go_to_trash() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    let bob = app.register("bob").await;
+    let post_id = app.create_post(&alice, "Title").await;
+    let uri = format!("/api/posts/{post_id}");
+
+    let response = app
+        .request(Method::DELETE, &uri, Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app
+        .request(Method::GET, "/api/posts", Some(&bob.token), None)
+        .await;
+    assert!(response.body["posts"].as_array().unwrap().is_empty());
+
+    let response = app
+        .request(
+            Method::POST,
+            &format!("{uri}/likes"),
+            Some(&bob.token),
+            None,
+        )
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    let response = app
+        .request(Method::GET, "/api/me/trash", Some(&alice.token), None)
+        .await;
+    assert_eq!(response.body["posts"][0]["post_id"], post_id);
+    assert!(!response.body["posts"][0]["deleted_at"].is_null());
+
+    // the trash is private to its owner
+    let restore_uri = format!("/api/me/trash/{post_id}/restore");
+    let response = app
+        .request(Method::POST, &restore_uri, Some(&bob.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    let response = app
+        .request(Method::POST, &restore_uri, Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app.request(Method::GET, &uri, Some(&bob.token), None).await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    let response = app
+        .request(Method::DELETE, &uri, Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+
+    // posts deleted before the cut-off are purged for good
+    let purged_before = chrono::Utc::now().naive_utc() + chrono::TimeDelta::seconds(1);
+    app.repository
+        .purge_deleted_posts(purged_before)
+        .await
+        .unwrap();
+
+    let response = app
+        .request(Method::GET, "/api/me/trash", Some(&alice.token), None)
+        .await;
+    assert!(response.body["posts"].as_array().unwrap().is_empty());
+
+    let response = app
+        .request(Method::POST, &restore_uri, Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+}
+
+/// `(method, path)` of every `/api` route registered in `initialize_router`, read from its
+/// source, with axum `:param` segments written as OpenAPI `{param}`.
+fn router_operations() -> BTreeSet<(String, String)> {
+    const METHODS: [&str; 5] = ["get", "post", "put", "patch", "delete"];
+
+    let source = include_str!("../routes.rs");
+    let start = source.find("fn initialize_router").unwrap();
+    let end = start + source[start..].find("\n}\n").unwrap();
+    let body = &source[start..end];
+
+    let mut operations = BTreeSet::new();
+    for route in body.split(".route(").skip(1) {
+        let path = route.split('"').nth(1).unwrap();
+        if !path.starts_with("/api/") {
+            continue;
+        }
+        let path = path
+            .split('/')
+            .map(|segment| match segment.strip_prefix(':') {
+                Some(param) => format!("{{{param}}}"),
+                None => segment.to_owned(),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+
+        for method in METHODS {
+            let pattern = format!("{method}(");
+            let registered = route.match_indices(&pattern).any(|(index, _)| {
+                !route[..index].ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == ':')
+            });
+            if registered {
+                operations.insert((method.to_owned(), path.clone()));
+            }
+        }
+    }
+
+    operations
+}
+
+#[tokio::test]
+async fn openapi_document_matches_router() {
+    let app = TestApp::new().await;
+    let document = serde_json::to_value(ApiDoc::openapi()).unwrap();
+
+    let documented = document["paths"]
+        .as_object()
+        .unwrap()
+        .iter()
+        .flat_map(|(path, item)| {
+            item.as_object()
+                .unwrap()
+                .keys()
+                .filter(|method| {
+                    ["get", "post", "put", "patch", "delete"].contains(&method.as_str())
+                })
+                .map(move |method| (method.clone(), path.clone()))
+        })
+        .collect::<BTreeSet<_>>();
+
+    let routed = router_operations();
+    assert!(
+        routed.len() > 30,
+        "routes were not found in the router source"
+    );
+    assert_eq!(
+        routed.difference(&documented).collect::<Vec<_>>(),
+        Vec::<&(String, String)>::new(),
+        "routes missing from the OpenAPI document"
+    );
+    assert_eq!(
+        documented.difference(&routed).collect::<Vec<_>>(),
+        Vec::<&(String, String)>::new(),
+        "documented operations without a route"
+    );
+
+    let response = app
+        .request(Method::GET, "/api/openapi.json", None, None)
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, document);
+
+    let response = app.request(Method::GET, "/api/docs/", None, None).await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.headers[header::CONTENT_TYPE]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+}
+
+#[tokio::test]
+async fn errors_have_codes_and_localized_messages() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+
+    let response = app
+        .request(Method::GET, "/api/posts/42", Some(&alice.token), None)
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    assert_eq!(response.body["code"], "post_not_found");
+    assert_eq!(
+        response.body["message"],
+        "The requested post does not exist."
+    );
+    assert_eq!(response.headers[header::CONTENT_LANGUAGE], "en");
+
+    let request = Request::builder()
+        .uri("/api/posts/42")
+        .header(header::AUTHORIZATION, format!("Bearer {}", alice.token))
+        .header(header::ACCEPT_LANGUAGE, "de-DE, ru-RU;q=0.8, en;q=0.5")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.send(request).await;
+    assert_eq!(response.body["code"], "post_not_found");
+    assert_eq!(response.body["message"], "Запрошенный пост не существует.");
+    assert_eq!(response.headers[header::CONTENT_LANGUAGE], "ru");
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/register")
+        .header(header::CONTENT_TYPE, "application/json")
+        .header(header::ACCEPT_LANGUAGE, "ru")
+        .body(Body::from(
+            json!({ "username": "bob", "password": "short1" }).to_string(),
+        ))
+        .unwrap();
+    let response = app.send(request).await;
+    assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+    assert_eq!(response.body["code"], "validation_failed");
+    assert_eq!(
+        response.body["errors"]["password"][0],
+        "Пароль должен содержать не меньше 8 символов."
+    );
+}
+
+#[tokio::test]
+async fn pages_follow_accept_language() {
+    let app = TestApp::new().await;
+
+    for (accept_language, heading) in [
+        ("en-US,en;q=0.9", "<h1>Log in</h1>"),
+        ("ru", "<h1>Вход</h1>"),
+    ] {
+        let request = Request::builder()
+            .uri("/login")
+            .header(header::ACCEPT_LANGUAGE, accept_language)
+            .body(Body::empty())
+            .unwrap();
+        let response = app.router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let html = String::from_utf8(bytes.to_vec()).unwrap();
+        assert!(html.contains(heading), "{html}");
+    }
+}
+
+#[tokio::test]
+async fn user_page_keeps_sort_in_load_more() {
+    let app = TestApp::new().await;
+    let alice = app.register("alice").await;
+    app.create_post(&alice, "First").await;
+    app.create_post(&alice, "Second").await;
+
+    let page = |uri: String| {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        app.router.clone().oneshot(request)
+    };
+    let response = page(format!("/users/{}?sort=most_liked&limit=1", alice.user_id))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let html = String::from_utf8(bytes.to_vec()).unwrap();
+
+    let link = format!("/users/{}?sort=most_liked&cursor=", alice.user_id);
+    let start = html.find(&link).expect(&html) + link.len();
+    let cursor = &html[start..start + html[start..].find('"').unwrap()];
+
+    // the cursor of the `most_liked` order is rejected by the `newest` one
+    let response = page(format!("{link}{cursor}&limit=1")).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[test]
+fn catalogs_define_same_messages() {
+    let [first, rest @ ..] = Locale::ALL;
+    for locale in rest {
+        assert_eq!(
+            first.message_ids(),
+            locale.message_ids(),
+            "catalog '{locale}'"
+        );
+    }
+}
@@ -0,0 +1,84 @@
+use super::{AppError, Claims, SharedRepository};
+use crate::{i18n::Locale, repository::PostRestoreResult, storage::MediaStorage};
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Extension, Json,
+};
+use log::{error, info};
+use serde_json::json;
+use std::time::Duration;
+
+/// How often the trash is checked for posts past the retention period.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// `GET /api/me/trash`
+#[utoipa::path(
+    get,
+    path = "/api/me/trash",
+    tag = "posts",
+    summary = "List deleted posts of the current user, most recently deleted first",
+    responses(
+        (status = 200, description = "Posts in the trash", body = super::openapi::schemas::TrashResponse),
+    ),
+)]
+pub(super) async fn get_trash(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Trash was requested.");
+
+    let posts = pool.get_deleted_posts(claims.sub).await?;
+
+    Ok(Json(json!({ "result": "ok", "posts": posts })))
+}
+
+/// `POST /api/me/trash/{post_id}/restore`
+#[utoipa::path(
+    post,
+    path = "/api/me/trash/{post_id}/restore",
+    tag = "posts",
+    summary = "Restore a deleted post of the current user",
+    params(("post_id" = i32, Path, description = "Id of the post")),
+    responses(
+        (status = 200, description = "Post restored", body = super::openapi::schemas::MessageResponse),
+        (status = 404, description = "Post is not in the trash", body = super::openapi::schemas::ErrorResponse),
+    ),
+)]
+pub(super) async fn restore_post(
+    State(pool): State<SharedRepository>,
+    Extension(claims): Extension<Claims>,
+    Extension(locale): Extension<Locale>,
+    Path(post_id): Path<i32>,
+) -> Result<impl IntoResponse, AppError> {
+    info!("Restore of post with id = {post_id} was requested.");
+
+    match pool.restore_post(post_id, claims.sub).await? {
+        PostRestoreResult::Restored => Ok(Json(json!({
+            "result": "ok",
+            "message": locale.text("post_restored")
+        }))),
+        PostRestoreResult::NotFound => Err(AppError::post_not_found()),
+    }
+}
+
+/// Purges posts that stayed in the trash longer than `retention`, every
+/// [`PURGE_INTERVAL`], together with their attachment files.
+pub(crate) fn spawn_purge(
+    pool: SharedRepository,
+    media: MediaStorage,
+    retention: chrono::TimeDelta,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+
+            let deleted_before = chrono::Utc::now().naive_utc() - retention;
+            match pool.purge_deleted_posts(deleted_before).await {
+                Ok(attachments) => super::attachments::remove_files(&media, attachments).await,
+                Err(err) => error!("Purging the trash failed: {err:?}"),
+            }
+        }
+    });
+}
@@ -0,0 +1,44 @@
+use super::AppError;
+use crate::i18n::Message;
+use axum::{
+    async_trait,
+    extract::{rejection::JsonRejection, FromRequest, Request},
+    Json,
+};
+use log::warn;
+use serde::de::DeserializeOwned;
+use validator::Validate;
+
+/// JSON body which is deserialized and then checked by the rules declared on `T`.
+///
+/// Malformed JSON is rejected with `400 Bad Request`, broken rules with
+/// `422 Unprocessable Entity` listing messages for every invalid field.
+pub(crate) struct ValidJson<T>(pub(crate) T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(payload) =
+            Json::<T>::from_request(req, state)
+                .await
+                .map_err(|rejection: JsonRejection| {
+                    warn!("Malformed JSON body: {}", rejection.body_text());
+                    AppError::bad_request(
+                        Message::new("malformed_json").arg("detail", rejection.body_text()),
+                    )
+                })?;
+
+        if let Err(errors) = payload.validate() {
+            warn!("Request validation failed: {errors}");
+            return Err(AppError::validation(errors));
+        }
+
+        Ok(Self(payload))
+    }
+}
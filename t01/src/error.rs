@@ -1,37 +1,95 @@
 use axum::{response::IntoResponse, Json};
+use fluent_bundle::{FluentArgs, FluentValue};
 use http::StatusCode;
 use log::error;
 
+use crate::{
+    i18n::{Locale, Message},
+    repository::UserBlock,
+};
+
 pub(crate) struct AppError {
     code: StatusCode,
     kind: ErrorKind,
 }
 
-#[derive(Debug, thiserror::Error)]
+/// What went wrong. Every kind has a stable [code](ErrorKind::code) clients can match on,
+/// the message is taken from the catalog of the request locale.
+#[derive(Debug)]
 pub(crate) enum ErrorKind {
-    #[error("{0}")]
-    Registration(String),
+    Registration,
+
+    Authenthication(Message),
+
+    Forbidden(Message),
+
+    Blocked(UserBlock),
+
+    BadRequest(Message),
 
-    #[error("{0}")]
-    Authenthication(String),
+    Validation(validator::ValidationErrors),
 
-    #[error("{0}")]
-    Forbidden(String),
+    TooManyRequests(std::time::Duration),
 
-    #[error(transparent)]
-    JwtToken(#[from] jsonwebtoken::errors::Error),
+    JwtToken(jsonwebtoken::errors::Error),
 
-    #[error("The requested page does not exist.")]
     PageNotFound,
 
-    #[error("The requested post does not exist.")]
     PostNotFound,
 
-    #[error("User does not exist.")]
     UserNotFound,
 
-    #[error(transparent)]
-    Other(#[from] anyhow::Error),
+    CommentNotFound,
+
+    ExportNotFound,
+
+    Other(anyhow::Error),
+}
+
+impl ErrorKind {
+    /// Stable `code` of the JSON body, also the id of the message in the catalogs.
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            Self::Registration => "user_already_exists",
+            Self::Authenthication(message)
+            | Self::Forbidden(message)
+            | Self::BadRequest(message) => message.id(),
+            Self::Blocked(UserBlock::Banned) => "account_banned",
+            Self::Blocked(UserBlock::Suspended { .. }) => "account_suspended",
+            Self::Validation(_) => "validation_failed",
+            Self::TooManyRequests(_) => "too_many_requests",
+            Self::JwtToken(error)
+                if matches!(
+                    error.kind(),
+                    jsonwebtoken::errors::ErrorKind::ExpiredSignature
+                ) =>
+            {
+                "session_expired"
+            }
+            Self::JwtToken(_) => "invalid_token",
+            Self::PageNotFound => "page_not_found",
+            Self::PostNotFound => "post_not_found",
+            Self::UserNotFound => "user_not_found",
+            Self::CommentNotFound => "comment_not_found",
+            Self::ExportNotFound => "export_not_found",
+            Self::Other(_) => "internal_error",
+        }
+    }
+
+    fn message(&self) -> Message {
+        match self {
+            Self::Authenthication(message)
+            | Self::Forbidden(message)
+            | Self::BadRequest(message) => message.clone(),
+            Self::Blocked(UserBlock::Suspended { until }) => {
+                Message::new(self.code()).arg("until", until.format("%Y-%m-%d %H:%M").to_string())
+            }
+            Self::TooManyRequests(retry_after) => {
+                Message::new(self.code()).arg("seconds", retry_after.as_secs())
+            }
+            _ => Message::new(self.code()),
+        }
+    }
 }
 
 impl AppError {
@@ -40,16 +98,13 @@ impl AppError {
     }
 
     pub(crate) fn user_already_exist() -> Self {
-        Self::new(
-            StatusCode::CONFLICT,
-            ErrorKind::Registration("User already exists".to_owned()),
-        )
+        Self::new(StatusCode::CONFLICT, ErrorKind::Registration)
     }
 
-    pub(crate) fn authenthication(message: &str) -> Self {
+    pub(crate) fn authenthication(message: impl Into<Message>) -> Self {
         Self::new(
             StatusCode::UNAUTHORIZED,
-            ErrorKind::Authenthication(message.to_owned()),
+            ErrorKind::Authenthication(message.into()),
         )
     }
 
@@ -69,13 +124,47 @@ impl AppError {
         }
     }
 
-    pub(crate) fn forbidden(message: &str) -> Self {
+    pub(crate) fn forbidden(message: impl Into<Message>) -> Self {
         Self {
             code: StatusCode::FORBIDDEN,
-            kind: ErrorKind::Forbidden(message.to_owned()),
+            kind: ErrorKind::Forbidden(message.into()),
         }
     }
 
+    pub(crate) fn user_blocked(block: &UserBlock) -> Self {
+        Self::new(StatusCode::FORBIDDEN, ErrorKind::Blocked(*block))
+    }
+
+    pub(crate) fn validation(errors: validator::ValidationErrors) -> Self {
+        Self::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            ErrorKind::Validation(errors),
+        )
+    }
+
+    pub(crate) fn too_many_requests(retry_after: std::time::Duration) -> Self {
+        // round up, so that the client does not retry a moment too early
+        let retry_after = std::time::Duration::from_secs(retry_after.as_secs() + 1);
+        Self::new(
+            StatusCode::TOO_MANY_REQUESTS,
+            ErrorKind::TooManyRequests(retry_after),
+        )
+    }
+
+    pub(crate) fn bad_request(message: impl Into<Message>) -> Self {
+        Self::new(
+            StatusCode::BAD_REQUEST,
+            ErrorKind::BadRequest(message.into()),
+        )
+    }
+
+    pub(crate) fn payload_too_large(message: impl Into<Message>) -> Self {
+        Self::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            ErrorKind::BadRequest(message.into()),
+        )
+    }
+
     pub(crate) fn page_not_found() -> Self {
         Self::new(StatusCode::NOT_FOUND, ErrorKind::PageNotFound)
     }
@@ -87,35 +176,97 @@ impl AppError {
     pub(crate) fn user_not_found() -> Self {
         Self::new(StatusCode::NOT_FOUND, ErrorKind::UserNotFound)
     }
+
+    pub(crate) fn comment_not_found() -> Self {
+        Self::new(StatusCode::NOT_FOUND, ErrorKind::CommentNotFound)
+    }
+
+    pub(crate) fn export_not_found() -> Self {
+        Self::new(StatusCode::NOT_FOUND, ErrorKind::ExportNotFound)
+    }
+
+    /// The export is still being built or failed, so there is nothing to download.
+    pub(crate) fn export_not_ready() -> Self {
+        Self::new(
+            StatusCode::CONFLICT,
+            ErrorKind::BadRequest(Message::new("export_not_ready")),
+        )
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> axum::response::Response {
         let code = self.code;
-        let message = match self.kind {
-            ErrorKind::JwtToken(jwt_error)
-                if matches!(
-                    jwt_error.kind(),
-                    jsonwebtoken::errors::ErrorKind::ExpiredSignature
-                ) =>
-            {
-                "Session expired. Please, authorize again.".to_owned()
-            }
-            ErrorKind::Other(err) => {
-                error!("{err:?}");
-                "Something went wrong.".to_owned()
-            }
-            _ => self.kind.to_string(),
+        let locale = Locale::current();
+        let retry_after = match &self.kind {
+            ErrorKind::TooManyRequests(retry_after) => Some(retry_after.as_secs()),
+            _ => None,
         };
-        let json = serde_json::json!({
+        let errors = match &self.kind {
+            ErrorKind::Validation(errors) => Some(field_messages(errors, locale)),
+            _ => None,
+        };
+        if let ErrorKind::Other(err) = &self.kind {
+            error!("{err:?}");
+        }
+
+        let mut json = serde_json::json!({
             "result": "err",
-            "message": message
+            "code": self.kind.code(),
+            "message": self.kind.message().format(locale)
         });
+        if let Some(errors) = errors {
+            json["errors"] = errors;
+        }
 
-        (code, Json(json)).into_response()
+        let mut response = (code, Json(json)).into_response();
+        if let Some(retry_after) = retry_after {
+            response
+                .headers_mut()
+                .insert(http::header::RETRY_AFTER, retry_after.into());
+        }
+        response
     }
 }
 
+/// `{"field": ["message", ...]}` for every invalid field of a request.
+///
+/// The `message` of a rule is an id in the catalogs, formatted with the parameters of the
+/// rule, like `max` of `length`.
+fn field_messages(errors: &validator::ValidationErrors, locale: Locale) -> serde_json::Value {
+    let fields = errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let messages = errors
+                .iter()
+                .map(|error| match &error.message {
+                    Some(id) => {
+                        let args = error
+                            .params
+                            .iter()
+                            .filter_map(|(name, value)| match value {
+                                serde_json::Value::Number(number) => {
+                                    Some((name.as_ref(), FluentValue::from(number.as_f64()?)))
+                                }
+                                _ => None,
+                            })
+                            .collect::<FluentArgs>();
+                        locale.format(id, Some(&args))
+                    }
+                    None => {
+                        let args = FluentArgs::from_iter([("rule", error.code.to_string())]);
+                        locale.format("invalid_value", Some(&args))
+                    }
+                })
+                .collect::<Vec<_>>();
+            (field.to_string(), serde_json::json!(messages))
+        })
+        .collect::<serde_json::Map<_, _>>();
+
+    serde_json::Value::Object(fields)
+}
+
 impl<E> From<E> for AppError
 where
     E: Into<anyhow::Error>,
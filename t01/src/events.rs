@@ -0,0 +1,133 @@
+use anyhow::Result;
+use futures_util::{stream, StreamExt};
+use log::{error, info, warn};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_postgres::AsyncMessage;
+
+/// Postgres channel with feed events. Events are sent with `pg_notify` inside the transactions
+/// that change the feed, so every server instance receives exactly the committed changes.
+pub(crate) const FEED_EVENTS_CHANNEL: &str = "feed_events";
+
+/// Events that are not received by a slow subscriber in time are dropped for it.
+const SUBSCRIBER_BUFFER: usize = 256;
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Change of the feed pushed to the clients of `/api/stream`.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub(crate) enum FeedEvent {
+    PostCreated { post_id: i32, user_id: i32 },
+    PostDeleted { post_id: i32 },
+    LikesChanged { post_id: i32, likes_count: i64 },
+}
+
+impl FeedEvent {
+    /// Name of the SSE event.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            FeedEvent::PostCreated { .. } => "post_created",
+            FeedEvent::PostDeleted { .. } => "post_deleted",
+            FeedEvent::LikesChanged { .. } => "likes_changed",
+        }
+    }
+}
+
+/// Fan-out of the feed events received from Postgres to the local subscribers.
+#[derive(Clone)]
+pub(crate) struct FeedEvents {
+    sender: broadcast::Sender<FeedEvent>,
+}
+
+impl FeedEvents {
+    /// Starts listening to [`FEED_EVENTS_CHANNEL`] on a dedicated connection, the pooled ones
+    /// are returned to the pool between queries and would lose the subscription.
+    pub(crate) fn listen(config: tokio_postgres::Config) -> Self {
+        let (sender, _) = broadcast::channel(SUBSCRIBER_BUFFER);
+
+        tokio::spawn({
+            let sender = sender.clone();
+            async move {
+                loop {
+                    if let Err(err) = forward_notifications(&config, &sender).await {
+                        error!("Listening to feed events failed: {err}");
+                    }
+                    warn!(
+                        "Reconnecting to feed events in {} seconds",
+                        RECONNECT_DELAY.as_secs()
+                    );
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        });
+
+        Self { sender }
+    }
+
+    /// Events that are not backed by Postgres: they are published directly by the
+    /// in-memory repository of the tests.
+    #[cfg(test)]
+    pub(crate) fn local() -> Self {
+        let (sender, _) = broadcast::channel(SUBSCRIBER_BUFFER);
+        Self { sender }
+    }
+
+    #[cfg(test)]
+    pub(crate) fn publish(&self, event: FeedEvent) {
+        // no subscribers is not an error
+        let _ = self.sender.send(event);
+    }
+
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<FeedEvent> {
+        self.sender.subscribe()
+    }
+}
+
+async fn forward_notifications(
+    config: &tokio_postgres::Config,
+    sender: &broadcast::Sender<FeedEvent>,
+) -> Result<()> {
+    let (client, mut connection) = config.connect(tokio_postgres::NoTls).await?;
+
+    // the connection yields notifications only while it is polled
+    let (notifications_sender, mut notifications) = tokio::sync::mpsc::unbounded_channel();
+    let connection = tokio::spawn(async move {
+        let mut messages = stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(message) = messages.next().await {
+            let forwarded = match message? {
+                AsyncMessage::Notification(notification) => {
+                    notifications_sender.send(notification).is_ok()
+                }
+                AsyncMessage::Notice(notice) => {
+                    info!("Notice from database: {notice}");
+                    true
+                }
+                _ => true,
+            };
+            if !forwarded {
+                break;
+            }
+        }
+        Ok::<_, tokio_postgres::Error>(())
+    });
+
+    client
+        .batch_execute(&format!("listen {FEED_EVENTS_CHANNEL};"))
+        .await?;
+    info!("Listening to feed events");
+
+    while let Some(notification) = notifications.recv().await {
+        match serde_json::from_str::<FeedEvent>(notification.payload()) {
+            // no subscribers is not an error
+            Ok(event) => {
+                let _ = sender.send(event);
+            }
+            Err(err) => warn!("Invalid feed event '{}': {err}", notification.payload()),
+        }
+    }
+
+    connection.await??;
+
+    Err(anyhow::anyhow!("Connection to database was closed"))
+}
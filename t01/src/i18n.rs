@@ -0,0 +1,181 @@
+use fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource, FluentValue};
+use fluent_langneg::{accepted_languages, negotiate_languages, NegotiationStrategy};
+use log::warn;
+use std::{fmt, sync::LazyLock};
+use unic_langid::{langid, LanguageIdentifier};
+
+/// Language of the messages in API responses and of the pages.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Locale {
+    #[default]
+    En,
+    Ru,
+}
+
+/// Message catalogs, in the order of [`Locale::ALL`].
+static CATALOGS: LazyLock<Vec<FluentBundle<FluentResource>>> =
+    LazyLock::new(|| Locale::ALL.iter().map(|locale| locale.load()).collect());
+
+tokio::task_local! {
+    /// Locale of the request being handled, see [`Locale::scope`].
+    static REQUEST_LOCALE: Locale;
+}
+
+impl Locale {
+    pub(crate) const ALL: [Self; 2] = [Self::En, Self::Ru];
+
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::En => "en",
+            Self::Ru => "ru",
+        }
+    }
+
+    fn language(self) -> LanguageIdentifier {
+        match self {
+            Self::En => langid!("en"),
+            Self::Ru => langid!("ru"),
+        }
+    }
+
+    fn source(self) -> &'static str {
+        match self {
+            Self::En => include_str!("../locales/en.ftl"),
+            Self::Ru => include_str!("../locales/ru.ftl"),
+        }
+    }
+
+    fn load(self) -> FluentBundle<FluentResource> {
+        let resource = FluentResource::try_new(self.source().to_owned())
+            .unwrap_or_else(|(_, errors)| panic!("catalog '{self}' is invalid: {errors:?}"));
+
+        let mut bundle = FluentBundle::new_concurrent(vec![self.language()]);
+        // the messages end up in JSON and HTML attributes, where isolation marks are noise
+        bundle.set_use_isolating(false);
+        bundle
+            .add_resource(resource)
+            .unwrap_or_else(|errors| panic!("catalog '{self}' is invalid: {errors:?}"));
+        bundle
+    }
+
+    /// The best supported match for an `Accept-Language` header, English by default.
+    pub(crate) fn negotiate(accept_language: &str) -> Self {
+        let requested = accepted_languages::parse(accept_language);
+        let available = Self::ALL.map(Self::language);
+        let default = Self::default().language();
+
+        let supported = negotiate_languages(
+            &requested,
+            &available,
+            Some(&default),
+            NegotiationStrategy::Lookup,
+        );
+
+        supported
+            .first()
+            .and_then(|language| {
+                Self::ALL
+                    .into_iter()
+                    .find(|locale| locale.language() == **language)
+            })
+            .unwrap_or_default()
+    }
+
+    /// Runs `future` with `self` as [`Locale::current`].
+    pub(crate) async fn scope<F: std::future::Future>(self, future: F) -> F::Output {
+        REQUEST_LOCALE.scope(self, future).await
+    }
+
+    /// Locale of the request being handled, for code that has no access to the request,
+    /// like the conversion of errors into responses.
+    pub(crate) fn current() -> Self {
+        REQUEST_LOCALE
+            .try_with(|locale| *locale)
+            .unwrap_or_default()
+    }
+
+    /// Message without arguments.
+    pub(crate) fn text(self, id: &str) -> String {
+        self.format(id, None)
+    }
+
+    /// Message with arguments. An unknown `id` is returned as is.
+    pub(crate) fn format(self, id: &str, args: Option<&FluentArgs>) -> String {
+        let bundle = &CATALOGS[self as usize];
+        let Some(pattern) = bundle.get_message(id).and_then(|message| message.value()) else {
+            warn!("Message '{id}' is missing in catalog '{self}'");
+            return id.to_owned();
+        };
+
+        let mut errors = Vec::new();
+        let text = bundle.format_pattern(pattern, args, &mut errors);
+        if !errors.is_empty() {
+            warn!("Message '{id}' of catalog '{self}' is formatted with errors: {errors:?}");
+        }
+        text.into_owned()
+    }
+
+    /// Ids of all messages of the catalog.
+    #[cfg(test)]
+    pub(crate) fn message_ids(self) -> std::collections::BTreeSet<&'static str> {
+        self.source()
+            .lines()
+            .filter_map(|line| line.split_once(" =").map(|(id, _)| id))
+            .filter(|id| !id.is_empty() && !id.starts_with([' ', '#', '-']))
+            .collect()
+    }
+}
+
+impl fmt::Display for Locale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Catalog message with its arguments, formatted once the locale is known.
+#[derive(Debug, Clone)]
+pub(crate) struct Message {
+    id: &'static str,
+    args: Vec<(&'static str, FluentValue<'static>)>,
+}
+
+impl Message {
+    pub(crate) fn new(id: &'static str) -> Self {
+        Self {
+            id,
+            args: Vec::new(),
+        }
+    }
+
+    pub(crate) fn arg(
+        mut self,
+        name: &'static str,
+        value: impl Into<FluentValue<'static>>,
+    ) -> Self {
+        self.args.push((name, value.into()));
+        self
+    }
+
+    pub(crate) fn id(&self) -> &'static str {
+        self.id
+    }
+
+    pub(crate) fn format(&self, locale: Locale) -> String {
+        if self.args.is_empty() {
+            return locale.text(self.id);
+        }
+
+        let args = self
+            .args
+            .iter()
+            .map(|(name, value)| (*name, value.clone()))
+            .collect::<FluentArgs>();
+        locale.format(self.id, Some(&args))
+    }
+}
+
+impl From<&'static str> for Message {
+    fn from(id: &'static str) -> Self {
+        Self::new(id)
+    }
+}
@@ -1,11 +1,15 @@
 use anyhow::Result;
 use log::info;
-use std::net::Ipv4Addr;
+use std::{net::Ipv4Addr, path::PathBuf};
 
 mod app;
 mod error;
+mod events;
+mod i18n;
+mod markdown;
 mod model;
 mod repository;
+mod storage;
 mod utils;
 
 pub async fn run() -> Result<()> {
@@ -24,6 +28,14 @@ pub async fn run() -> Result<()> {
 pub(crate) struct Config {
     address: (Ipv4Addr, u16),
     database_config: deadpool_postgres::Config,
+    upload_dir: PathBuf,
+    /// How long deleted posts stay in the trash before they are purged.
+    trash_retention: chrono::TimeDelta,
+    export_dir: PathBuf,
+    /// How long a personal data export can be downloaded.
+    export_retention: chrono::TimeDelta,
+    /// Kinds of reactions offered to users.
+    reaction_kinds: Vec<model::ReactionKind>,
 }
 
 impl Config {
@@ -60,9 +72,39 @@ impl Config {
             database_config
         };
 
+        let upload_dir = match std::env::var("UPLOAD_DIR") {
+            Ok(upload_dir) => PathBuf::from(upload_dir),
+            Err(_) => PathBuf::from("uploads"),
+        };
+
+        let trash_retention = match std::env::var("TRASH_RETENTION_DAYS") {
+            Ok(days) => chrono::TimeDelta::days(days.parse::<u16>()?.into()),
+            Err(_) => chrono::TimeDelta::days(30),
+        };
+
+        let export_dir = match std::env::var("EXPORT_DIR") {
+            Ok(export_dir) => PathBuf::from(export_dir),
+            Err(_) => PathBuf::from("exports"),
+        };
+
+        let export_retention = match std::env::var("EXPORT_RETENTION_HOURS") {
+            Ok(hours) => chrono::TimeDelta::hours(hours.parse::<u16>()?.into()),
+            Err(_) => chrono::TimeDelta::hours(24),
+        };
+
+        let reaction_kinds = match std::env::var("REACTION_KINDS") {
+            Ok(kinds) => model::ReactionKind::parse_list(&kinds)?,
+            Err(_) => model::ReactionKind::ALL.to_vec(),
+        };
+
         Ok(Self {
             address,
             database_config,
+            upload_dir,
+            trash_retention,
+            export_dir,
+            export_retention,
+            reaction_kinds,
         })
     }
 }
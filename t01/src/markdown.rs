@@ -0,0 +1,98 @@
+use pulldown_cmark::{Event, Parser, Tag, TagEnd};
+use std::{collections::HashSet, sync::LazyLock};
+
+/// Tags of the supported Markdown subset: paragraphs, links, emphasis, code and lists.
+/// Everything else, like headings or images, is reduced to its text.
+const ALLOWED_TAGS: [&str; 11] = [
+    "p",
+    "br",
+    "a",
+    "em",
+    "strong",
+    "code",
+    "pre",
+    "ul",
+    "ol",
+    "li",
+    "blockquote",
+];
+const ALLOWED_URL_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+static SANITIZER: LazyLock<ammonia::Builder<'static>> = LazyLock::new(|| {
+    let mut sanitizer = ammonia::Builder::empty();
+    sanitizer
+        .add_tags(ALLOWED_TAGS)
+        .add_tag_attributes("a", ["href", "title"])
+        .add_tag_attributes("ol", ["start"])
+        .url_schemes(HashSet::from(ALLOWED_URL_SCHEMES))
+        .link_rel(Some("nofollow noopener noreferrer"));
+    sanitizer
+});
+
+/// Renders Markdown source of a post to HTML that is safe to embed in a page.
+///
+/// HTML written in the source is shown as text, and the rendered HTML is sanitized anyway,
+/// so neither the source nor a bug of the renderer can inject scripts.
+pub(crate) fn render(source: &str) -> String {
+    // the alt text of an image stays between its start and end events
+    let parser = Parser::new(source).filter_map(|event| match event {
+        Event::Html(html) | Event::InlineHtml(html) => Some(Event::Text(html)),
+        Event::Start(Tag::Image { .. }) | Event::End(TagEnd::Image) => None,
+        event => Some(event),
+    });
+
+    let mut html = String::with_capacity(source.len() * 3 / 2);
+    pulldown_cmark::html::push_html(&mut html, parser);
+
+    SANITIZER.clean(&html).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+
+    #[test]
+    fn html_in_source_is_shown_as_text() {
+        let html = render("<script>alert(1)</script>");
+        assert!(!html.contains("<script"), "{html}");
+        assert!(
+            html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"),
+            "{html}"
+        );
+
+        let html = render("Look <img src=x onerror=alert(1)>");
+        assert!(!html.contains("<img"), "{html}");
+        assert!(
+            html.contains("&lt;img src=x onerror=alert(1)&gt;"),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn links_with_unsafe_schemes_lose_href() {
+        let html = render("[x](javascript:alert(1))");
+        assert!(!html.contains("javascript"), "{html}");
+        assert!(!html.contains("href"), "{html}");
+        assert!(html.contains(">x</a>"), "{html}");
+    }
+
+    #[test]
+    fn links_do_not_pass_referrer_or_rank() {
+        let html = render("[site](https://example.com)");
+        assert!(
+            html.contains(
+                r#"<a href="https://example.com" rel="nofollow noopener noreferrer">site</a>"#
+            ),
+            "{html}"
+        );
+    }
+
+    #[test]
+    fn unsupported_elements_are_reduced_to_text() {
+        let html = render("# Heading\n\n![alt text](https://example.com/image.png)");
+        assert!(!html.contains("<h1"), "{html}");
+        assert!(!html.contains("<img"), "{html}");
+        assert!(html.contains("Heading"), "{html}");
+        assert!(html.contains("alt text"), "{html}");
+    }
+}
@@ -1,37 +1,584 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use serde::{Deserialize, Serialize};
+use std::{borrow::Cow, str::FromStr, sync::OnceLock};
+use utoipa::{IntoParams, ToSchema};
+use validator::{Validate, ValidationError};
 
 #[derive(Debug, Deserialize, Serialize, Clone)]
 pub(crate) struct Claims {
     pub(crate) sub: i32,
     pub(crate) exp: usize,
+    pub(crate) jti: String,
+    pub(crate) role: Role,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Role of user, ordered by privileges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum Role {
+    User,
+    Moderator,
+    Admin,
+}
+
+impl Role {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Moderator => "moderator",
+            Role::Admin => "admin",
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = anyhow::Error;
+
+    fn from_str(role: &str) -> Result<Self, Self::Err> {
+        match role {
+            "user" => Ok(Role::User),
+            "moderator" => Ok(Role::Moderator),
+            "admin" => Ok(Role::Admin),
+            _ => Err(anyhow::anyhow!("Unknown role: '{role}'")),
+        }
+    }
+}
+
+/// Kinds of reactions offered, set once from `REACTION_KINDS` at startup.
+static ENABLED_REACTIONS: OnceLock<Vec<ReactionKind>> = OnceLock::new();
+
+/// Reaction to a post. The variants are the known kinds, the offered ones are chosen by
+/// `REACTION_KINDS`, see [`ReactionKind::enabled`].
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize, Serialize, ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ReactionKind {
+    Like,
+    Heart,
+    Laugh,
+    Wow,
+    Sad,
+}
+
+impl ReactionKind {
+    pub(crate) const ALL: [Self; 5] = [Self::Like, Self::Heart, Self::Laugh, Self::Wow, Self::Sad];
+
+    /// Parses a comma separated list of kinds, like `like,heart`. `like` can not be left
+    /// out, likes have endpoints of their own and order the `most_liked` feed.
+    pub(crate) fn parse_list(kinds: &str) -> anyhow::Result<Vec<Self>> {
+        let mut enabled = Vec::new();
+        for kind in kinds
+            .split(',')
+            .map(str::trim)
+            .filter(|kind| !kind.is_empty())
+        {
+            let kind = kind.parse::<Self>()?;
+            if !enabled.contains(&kind) {
+                enabled.push(kind);
+            }
+        }
+        if !enabled.contains(&Self::Like) {
+            return Err(anyhow::anyhow!("Reaction 'like' can not be disabled"));
+        }
+        enabled.sort();
+        Ok(enabled)
+    }
+
+    /// Offers only `kinds` from now on, the first call wins.
+    pub(crate) fn enable(kinds: Vec<Self>) {
+        if ENABLED_REACTIONS.set(kinds).is_err() {
+            log::warn!("Reaction kinds are set already");
+        }
+    }
+
+    /// Kinds offered to users, all of them unless [`ReactionKind::enable`] chose others.
+    pub(crate) fn enabled() -> &'static [Self] {
+        ENABLED_REACTIONS.get().map_or(&Self::ALL, Vec::as_slice)
+    }
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ReactionKind::Like => "like",
+            ReactionKind::Heart => "heart",
+            ReactionKind::Laugh => "laugh",
+            ReactionKind::Wow => "wow",
+            ReactionKind::Sad => "sad",
+        }
+    }
+
+    pub(crate) fn emoji(&self) -> &'static str {
+        match self {
+            ReactionKind::Like => "👍",
+            ReactionKind::Heart => "❤️",
+            ReactionKind::Laugh => "😂",
+            ReactionKind::Wow => "😮",
+            ReactionKind::Sad => "😢",
+        }
+    }
+}
+
+impl FromStr for ReactionKind {
+    type Err = anyhow::Error;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|reaction| reaction.as_str() == kind)
+            .ok_or_else(|| anyhow::anyhow!("Unknown reaction: '{kind}'"))
+    }
+}
+
+/// Whether a post is shown to the other users.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PostStatus {
+    /// Kept by the author until they publish it.
+    Draft,
+    /// Published by the background task at `publish_at`.
+    Scheduled,
+    Published,
+}
+
+impl PostStatus {
+    pub(crate) const ALL: [Self; 3] = [Self::Draft, Self::Scheduled, Self::Published];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            PostStatus::Draft => "draft",
+            PostStatus::Scheduled => "scheduled",
+            PostStatus::Published => "published",
+        }
+    }
+}
+
+impl FromStr for PostStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|post_status| post_status.as_str() == status)
+            .ok_or_else(|| anyhow::anyhow!("Unknown post status: '{status}'"))
+    }
+}
+
+/// Progress of a personal data export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum ExportStatus {
+    /// The archive is being built.
+    Pending,
+    /// The archive can be downloaded until `expires_at`.
+    Ready,
+    Failed,
+}
+
+impl ExportStatus {
+    pub(crate) const ALL: [Self; 3] = [Self::Pending, Self::Ready, Self::Failed];
+
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            ExportStatus::Pending => "pending",
+            ExportStatus::Ready => "ready",
+            ExportStatus::Failed => "failed",
+        }
+    }
+}
+
+impl FromStr for ExportStatus {
+    type Err = anyhow::Error;
+
+    fn from_str(status: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .into_iter()
+            .find(|export_status| export_status.as_str() == status)
+            .ok_or_else(|| anyhow::anyhow!("Unknown export status: '{status}'"))
+    }
+}
+
+/// Social action that is reported to the affected user.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum NotificationKind {
+    Like,
+    Comment,
+    Reply,
+    Follow,
+}
+
+impl NotificationKind {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            NotificationKind::Like => "like",
+            NotificationKind::Comment => "comment",
+            NotificationKind::Reply => "reply",
+            NotificationKind::Follow => "follow",
+        }
+    }
+}
+
+impl FromStr for NotificationKind {
+    type Err = anyhow::Error;
+
+    fn from_str(kind: &str) -> Result<Self, Self::Err> {
+        match kind {
+            "like" => Ok(NotificationKind::Like),
+            "comment" => Ok(NotificationKind::Comment),
+            "reply" => Ok(NotificationKind::Reply),
+            "follow" => Ok(NotificationKind::Follow),
+            _ => Err(anyhow::anyhow!("Unknown notification kind: '{kind}'")),
+        }
+    }
+}
+
+const MIN_USERNAME_LENGTH: usize = 3;
+const MAX_USERNAME_LENGTH: usize = 32;
+const MIN_PASSWORD_LENGTH: usize = 8;
+/// bcrypt ignores everything after the first 72 bytes of a password.
+const MAX_PASSWORD_BYTES: usize = 72;
+const MAX_TITLE_LENGTH: u64 = 200;
+const MAX_CONTENT_LENGTH: u64 = 20_000;
+const MAX_COMMENT_LENGTH: u64 = 5_000;
+const MAX_REASON_LENGTH: u64 = 500;
+const MAX_SUSPENSION_DAYS: i64 = 365;
+const MAX_READ_NOTIFICATIONS: u64 = 100;
+const MAX_DISPLAY_NAME_LENGTH: u64 = 50;
+const MAX_BIO_LENGTH: u64 = 500;
+
+/// `message` is the id of the message in the catalogs, `params` are its arguments.
+fn validation_error(
+    code: &'static str,
+    message: &'static str,
+    params: &[(&'static str, usize)],
+) -> ValidationError {
+    let mut error = ValidationError::new(code).with_message(Cow::Borrowed(message));
+    for (name, value) in params {
+        error.add_param(Cow::Borrowed(name), value);
+    }
+    error
+}
+
+/// Letters, digits, `_` and `-`, from 3 to 32 characters.
+fn validate_username(username: &str) -> Result<(), ValidationError> {
+    let length = username.chars().count();
+    if !(MIN_USERNAME_LENGTH..=MAX_USERNAME_LENGTH).contains(&length) {
+        return Err(validation_error(
+            "length",
+            "username_length",
+            &[("min", MIN_USERNAME_LENGTH), ("max", MAX_USERNAME_LENGTH)],
+        ));
+    }
+
+    if !username
+        .chars()
+        .all(|char| char.is_alphanumeric() || char == '_' || char == '-')
+    {
+        return Err(validation_error("charset", "username_charset", &[]));
+    }
+
+    Ok(())
+}
+
+/// At least 8 characters, no more than 72 bytes, with both letters and digits.
+fn validate_password(password: &str) -> Result<(), ValidationError> {
+    if password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Err(validation_error(
+            "length",
+            "password_too_short",
+            &[("min", MIN_PASSWORD_LENGTH)],
+        ));
+    }
+
+    if password.len() > MAX_PASSWORD_BYTES {
+        return Err(validation_error(
+            "length",
+            "password_too_long",
+            &[("max", MAX_PASSWORD_BYTES)],
+        ));
+    }
+
+    if !password.chars().any(char::is_alphabetic) || !password.chars().any(|char| char.is_numeric())
+    {
+        return Err(validation_error("strength", "password_too_weak", &[]));
+    }
+
+    Ok(())
+}
+
+fn validate_not_blank(text: &str) -> Result<(), ValidationError> {
+    if text.trim().is_empty() {
+        return Err(validation_error("blank", "blank", &[]));
+    }
+
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
 pub(crate) struct RegisterRequest {
+    #[validate(custom(function = "validate_username"))]
     pub(crate) username: String,
+    #[validate(custom(function = "validate_password"))]
     pub(crate) password: String,
 }
 
-impl RegisterRequest {
-    pub(crate) fn is_empty(&self) -> bool {
-        self.username.is_empty() || self.password.is_empty()
-    }
+/// How the client keeps its tokens after login.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum SessionMode {
+    /// Tokens are returned in the body and sent back in the `Authorization` header.
+    #[default]
+    Bearer,
+    /// Tokens are kept in HttpOnly cookies, state-changing requests carry a CSRF token.
+    Cookie,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Only presence is checked, so that accounts created under older rules can log in.
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
 pub(crate) struct LoginRequest {
+    #[validate(length(min = 1, message = "username_required"))]
     pub(crate) username: String,
+    #[validate(length(min = 1, message = "password_required"))]
     pub(crate) password: String,
+    #[serde(default)]
+    pub(crate) session: SessionMode,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct CreatePostRequest {
+    #[validate(
+        custom(function = "validate_not_blank"),
+        length(max = MAX_TITLE_LENGTH, message = "title_too_long")
+    )]
+    pub(crate) title: String,
+    #[validate(
+        custom(function = "validate_not_blank"),
+        length(max = MAX_CONTENT_LENGTH, message = "content_too_long")
+    )]
+    pub(crate) content: String,
+    /// Id of the post to quote, the quote embeds it.
+    #[serde(default)]
+    pub(crate) quote_of_id: Option<i32>,
+    /// Saves the post as a draft, see `GET /api/me/drafts`.
+    #[serde(default)]
+    pub(crate) draft: bool,
+    /// Publishes the post later, a time in the past publishes it right away.
+    #[serde(default)]
+    pub(crate) publish_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct PublishDraftRequest {
+    /// Publishes the post later instead, a time in the past publishes it right away.
+    #[serde(default)]
+    pub(crate) publish_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Moves a scheduled post back to the drafts.
+    #[serde(default)]
+    pub(crate) draft: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct EditPostRequest {
+    #[validate(
+        custom(function = "validate_not_blank"),
+        length(max = MAX_TITLE_LENGTH, message = "title_too_long")
+    )]
+    pub(crate) title: Option<String>,
+    #[validate(
+        custom(function = "validate_not_blank"),
+        length(max = MAX_CONTENT_LENGTH, message = "content_too_long")
+    )]
+    pub(crate) content: Option<String>,
 }
 
-impl LoginRequest {
+impl EditPostRequest {
     pub(crate) fn is_empty(&self) -> bool {
-        self.username.is_empty() || self.password.is_empty()
+        self.title.is_none() && self.content.is_none()
     }
 }
 
-#[derive(Debug, Deserialize, Serialize)]
-pub(crate) struct CreatePostRequest {
-    pub(crate) title: String,
+/// Without `refresh_token` the token is taken from the session cookie.
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct RefreshRequest {
+    #[serde(default)]
+    #[validate(length(min = 1, message = "refresh_token_empty"))]
+    pub(crate) refresh_token: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct LogoutRequest {
+    pub(crate) refresh_token: Option<String>,
+}
+
+/// An empty string clears the field, a missing one is left as is.
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct EditProfileRequest {
+    #[validate(length(
+        max = MAX_DISPLAY_NAME_LENGTH,
+        message = "display_name_too_long"
+    ))]
+    pub(crate) display_name: Option<String>,
+    #[validate(length(
+        max = MAX_BIO_LENGTH,
+        message = "bio_too_long"
+    ))]
+    pub(crate) bio: Option<String>,
+}
+
+impl EditProfileRequest {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.display_name.is_none() && self.bio.is_none()
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct ChangePasswordRequest {
+    #[validate(length(min = 1, message = "current_password_required"))]
+    pub(crate) current_password: String,
+    #[validate(custom(function = "validate_password"))]
+    pub(crate) new_password: String,
+}
+
+/// Deletion is confirmed with the password, a stolen session alone is not enough.
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct DeleteAccountRequest {
+    #[validate(length(min = 1, message = "password_required"))]
+    pub(crate) password: String,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct CreateCommentRequest {
+    #[validate(
+        custom(function = "validate_not_blank"),
+        length(max = MAX_COMMENT_LENGTH, message = "comment_too_long")
+    )]
     pub(crate) content: String,
+    pub(crate) parent_comment_id: Option<i32>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct ModerationRequest {
+    #[validate(length(max = MAX_REASON_LENGTH, message = "reason_too_long"))]
+    pub(crate) reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct SuspendRequest {
+    #[validate(range(min = 1, max = MAX_SUSPENSION_DAYS, message = "suspension_days_out_of_range"))]
+    pub(crate) days: i64,
+    #[validate(length(max = MAX_REASON_LENGTH, message = "reason_too_long"))]
+    pub(crate) reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct ChangeRoleRequest {
+    pub(crate) role: Role,
+}
+
+const DEFAULT_PAGE_LIMIT: i64 = 20;
+const MAX_PAGE_LIMIT: i64 = 100;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum PostSort {
+    #[default]
+    Newest,
+    MostLiked,
+}
+
+impl PostSort {
+    pub(crate) fn as_str(&self) -> &'static str {
+        match self {
+            PostSort::Newest => "newest",
+            PostSort::MostLiked => "most_liked",
+        }
+    }
+}
+
+/// Query parameters of post listings: `?limit=20&sort=most_liked&cursor=...`
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct PostsQuery {
+    /// Page size, 20 by default and at most 100.
+    pub(crate) limit: Option<i64>,
+    /// `next_cursor` of the previous page.
+    pub(crate) cursor: Option<String>,
+    #[serde(default)]
+    pub(crate) sort: PostSort,
+}
+
+impl PostsQuery {
+    pub(crate) fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT)
+    }
+}
+
+const DEFAULT_SEARCH_LIMIT: i64 = 20;
+const MAX_SEARCH_LIMIT: i64 = 50;
+
+/// Query parameters of search: `?q=rust async&limit=20`
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct SearchQuery {
+    pub(crate) q: String,
+    /// Number of posts and of users, 20 by default and at most 50.
+    pub(crate) limit: Option<i64>,
+}
+
+impl SearchQuery {
+    pub(crate) fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_SEARCH_LIMIT)
+            .clamp(1, MAX_SEARCH_LIMIT)
+    }
+}
+
+/// Query parameters of notifications: `?limit=20&before=42&unread=true`
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct NotificationsQuery {
+    /// Page size, 20 by default and at most 100.
+    pub(crate) limit: Option<i64>,
+    /// Returns notifications older than the one with this id.
+    pub(crate) before: Option<i32>,
+    #[serde(default)]
+    pub(crate) unread: bool,
+}
+
+impl NotificationsQuery {
+    pub(crate) fn limit(&self) -> i64 {
+        self.limit
+            .unwrap_or(DEFAULT_PAGE_LIMIT)
+            .clamp(1, MAX_PAGE_LIMIT)
+    }
+}
+
+/// Marks the listed notifications as read, or all of them if none are listed.
+#[derive(Debug, Deserialize, Serialize, Validate, ToSchema)]
+pub(crate) struct ReadNotificationsRequest {
+    #[validate(length(max = MAX_READ_NOTIFICATIONS, message = "too_many_notification_ids"))]
+    pub(crate) notification_ids: Option<Vec<i32>>,
+}
+
+/// Position of the last returned post, handed to clients as an opaque string.
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub(crate) struct PostCursor {
+    pub(crate) sort: PostSort,
+    pub(crate) likes_count: i64,
+    pub(crate) created_at: chrono::NaiveDateTime,
+    pub(crate) post_id: i32,
+}
+
+impl PostCursor {
+    pub(crate) fn encode(&self) -> String {
+        let json = serde_json::to_vec(self).expect("cursor is always serializable");
+        URL_SAFE_NO_PAD.encode(json)
+    }
+
+    pub(crate) fn decode(cursor: &str) -> Option<Self> {
+        let json = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        serde_json::from_slice(&json).ok()
+    }
 }
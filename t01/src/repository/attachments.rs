@@ -0,0 +1,189 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use deadpool_postgres::GenericClient;
+use log::info;
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio_postgres::Row;
+use utoipa::ToSchema;
+
+use super::{DatabasePost, PostgresRepository};
+use crate::storage::StoredImage;
+
+/// Upper bound of images attached to a single post.
+pub(crate) const MAX_ATTACHMENTS_PER_POST: i64 = 10;
+
+#[async_trait]
+pub(crate) trait AttachmentRepository: Send + Sync {
+    /// Links stored images to the post. The post row is locked, so concurrent uploads
+    /// cannot exceed [`MAX_ATTACHMENTS_PER_POST`] together.
+    async fn create_attachments(
+        &self,
+        post_id: i32,
+        user_id: i32,
+        images: &[StoredImage],
+    ) -> Result<AttachmentCreateResult>;
+
+    async fn get_attachments(&self, post_id: i32) -> Result<Vec<DatabaseAttachment>>;
+}
+
+#[async_trait]
+impl AttachmentRepository for PostgresRepository {
+    async fn create_attachments(
+        &self,
+        post_id: i32,
+        user_id: i32,
+        images: &[StoredImage],
+    ) -> Result<AttachmentCreateResult> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for attaching images to post (id = {post_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select user_id
+            from posts
+            where post_id = $1 and deleted_at is null and repost_of_id is null
+            for update;
+        ";
+        let Some(row) = transaction.query_opt(query, &[&post_id]).await? else {
+            return Ok(AttachmentCreateResult::PostNotFound);
+        };
+
+        let post_user_id: i32 = row.try_get("user_id")?;
+        if post_user_id != user_id {
+            return Ok(AttachmentCreateResult::NotOwned);
+        }
+
+        let query = "
+            select count(*)
+            from attachments
+            where post_id = $1;
+        ";
+        let attached: i64 = transaction
+            .query_one(query, &[&post_id])
+            .await?
+            .try_get(0)?;
+        if attached + i64::try_from(images.len())? > MAX_ATTACHMENTS_PER_POST {
+            return Ok(AttachmentCreateResult::TooMany);
+        }
+
+        let query = "
+            insert into attachments
+                (post_id, user_id, file_name, thumbnail_name, content_type, size_bytes, width, height)
+            values ($1, $2, $3, $4, $5, $6, $7, $8)
+            returning attachment_id, post_id, file_name, thumbnail_name, content_type, size_bytes, width, height;
+        ";
+        let mut attachments = Vec::with_capacity(images.len());
+        for image in images {
+            let row = transaction
+                .query_one(
+                    query,
+                    &[
+                        &post_id,
+                        &user_id,
+                        &image.file_name,
+                        &image.thumbnail_name,
+                        &image.content_type,
+                        &image.size_bytes,
+                        &image.width,
+                        &image.height,
+                    ],
+                )
+                .await?;
+            attachments.push(DatabaseAttachment::try_from(row)?);
+        }
+
+        transaction.commit().await?;
+
+        info!("Transaction for attaching images to post (id = {post_id}) successfully ended");
+
+        Ok(AttachmentCreateResult::Created(attachments))
+    }
+
+    async fn get_attachments(&self, post_id: i32) -> Result<Vec<DatabaseAttachment>> {
+        let connection = self.pool.get().await?;
+
+        let query = "
+            select attachment_id, post_id, file_name, thumbnail_name, content_type, size_bytes, width, height
+            from attachments
+            where post_id = $1
+            order by attachment_id;
+        ";
+        let rows = connection.query(query, &[&post_id]).await?;
+
+        rows.into_iter().map(DatabaseAttachment::try_from).collect()
+    }
+}
+
+/// Fills `attachments` of the posts with a single query.
+pub(super) async fn load_attachments(
+    client: &impl GenericClient,
+    posts: &mut [DatabasePost],
+) -> Result<()> {
+    if posts.is_empty() {
+        return Ok(());
+    }
+
+    let post_ids = posts.iter().map(|post| post.post_id).collect::<Vec<_>>();
+    let query = "
+        select attachment_id, post_id, file_name, thumbnail_name, content_type, size_bytes, width, height
+        from attachments
+        where post_id = any($1)
+        order by attachment_id;
+    ";
+    let rows = client.query(query, &[&post_ids]).await?;
+
+    let mut attachments: HashMap<i32, Vec<DatabaseAttachment>> = HashMap::new();
+    for row in rows {
+        let attachment = DatabaseAttachment::try_from(row)?;
+        attachments
+            .entry(attachment.post_id)
+            .or_default()
+            .push(attachment);
+    }
+
+    for post in posts {
+        post.attachments = attachments.remove(&post.post_id).unwrap_or_default();
+    }
+
+    Ok(())
+}
+
+pub(crate) enum AttachmentCreateResult {
+    Created(Vec<DatabaseAttachment>),
+    PostNotFound,
+    NotOwned,
+    TooMany,
+}
+
+/// Image attached to a post, both files are served from `/media/`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub(crate) struct DatabaseAttachment {
+    pub(crate) attachment_id: i32,
+    pub(crate) post_id: i32,
+    pub(crate) file_name: String,
+    pub(crate) thumbnail_name: String,
+    pub(crate) content_type: String,
+    pub(crate) size_bytes: i32,
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+}
+
+impl TryFrom<Row> for DatabaseAttachment {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self> {
+        Ok(Self {
+            attachment_id: row.try_get("attachment_id")?,
+            post_id: row.try_get("post_id")?,
+            file_name: row.try_get("file_name")?,
+            thumbnail_name: row.try_get("thumbnail_name")?,
+            content_type: row.try_get("content_type")?,
+            size_bytes: row.try_get("size_bytes")?,
+            width: row.try_get("width")?,
+            height: row.try_get("height")?,
+        })
+    }
+}
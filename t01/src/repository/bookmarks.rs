@@ -0,0 +1,119 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use log::info;
+use std::collections::HashSet;
+
+use super::{reposts, PostgresRepository, PostsFilter, PostsPage};
+use crate::model::{PostCursor, PostSort};
+
+#[async_trait]
+pub(crate) trait BookmarkRepository: Send + Sync {
+    /// Bookmarks the post, bookmarking twice changes nothing. A bookmarked repost saves the
+    /// original post. `false` if the post does not exist.
+    async fn add_bookmark(&self, user_id: i32, post_id: i32) -> Result<bool>;
+
+    /// `false` if the post does not exist.
+    async fn remove_bookmark(&self, user_id: i32, post_id: i32) -> Result<bool>;
+
+    /// Posts bookmarked by `user_id`, in the order and pages of the other post listings.
+    async fn get_bookmarks(
+        &self,
+        user_id: i32,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage>;
+
+    /// Those of `post_ids` bookmarked by `user_id`.
+    async fn get_bookmarked(&self, user_id: i32, post_ids: &[i32]) -> Result<HashSet<i32>>;
+}
+
+#[async_trait]
+impl BookmarkRepository for PostgresRepository {
+    async fn add_bookmark(&self, user_id: i32, post_id: i32) -> Result<bool> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for bookmarking post (id = {post_id}) by user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let Some(original_id) = reposts::original_post(&transaction, post_id).await? else {
+            return Ok(false);
+        };
+
+        let query = "
+            insert into bookmarks (user_id, post_id)
+            values ($1, $2)
+            on conflict do nothing;
+        ";
+        transaction
+            .execute(query, &[&user_id, &original_id])
+            .await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for bookmarking post (id = {post_id}) by user (id = {user_id}) successfully ended");
+
+        Ok(true)
+    }
+
+    async fn remove_bookmark(&self, user_id: i32, post_id: i32) -> Result<bool> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for removing bookmark of post (id = {post_id}) by user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let Some(original_id) = reposts::original_post(&transaction, post_id).await? else {
+            return Ok(false);
+        };
+
+        let query = "
+            delete from bookmarks
+            where user_id = $1 and post_id = $2;
+        ";
+        transaction
+            .execute(query, &[&user_id, &original_id])
+            .await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for removing bookmark of post (id = {post_id}) by user (id = {user_id}) successfully ended");
+
+        Ok(true)
+    }
+
+    async fn get_bookmarks(
+        &self,
+        user_id: i32,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage> {
+        info!("Listing bookmarks of user (id = {user_id}) started");
+
+        let posts = self
+            .list_posts(PostsFilter::BookmarkedBy(user_id), sort, cursor, limit)
+            .await?;
+
+        info!("Listing bookmarks of user (id = {user_id}) successfully ended");
+
+        Ok(posts)
+    }
+
+    async fn get_bookmarked(&self, user_id: i32, post_ids: &[i32]) -> Result<HashSet<i32>> {
+        let connection = self.pool.get().await?;
+
+        let query = "
+            select post_id
+            from bookmarks
+            where user_id = $1 and post_id = any($2);
+        ";
+        connection
+            .query(query, &[&user_id, &post_ids])
+            .await?
+            .into_iter()
+            .map(|row| row.try_get("post_id").map_err(Into::into))
+            .collect()
+    }
+}
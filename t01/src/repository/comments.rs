@@ -0,0 +1,279 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use log::info;
+use serde::Serialize;
+use std::collections::HashMap;
+use tokio_postgres::Row;
+use utoipa::ToSchema;
+
+use super::{notifications, PostgresRepository};
+use crate::model::NotificationKind;
+
+#[async_trait]
+pub(crate) trait CommentRepository: Send + Sync {
+    async fn create_comment(
+        &self,
+        post_id: i32,
+        user_id: i32,
+        parent_comment_id: Option<i32>,
+        content: &str,
+    ) -> Result<CommentCreateResult>;
+
+    async fn get_comments(&self, post_id: i32) -> Result<Vec<DatabaseComment>>;
+
+    /// Deletes the comment together with all replies to it. Allowed to the author of
+    /// the comment and to the owner of the post.
+    async fn delete_comment(
+        &self,
+        post_id: i32,
+        comment_id: i32,
+        user_id: i32,
+    ) -> Result<CommentDeleteResult>;
+}
+
+#[async_trait]
+impl CommentRepository for PostgresRepository {
+    async fn create_comment(
+        &self,
+        post_id: i32,
+        user_id: i32,
+        parent_comment_id: Option<i32>,
+        content: &str,
+    ) -> Result<CommentCreateResult> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for commenting post (id = {post_id}) by user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select user_id
+            from posts
+            where post_id = $1 and deleted_at is null and status = 'published' and repost_of_id is null;
+        ";
+        let Some(post) = transaction.query_opt(query, &[&post_id]).await? else {
+            return Ok(CommentCreateResult::PostNotFound);
+        };
+        let post_author_id: i32 = post.try_get("user_id")?;
+
+        let parent_author_id = match parent_comment_id {
+            Some(parent_comment_id) => {
+                let query = "
+                    select user_id
+                    from comments
+                    where comment_id = $1 and post_id = $2;
+                ";
+                let Some(parent) = transaction
+                    .query_opt(query, &[&parent_comment_id, &post_id])
+                    .await?
+                else {
+                    return Ok(CommentCreateResult::ParentNotFound);
+                };
+                Some(parent.try_get::<_, i32>("user_id")?)
+            }
+            None => None,
+        };
+
+        let query = "
+            insert into comments (post_id, user_id, parent_comment_id, content)
+            values ($1, $2, $3, $4)
+            returning comment_id;
+        ";
+        let row = transaction
+            .query_one(query, &[&post_id, &user_id, &parent_comment_id, &content])
+            .await?;
+
+        let comment_id: i32 = row.try_get(0)?;
+
+        if let Some(parent_author_id) = parent_author_id {
+            notifications::notify(
+                &transaction,
+                parent_author_id,
+                user_id,
+                NotificationKind::Reply,
+                Some(post_id),
+                Some(comment_id),
+            )
+            .await?;
+        }
+
+        // the author replied to is already notified, even if they wrote the post
+        if parent_author_id != Some(post_author_id) {
+            notifications::notify(
+                &transaction,
+                post_author_id,
+                user_id,
+                NotificationKind::Comment,
+                Some(post_id),
+                Some(comment_id),
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        info!("Transaction for commenting post (id = {post_id}) by user (id = {user_id}) successfully ended");
+
+        Ok(CommentCreateResult::Created(comment_id))
+    }
+
+    async fn get_comments(&self, post_id: i32) -> Result<Vec<DatabaseComment>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for listing comments of post (id = {post_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select
+                c.comment_id,
+                c.post_id,
+                c.user_id,
+                u.username,
+                c.parent_comment_id,
+                c.content,
+                c.created_at
+            from comments c
+            join users u on c.user_id = u.user_id
+            where c.post_id = $1
+            order by c.created_at, c.comment_id;
+        ";
+        let rows = transaction.query(query, &[&post_id]).await?;
+
+        let comments = rows
+            .into_iter()
+            .map(DatabaseComment::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for listing comments of post (id = {post_id}) successfully ended");
+
+        Ok(comments)
+    }
+
+    async fn delete_comment(
+        &self,
+        post_id: i32,
+        comment_id: i32,
+        user_id: i32,
+    ) -> Result<CommentDeleteResult> {
+        let mut connection = self.pool.get().await?;
+
+        info!(
+            "Transaction for deleting comment (id = {comment_id}) by user (id = {user_id}) started"
+        );
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select c.user_id, p.user_id as post_user_id
+            from comments c
+            join posts p on c.post_id = p.post_id
+            where c.comment_id = $1 and c.post_id = $2 and p.deleted_at is null;
+        ";
+        let Some(row) = transaction
+            .query_opt(query, &[&comment_id, &post_id])
+            .await?
+        else {
+            return Ok(CommentDeleteResult::NotFound);
+        };
+
+        let comment_user_id: i32 = row.try_get("user_id")?;
+        let post_user_id: i32 = row.try_get("post_user_id")?;
+
+        if user_id != comment_user_id && user_id != post_user_id {
+            return Ok(CommentDeleteResult::NotOwned);
+        }
+
+        let query = "
+            delete from comments
+            where comment_id = $1;
+        ";
+        transaction.execute(query, &[&comment_id]).await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for deleting comment (id = {comment_id}) by user (id = {user_id}) successfully ended");
+
+        Ok(CommentDeleteResult::Deleted)
+    }
+}
+
+pub(crate) enum CommentCreateResult {
+    Created(i32),
+    PostNotFound,
+    ParentNotFound,
+}
+
+pub(crate) enum CommentDeleteResult {
+    Deleted,
+    NotFound,
+    NotOwned,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct DatabaseComment {
+    pub(crate) comment_id: i32,
+    pub(crate) post_id: i32,
+    pub(crate) user_id: i32,
+    pub(crate) username: String,
+    pub(crate) parent_comment_id: Option<i32>,
+    pub(crate) content: String,
+    pub(crate) created_at: chrono::NaiveDateTime,
+}
+
+impl TryFrom<Row> for DatabaseComment {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self> {
+        Ok(Self {
+            comment_id: row.try_get("comment_id")?,
+            post_id: row.try_get("post_id")?,
+            user_id: row.try_get("user_id")?,
+            username: row.try_get("username")?,
+            parent_comment_id: row.try_get("parent_comment_id")?,
+            content: row.try_get("content")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
+
+/// Comment with all replies to it, in the order they were written.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct CommentThread {
+    #[serde(flatten)]
+    pub(crate) comment: DatabaseComment,
+    #[schema(no_recursion)]
+    pub(crate) replies: Vec<CommentThread>,
+}
+
+impl CommentThread {
+    /// Builds threads from a flat list of comments of a single post.
+    pub(crate) fn from_comments(comments: Vec<DatabaseComment>) -> Vec<Self> {
+        let mut children: HashMap<Option<i32>, Vec<DatabaseComment>> = HashMap::new();
+        for comment in comments {
+            children
+                .entry(comment.parent_comment_id)
+                .or_default()
+                .push(comment);
+        }
+
+        Self::collect(None, &mut children)
+    }
+
+    fn collect(
+        parent_comment_id: Option<i32>,
+        children: &mut HashMap<Option<i32>, Vec<DatabaseComment>>,
+    ) -> Vec<Self> {
+        let comments = children.remove(&parent_comment_id).unwrap_or_default();
+
+        comments
+            .into_iter()
+            .map(|comment| {
+                let replies = Self::collect(Some(comment.comment_id), children);
+                Self { comment, replies }
+            })
+            .collect()
+    }
+}
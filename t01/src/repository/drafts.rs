@@ -0,0 +1,173 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use log::{debug, info};
+
+use super::{load_details, publish, DatabasePost, PostgresRepository, POST_SELECT};
+use crate::{events::FeedEvent, model::PostStatus};
+
+/// When a post is published.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Publication {
+    Now,
+    /// By the background task, once the time comes.
+    At(NaiveDateTime),
+    /// Only when the author publishes the draft.
+    Draft,
+}
+
+impl Publication {
+    pub(crate) fn status(&self) -> PostStatus {
+        match self {
+            Publication::Now => PostStatus::Published,
+            Publication::At(_) => PostStatus::Scheduled,
+            Publication::Draft => PostStatus::Draft,
+        }
+    }
+
+    pub(crate) fn publish_at(&self) -> Option<NaiveDateTime> {
+        match self {
+            Publication::At(publish_at) => Some(*publish_at),
+            Publication::Now | Publication::Draft => None,
+        }
+    }
+}
+
+#[async_trait]
+pub(crate) trait DraftRepository: Send + Sync {
+    /// Drafts and scheduled posts of the user, the next to be published first, drafts
+    /// last. Posts in the trash are left out.
+    async fn get_drafts(&self, user_id: i32) -> Result<Vec<DatabasePost>>;
+
+    /// The draft or scheduled post, `None` unless it is one of the user.
+    async fn get_draft(&self, post_id: i32, user_id: i32) -> Result<Option<DatabasePost>>;
+
+    /// Publishes, schedules or moves back to the drafts the draft or scheduled post of the
+    /// user. `false` if there is no such post.
+    async fn publish_draft(
+        &self,
+        post_id: i32,
+        user_id: i32,
+        publication: Publication,
+    ) -> Result<bool>;
+
+    /// Publishes the scheduled posts due by `now` and returns their ids.
+    async fn publish_due_posts(&self, now: NaiveDateTime) -> Result<Vec<i32>>;
+}
+
+#[async_trait]
+impl DraftRepository for PostgresRepository {
+    async fn get_drafts(&self, user_id: i32) -> Result<Vec<DatabasePost>> {
+        let connection = self.pool.get().await?;
+
+        let query = format!(
+            "{POST_SELECT} where p.user_id = $1 and p.status <> 'published' and p.deleted_at is null
+             order by p.publish_at asc nulls last, p.created_at desc, p.post_id desc;"
+        );
+        let mut posts = connection
+            .query(&query, &[&user_id])
+            .await?
+            .into_iter()
+            .map(DatabasePost::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        load_details(&connection, &mut posts).await?;
+
+        Ok(posts)
+    }
+
+    async fn get_draft(&self, post_id: i32, user_id: i32) -> Result<Option<DatabasePost>> {
+        let connection = self.pool.get().await?;
+
+        let query = format!(
+            "{POST_SELECT} where p.post_id = $1 and p.user_id = $2
+             and p.status <> 'published' and p.deleted_at is null;"
+        );
+        let row = connection.query_opt(&query, &[&post_id, &user_id]).await?;
+
+        let mut post = row.map(DatabasePost::try_from).transpose()?;
+        if let Some(post) = &mut post {
+            load_details(&connection, std::slice::from_mut(post)).await?;
+        }
+
+        Ok(post)
+    }
+
+    async fn publish_draft(
+        &self,
+        post_id: i32,
+        user_id: i32,
+        publication: Publication,
+    ) -> Result<bool> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for publishing draft (id = {post_id}) by user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        // a post published now takes its place at the top of the listings
+        let query = "
+            update posts
+            set
+                status = $3,
+                publish_at = $4,
+                created_at = case when $3 = 'published' then current_timestamp else created_at end
+            where post_id = $1 and user_id = $2 and status <> 'published' and deleted_at is null
+            returning post_id;
+        ";
+        let updated = transaction
+            .query_opt(
+                query,
+                &[
+                    &post_id,
+                    &user_id,
+                    &publication.status().as_str(),
+                    &publication.publish_at(),
+                ],
+            )
+            .await?
+            .is_some();
+
+        if updated && publication == Publication::Now {
+            publish(&transaction, &FeedEvent::PostCreated { post_id, user_id }).await?;
+        }
+
+        transaction.commit().await?;
+
+        info!("Transaction for publishing draft (id = {post_id}) by user (id = {user_id}) successfully ended");
+
+        Ok(updated)
+    }
+
+    async fn publish_due_posts(&self, now: NaiveDateTime) -> Result<Vec<i32>> {
+        let mut connection = self.pool.get().await?;
+
+        // runs every 30 seconds, the publisher logs the posts it published
+        debug!("Transaction for publishing posts scheduled before {now} started");
+
+        let transaction = connection.transaction().await?;
+
+        // posts in the trash wait for the restore, the next run publishes them. As with
+        // `publish_draft`, the post takes its place at the top of the listings, an older
+        // date would hide it behind the cursor of pages already loaded
+        let query = "
+            update posts
+            set status = 'published', created_at = current_timestamp
+            where status = 'scheduled' and publish_at <= $1 and deleted_at is null
+            returning post_id, user_id;
+        ";
+        let mut post_ids = Vec::new();
+        for row in transaction.query(query, &[&now]).await? {
+            let post_id: i32 = row.try_get("post_id")?;
+            let user_id: i32 = row.try_get("user_id")?;
+            publish(&transaction, &FeedEvent::PostCreated { post_id, user_id }).await?;
+            post_ids.push(post_id);
+        }
+
+        transaction.commit().await?;
+
+        debug!("Transaction for publishing posts scheduled before {now} successfully ended");
+
+        Ok(post_ids)
+    }
+}
@@ -0,0 +1,319 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use log::info;
+use serde::Serialize;
+use tokio_postgres::Row;
+use utoipa::ToSchema;
+
+use super::{
+    load_details, DatabaseComment, DatabasePost, PostgresRepository, UserProfile, POST_SELECT,
+};
+use crate::model::{ExportStatus, ReactionKind};
+
+#[async_trait]
+pub(crate) trait ExportRepository: Send + Sync {
+    /// Starts an export for the user. While an export is being built, it is returned
+    /// instead of starting another one.
+    async fn create_export(&self, user_id: i32) -> Result<ExportCreateResult>;
+
+    /// The export of the user, `None` once it has expired.
+    async fn get_export(&self, export_id: i32, user_id: i32) -> Result<Option<DatabaseExport>>;
+
+    /// Everything stored about the user, `None` if the user does not exist.
+    async fn get_user_data(&self, user_id: i32) -> Result<Option<UserData>>;
+
+    /// Marks the pending export as ready with the archive in `file_name`, or as failed
+    /// without it. Either way it expires at `expires_at`. Returns `false` if the export is
+    /// no longer pending, failed as stale or deleted with its user.
+    async fn finish_export(
+        &self,
+        export_id: i32,
+        file_name: Option<&str>,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool>;
+
+    /// Marks the exports still pending since before `started_before` as failed, their
+    /// build was abandoned. They expire at `expires_at`. Returns how many were failed.
+    async fn fail_stale_exports(
+        &self,
+        started_before: NaiveDateTime,
+        expires_at: NaiveDateTime,
+    ) -> Result<u64>;
+
+    /// Deletes the exports expired by `now` and returns their archives, whose files are
+    /// left to the caller.
+    async fn purge_expired_exports(&self, now: NaiveDateTime) -> Result<Vec<ExpiredExport>>;
+}
+
+#[async_trait]
+impl ExportRepository for PostgresRepository {
+    async fn create_export(&self, user_id: i32) -> Result<ExportCreateResult> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for creating export of user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        // `exports_pending_idx` keeps a single pending export per user
+        let query = "
+            insert into exports (user_id)
+            values ($1)
+            on conflict (user_id) where status = 'pending' do nothing
+            returning export_id, status, created_at, completed_at, expires_at, file_name;
+        ";
+        let result = match transaction.query_opt(query, &[&user_id]).await? {
+            Some(row) => ExportCreateResult::Created(DatabaseExport::try_from(row)?),
+            None => {
+                let query = "
+                    select export_id, status, created_at, completed_at, expires_at, file_name
+                    from exports
+                    where user_id = $1 and status = 'pending';
+                ";
+                let row = transaction.query_one(query, &[&user_id]).await?;
+                ExportCreateResult::Pending(DatabaseExport::try_from(row)?)
+            }
+        };
+
+        transaction.commit().await?;
+
+        info!("Transaction for creating export of user (id = {user_id}) successfully ended");
+
+        Ok(result)
+    }
+
+    async fn get_export(&self, export_id: i32, user_id: i32) -> Result<Option<DatabaseExport>> {
+        let connection = self.pool.get().await?;
+
+        let query = "
+            select export_id, status, created_at, completed_at, expires_at, file_name
+            from exports
+            where export_id = $1 and user_id = $2
+                and (expires_at is null or expires_at > current_timestamp);
+        ";
+        let row = connection.query_opt(query, &[&export_id, &user_id]).await?;
+
+        row.map(DatabaseExport::try_from).transpose()
+    }
+
+    async fn get_user_data(&self, user_id: i32) -> Result<Option<UserData>> {
+        let connection = self.pool.get().await?;
+
+        let query = "
+            select user_id, username, display_name, bio, avatar_file_name, avatar_thumbnail_name, role, created_at
+            from users
+            where user_id = $1;
+        ";
+        let Some(row) = connection.query_opt(query, &[&user_id]).await? else {
+            return Ok(None);
+        };
+        let profile = UserProfile::try_from(row)?;
+
+        // drafts and posts in the trash are stored too
+        let query = format!("{POST_SELECT} where p.user_id = $1 order by p.created_at, p.post_id;");
+        let rows = connection.query(&query, &[&user_id]).await?;
+        let mut deleted_at = Vec::with_capacity(rows.len());
+        let mut posts = Vec::with_capacity(rows.len());
+        for row in rows {
+            deleted_at.push(row.try_get("deleted_at")?);
+            posts.push(DatabasePost::try_from(row)?);
+        }
+        load_details(&connection, &mut posts).await?;
+        let posts = posts
+            .into_iter()
+            .zip(deleted_at)
+            .map(|(post, deleted_at)| ExportedPost { post, deleted_at })
+            .collect();
+
+        let query = "
+            select post_id, kind, created_at
+            from reactions
+            where user_id = $1
+            order by created_at, reaction_id;
+        ";
+        let reactions = connection
+            .query(query, &[&user_id])
+            .await?
+            .into_iter()
+            .map(ExportedReaction::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        let query = "
+            select
+                c.comment_id,
+                c.post_id,
+                c.user_id,
+                u.username,
+                c.parent_comment_id,
+                c.content,
+                c.created_at
+            from comments c
+            join users u on c.user_id = u.user_id
+            where c.user_id = $1
+            order by c.created_at, c.comment_id;
+        ";
+        let comments = connection
+            .query(query, &[&user_id])
+            .await?
+            .into_iter()
+            .map(DatabaseComment::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Some(UserData {
+            profile,
+            posts,
+            reactions,
+            comments,
+        }))
+    }
+
+    async fn finish_export(
+        &self,
+        export_id: i32,
+        file_name: Option<&str>,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool> {
+        let connection = self.pool.get().await?;
+
+        let status = match file_name {
+            Some(_) => ExportStatus::Ready,
+            None => ExportStatus::Failed,
+        };
+        let query = "
+            update exports
+            set status = $2, file_name = $3, completed_at = current_timestamp, expires_at = $4
+            where export_id = $1 and status = 'pending';
+        ";
+        let updated = connection
+            .execute(
+                query,
+                &[&export_id, &status.as_str(), &file_name, &expires_at],
+            )
+            .await?;
+
+        Ok(updated == 1)
+    }
+
+    async fn fail_stale_exports(
+        &self,
+        started_before: NaiveDateTime,
+        expires_at: NaiveDateTime,
+    ) -> Result<u64> {
+        let connection = self.pool.get().await?;
+
+        let query = "
+            update exports
+            set status = 'failed', completed_at = current_timestamp, expires_at = $2
+            where status = 'pending' and created_at < $1;
+        ";
+        let failed = connection
+            .execute(query, &[&started_before, &expires_at])
+            .await?;
+
+        info!("Failed {failed} stale exports");
+
+        Ok(failed)
+    }
+
+    async fn purge_expired_exports(&self, now: NaiveDateTime) -> Result<Vec<ExpiredExport>> {
+        let connection = self.pool.get().await?;
+
+        let query = "
+            delete from exports
+            where expires_at <= $1
+            returning user_id, file_name;
+        ";
+        let rows = connection.query(query, &[&now]).await?;
+
+        let mut expired = Vec::with_capacity(rows.len());
+        for row in rows {
+            expired.push(ExpiredExport {
+                user_id: row.try_get("user_id")?,
+                file_name: row.try_get("file_name")?,
+            });
+        }
+
+        info!("Purged {} expired exports", expired.len());
+
+        Ok(expired)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) enum ExportCreateResult {
+    Created(DatabaseExport),
+    /// An export of the user is already being built.
+    Pending(DatabaseExport),
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub(crate) struct DatabaseExport {
+    pub(crate) export_id: i32,
+    pub(crate) status: ExportStatus,
+    pub(crate) created_at: NaiveDateTime,
+    pub(crate) completed_at: Option<NaiveDateTime>,
+    /// Set once the export is finished, the archive is deleted afterwards.
+    pub(crate) expires_at: Option<NaiveDateTime>,
+    /// Name of the archive in the export storage, set on ready exports.
+    #[serde(skip)]
+    pub(crate) file_name: Option<String>,
+}
+
+impl TryFrom<Row> for DatabaseExport {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self> {
+        Ok(Self {
+            export_id: row.try_get("export_id")?,
+            status: row.try_get::<_, &str>("status")?.parse()?,
+            created_at: row.try_get("created_at")?,
+            completed_at: row.try_get("completed_at")?,
+            expires_at: row.try_get("expires_at")?,
+            file_name: row.try_get("file_name")?,
+        })
+    }
+}
+
+/// Archive of an expired export, `file_name` is `None` for failed exports.
+#[derive(Debug)]
+pub(crate) struct ExpiredExport {
+    pub(crate) user_id: i32,
+    pub(crate) file_name: Option<String>,
+}
+
+/// Everything stored about a user, as put in the export archive.
+#[derive(Debug, Serialize)]
+pub(crate) struct UserData {
+    pub(crate) profile: UserProfile,
+    pub(crate) posts: Vec<ExportedPost>,
+    pub(crate) reactions: Vec<ExportedReaction>,
+    pub(crate) comments: Vec<DatabaseComment>,
+}
+
+/// Post of the user, including drafts and posts in the trash.
+#[derive(Debug, Serialize)]
+pub(crate) struct ExportedPost {
+    #[serde(flatten)]
+    pub(crate) post: DatabasePost,
+    pub(crate) deleted_at: Option<NaiveDateTime>,
+}
+
+/// Reaction of the user to a post, likes included.
+#[derive(Debug, Serialize)]
+pub(crate) struct ExportedReaction {
+    pub(crate) post_id: i32,
+    pub(crate) kind: ReactionKind,
+    pub(crate) created_at: NaiveDateTime,
+}
+
+impl TryFrom<Row> for ExportedReaction {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self> {
+        Ok(Self {
+            post_id: row.try_get("post_id")?,
+            kind: row.try_get::<_, &str>("kind")?.parse()?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
@@ -0,0 +1,241 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use log::info;
+use serde::Serialize;
+use tokio_postgres::Row;
+use utoipa::ToSchema;
+
+use super::{notifications, PostgresRepository, PostsFilter, PostsPage};
+use crate::model::{NotificationKind, PostCursor, PostSort};
+
+#[async_trait]
+pub(crate) trait FollowRepository: Send + Sync {
+    async fn follow_user(&self, follower_id: i32, followee_id: i32) -> Result<()>;
+
+    async fn unfollow_user(&self, follower_id: i32, followee_id: i32) -> Result<()>;
+
+    /// Follower and following counts of `user_id`, and whether `viewer_id` follows them.
+    /// `is_followed` tells whether `viewer_id` follows the user, anonymous viewers follow nobody.
+    async fn get_follow_counts(&self, user_id: i32, viewer_id: Option<i32>)
+        -> Result<FollowCounts>;
+
+    async fn get_followers(&self, user_id: i32) -> Result<Vec<DatabaseFollow>>;
+
+    async fn get_following(&self, user_id: i32) -> Result<Vec<DatabaseFollow>>;
+
+    /// Posts of the users followed by `user_id`.
+    async fn get_feed(
+        &self,
+        user_id: i32,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage>;
+}
+
+#[async_trait]
+impl FollowRepository for PostgresRepository {
+    async fn follow_user(&self, follower_id: i32, followee_id: i32) -> Result<()> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for following user (id = {followee_id}) by user (id = {follower_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            insert into follows (follower_id, followee_id)
+            values ($1, $2)
+            on conflict do nothing;
+        ";
+        let inserted = transaction
+            .execute(query, &[&follower_id, &followee_id])
+            .await?;
+
+        // following again must not notify twice
+        if inserted > 0 {
+            notifications::notify(
+                &transaction,
+                followee_id,
+                follower_id,
+                NotificationKind::Follow,
+                None,
+                None,
+            )
+            .await?;
+        }
+
+        transaction.commit().await?;
+
+        info!("Transaction for following user (id = {followee_id}) by user (id = {follower_id}) successfully ended");
+
+        Ok(())
+    }
+
+    async fn unfollow_user(&self, follower_id: i32, followee_id: i32) -> Result<()> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for unfollowing user (id = {followee_id}) by user (id = {follower_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            delete from follows
+            where follower_id = $1 and followee_id = $2;
+        ";
+        transaction
+            .execute(query, &[&follower_id, &followee_id])
+            .await?;
+
+        notifications::withdraw(
+            &transaction,
+            followee_id,
+            follower_id,
+            NotificationKind::Follow,
+            None,
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for unfollowing user (id = {followee_id}) by user (id = {follower_id}) successfully ended");
+
+        Ok(())
+    }
+
+    async fn get_follow_counts(
+        &self,
+        user_id: i32,
+        viewer_id: Option<i32>,
+    ) -> Result<FollowCounts> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for counting follows of user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select
+                (select count(*) from follows where followee_id = $1) as followers_count,
+                (select count(*) from follows where follower_id = $1) as following_count,
+                exists(
+                    select 1 from follows where follower_id = $2 and followee_id = $1
+                ) as is_followed;
+        ";
+        let row = transaction
+            .query_one(query, &[&user_id, &viewer_id])
+            .await?;
+
+        let counts = FollowCounts {
+            followers_count: row.try_get("followers_count")?,
+            following_count: row.try_get("following_count")?,
+            is_followed: row.try_get("is_followed")?,
+        };
+
+        transaction.commit().await?;
+
+        info!("Transaction for counting follows of user (id = {user_id}) successfully ended");
+
+        Ok(counts)
+    }
+
+    async fn get_followers(&self, user_id: i32) -> Result<Vec<DatabaseFollow>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for listing followers of user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select u.user_id, u.username, f.created_at
+            from follows f
+            join users u on f.follower_id = u.user_id
+            where f.followee_id = $1
+            order by f.created_at desc;
+        ";
+        let rows = transaction.query(query, &[&user_id]).await?;
+
+        let followers = rows
+            .into_iter()
+            .map(DatabaseFollow::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for listing followers of user (id = {user_id}) successfully ended");
+
+        Ok(followers)
+    }
+
+    async fn get_following(&self, user_id: i32) -> Result<Vec<DatabaseFollow>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for listing followed users of user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select u.user_id, u.username, f.created_at
+            from follows f
+            join users u on f.followee_id = u.user_id
+            where f.follower_id = $1
+            order by f.created_at desc;
+        ";
+        let rows = transaction.query(query, &[&user_id]).await?;
+
+        let following = rows
+            .into_iter()
+            .map(DatabaseFollow::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for listing followed users of user (id = {user_id}) successfully ended");
+
+        Ok(following)
+    }
+
+    async fn get_feed(
+        &self,
+        user_id: i32,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage> {
+        info!("Listing feed of user (id = {user_id}) started");
+
+        let posts = self
+            .list_posts(PostsFilter::FollowedBy(user_id), sort, cursor, limit)
+            .await?;
+
+        info!("Listing feed of user (id = {user_id}) successfully ended");
+
+        Ok(posts)
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub(crate) struct FollowCounts {
+    pub(crate) followers_count: i64,
+    pub(crate) following_count: i64,
+    pub(crate) is_followed: bool,
+}
+
+/// User on the other side of a follow relationship.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct DatabaseFollow {
+    pub(crate) user_id: i32,
+    pub(crate) username: String,
+    pub(crate) created_at: chrono::NaiveDateTime,
+}
+
+impl TryFrom<Row> for DatabaseFollow {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self> {
+        Ok(Self {
+            user_id: row.try_get("user_id")?,
+            username: row.try_get("username")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
@@ -0,0 +1,2157 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    sync::{Mutex, MutexGuard},
+};
+
+use super::{
+    exports::{ExpiredExport, ExportedPost, ExportedReaction},
+    follows::DatabaseFollow,
+    moderation::ModerationAction,
+    notifications::DatabaseNotification,
+    reactions,
+    reposts::RepostState,
+    revisions::DatabaseRevision,
+    search::{self, SearchedPost, SearchedUser},
+    AccessCheck, AttachmentCreateResult, AttachmentRepository, BookmarkRepository,
+    CommentCreateResult, CommentDeleteResult, CommentRepository, DatabaseAttachment,
+    DatabaseComment, DatabaseExport, DatabasePost, DatabaseUser, DeletedPost, DraftRepository,
+    ExportCreateResult, ExportRepository, FollowCounts, FollowRepository, Like,
+    ModerationRepository, ModerationResult, NotificationRepository, NotificationsPage,
+    PostDeleteResult, PostEditResult, PostRepository, PostRestoreResult, PostsFilter, PostsPage,
+    ProfileRepository, Publication, ReactionCounts, ReactionRepository, RefreshTokenRotation,
+    RepostRepository, RevisionRepository, SearchRepository, TokenRepository, UserBlock, UserData,
+    UserProfile, UserRepository, UserStatus, MAX_ATTACHMENTS_PER_POST,
+};
+use crate::{
+    error::AppError,
+    events::{FeedEvent, FeedEvents},
+    markdown,
+    model::{ExportStatus, NotificationKind, PostCursor, PostSort, PostStatus, ReactionKind, Role},
+    storage::StoredImage,
+    utils::PasswordHash,
+};
+
+/// Repository kept in memory, for tests of the router without a database. Follows the
+/// same rules as the Postgres one, including the cascades of its foreign keys, and
+/// publishes feed events straight to [`FeedEvents`].
+pub(crate) struct MemoryRepository {
+    state: Mutex<State>,
+    events: FeedEvents,
+}
+
+#[derive(Default)]
+struct State {
+    /// Shared by all tables, ids only have to be unique and increasing.
+    last_id: i32,
+    users: BTreeMap<i32, User>,
+    posts: BTreeMap<i32, Post>,
+    /// `(post_id, user_id, kind)` with the time of reacting.
+    reactions: BTreeMap<(i32, i32, ReactionKind), NaiveDateTime>,
+    /// `(user_id, post_id)`
+    bookmarks: BTreeSet<(i32, i32)>,
+    comments: BTreeMap<i32, Comment>,
+    revisions: BTreeMap<i32, Revision>,
+    /// `(follower_id, followee_id)` with the time of following.
+    follows: BTreeMap<(i32, i32), NaiveDateTime>,
+    attachments: BTreeMap<i32, Attachment>,
+    refresh_tokens: HashMap<String, RefreshToken>,
+    revoked_jtis: HashSet<String>,
+    notifications: BTreeMap<i32, Notification>,
+    moderation_actions: BTreeMap<i32, Action>,
+    exports: BTreeMap<i32, Export>,
+}
+
+struct User {
+    username: String,
+    password_hash: PasswordHash,
+    role: Role,
+    banned_at: Option<NaiveDateTime>,
+    suspended_until: Option<NaiveDateTime>,
+    display_name: Option<String>,
+    bio: Option<String>,
+    avatar: Option<(String, String)>,
+    created_at: NaiveDateTime,
+}
+
+struct Post {
+    user_id: i32,
+    title: String,
+    content: String,
+    created_at: NaiveDateTime,
+    edited_at: Option<NaiveDateTime>,
+    deleted_at: Option<NaiveDateTime>,
+    repost_of_id: Option<i32>,
+    quote_of_id: Option<i32>,
+    status: PostStatus,
+    publish_at: Option<NaiveDateTime>,
+}
+
+struct Comment {
+    post_id: i32,
+    user_id: i32,
+    parent_comment_id: Option<i32>,
+    content: String,
+    created_at: NaiveDateTime,
+}
+
+struct Revision {
+    post_id: i32,
+    title: String,
+    content: String,
+    created_at: NaiveDateTime,
+}
+
+struct Attachment {
+    post_id: i32,
+    user_id: i32,
+    file_name: String,
+    thumbnail_name: String,
+    content_type: String,
+    size_bytes: i32,
+    width: i32,
+    height: i32,
+}
+
+struct RefreshToken {
+    user_id: i32,
+    expires_at: NaiveDateTime,
+    revoked_at: Option<NaiveDateTime>,
+}
+
+struct Notification {
+    user_id: i32,
+    actor_id: i32,
+    kind: NotificationKind,
+    post_id: Option<i32>,
+    comment_id: Option<i32>,
+    read_at: Option<NaiveDateTime>,
+    created_at: NaiveDateTime,
+}
+
+struct Action {
+    moderator_id: Option<i32>,
+    action: String,
+    target_user_id: Option<i32>,
+    target_post_id: Option<i32>,
+    reason: Option<String>,
+    created_at: NaiveDateTime,
+}
+
+struct Export {
+    user_id: i32,
+    status: ExportStatus,
+    file_name: Option<String>,
+    created_at: NaiveDateTime,
+    completed_at: Option<NaiveDateTime>,
+    expires_at: Option<NaiveDateTime>,
+}
+
+impl Export {
+    fn to_database(&self, export_id: i32) -> DatabaseExport {
+        DatabaseExport {
+            export_id,
+            status: self.status,
+            created_at: self.created_at,
+            completed_at: self.completed_at,
+            expires_at: self.expires_at,
+            file_name: self.file_name.clone(),
+        }
+    }
+}
+
+fn now() -> NaiveDateTime {
+    chrono::Utc::now().naive_utc()
+}
+
+impl MemoryRepository {
+    pub(crate) fn new(events: FeedEvents) -> Self {
+        Self {
+            state: Mutex::new(State::default()),
+            events,
+        }
+    }
+
+    /// Roles are granted by hand in the database, tests grant them here.
+    pub(crate) fn set_role(&self, user_id: i32, role: Role) {
+        if let Some(user) = self.state().users.get_mut(&user_id) {
+            user.role = role;
+        }
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state
+            .lock()
+            .expect("memory repository is never poisoned")
+    }
+
+    fn publish_reposts_deleted(&self, state: &State, post_id: i32) {
+        for (repost_id, post) in &state.posts {
+            if post.repost_of_id == Some(post_id) && post.deleted_at.is_none() {
+                self.events.publish(FeedEvent::PostDeleted {
+                    post_id: *repost_id,
+                });
+            }
+        }
+    }
+
+    fn publish_likes_changed(&self, state: &State, post_id: i32) {
+        self.events.publish(FeedEvent::LikesChanged {
+            post_id,
+            likes_count: state.likes_count(post_id),
+        });
+    }
+}
+
+impl State {
+    fn next_id(&mut self) -> i32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn username(&self, user_id: i32) -> Option<String> {
+        self.users.get(&user_id).map(|user| user.username.clone())
+    }
+
+    fn likes_count(&self, post_id: i32) -> i64 {
+        self.reactions(post_id)[&ReactionKind::Like]
+    }
+
+    fn reactions(&self, post_id: i32) -> ReactionCounts {
+        let mut counts = reactions::empty_counts();
+        for ((_, _, kind), _) in self
+            .reactions
+            .range((post_id, i32::MIN, ReactionKind::Like)..=(post_id, i32::MAX, ReactionKind::Sad))
+        {
+            if let Some(count) = counts.get_mut(kind) {
+                *count += 1;
+            }
+        }
+        counts
+    }
+
+    fn status(&self, user_id: i32, now: NaiveDateTime) -> Option<UserStatus> {
+        self.users.get(&user_id).map(|user| UserStatus {
+            role: user.role,
+            block: UserBlock::from_columns(user.banned_at, user.suspended_until, now),
+        })
+    }
+
+    /// The post unless it is in the trash.
+    fn live_post(&self, post_id: i32) -> Option<&Post> {
+        self.posts
+            .get(&post_id)
+            .filter(|post| post.deleted_at.is_none())
+    }
+
+    /// The post unless it is in the trash or is a repost, which has no content to edit.
+    fn authored_post(&self, post_id: i32) -> Option<&Post> {
+        self.live_post(post_id)
+            .filter(|post| post.repost_of_id.is_none())
+    }
+
+    /// The post if it can be commented and reacted to: published, not in the trash and not
+    /// a repost.
+    fn published_post(&self, post_id: i32) -> Option<&Post> {
+        self.authored_post(post_id)
+            .filter(|post| post.status == PostStatus::Published)
+    }
+
+    /// The post unless it is unpublished, in the trash or a repost of a post in the trash,
+    /// as `VISIBLE_POST` of the Postgres repository.
+    fn visible_post(&self, post_id: i32) -> Option<&Post> {
+        self.live_post(post_id)
+            .filter(|post| post.status == PostStatus::Published)
+            .filter(|post| {
+                post.repost_of_id
+                    .is_none_or(|original_id| self.live_post(original_id).is_some())
+            })
+    }
+
+    /// Id of the post shared when `post_id` is shared, see `reposts::original_post`.
+    fn original_id(&self, post_id: i32) -> Option<i32> {
+        let post = self.visible_post(post_id)?;
+        Some(post.repost_of_id.unwrap_or(post_id))
+    }
+
+    fn reposts_count(&self, post_id: i32) -> i64 {
+        self.posts
+            .values()
+            .filter(|post| post.repost_of_id == Some(post_id) && post.deleted_at.is_none())
+            .count() as i64
+    }
+
+    /// The post with the reposted or quoted one, as `load_details` of the Postgres
+    /// repository.
+    fn post(&self, post_id: i32) -> Option<DatabasePost> {
+        let mut post = self.post_row(post_id)?;
+        post.original = self.original(&post);
+        if post.repost_of_id.is_some() {
+            if let Some(original) = post.original.as_mut() {
+                original.original = self.original(original);
+            }
+        }
+        Some(post)
+    }
+
+    fn original(&self, post: &DatabasePost) -> Option<Box<DatabasePost>> {
+        post.repost_of_id
+            .or(post.quote_of_id)
+            .filter(|original_id| self.live_post(*original_id).is_some())
+            .and_then(|original_id| self.post_row(original_id))
+            .map(Box::new)
+    }
+
+    fn post_row(&self, post_id: i32) -> Option<DatabasePost> {
+        let post = self.posts.get(&post_id)?;
+
+        Some(DatabasePost {
+            post_id,
+            user_id: post.user_id,
+            username: self.username(post.user_id)?,
+            title: post.title.clone(),
+            content_html: markdown::render(&post.content),
+            content: post.content.clone(),
+            created_at: post.created_at,
+            edited_at: post.edited_at,
+            likes_count: self.likes_count(post_id),
+            comments_count: self
+                .comments
+                .values()
+                .filter(|comment| comment.post_id == post_id)
+                .count() as i64,
+            attachments: self.attachments(post_id),
+            reactions: self.reactions(post_id),
+            repost_of_id: post.repost_of_id,
+            quote_of_id: post.quote_of_id,
+            reposts_count: self.reposts_count(post_id),
+            original: None,
+            bookmarked: false,
+            status: post.status,
+            publish_at: post.publish_at,
+        })
+    }
+
+    fn attachments(&self, post_id: i32) -> Vec<DatabaseAttachment> {
+        self.attachments
+            .iter()
+            .filter(|(_, attachment)| attachment.post_id == post_id)
+            .map(|(attachment_id, attachment)| DatabaseAttachment {
+                attachment_id: *attachment_id,
+                post_id,
+                file_name: attachment.file_name.clone(),
+                thumbnail_name: attachment.thumbnail_name.clone(),
+                content_type: attachment.content_type.clone(),
+                size_bytes: attachment.size_bytes,
+                width: attachment.width,
+                height: attachment.height,
+            })
+            .collect()
+    }
+
+    fn list_posts(
+        &self,
+        filter: PostsFilter,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage> {
+        let key = |post: &DatabasePost| match sort {
+            PostSort::Newest => (0, post.created_at, post.post_id),
+            PostSort::MostLiked => (post.likes_count, post.created_at, post.post_id),
+        };
+        let cursor_key = cursor.map(|cursor| match sort {
+            PostSort::Newest => (0, cursor.created_at, cursor.post_id),
+            PostSort::MostLiked => (cursor.likes_count, cursor.created_at, cursor.post_id),
+        });
+
+        let mut posts = self
+            .posts
+            .iter()
+            .filter(|(post_id, _)| self.visible_post(**post_id).is_some())
+            .filter(|(post_id, post)| match filter {
+                PostsFilter::All => true,
+                PostsFilter::User(user_id) => post.user_id == user_id,
+                PostsFilter::FollowedBy(user_id) => {
+                    self.follows.contains_key(&(user_id, post.user_id))
+                }
+                PostsFilter::BookmarkedBy(user_id) => {
+                    self.bookmarks.contains(&(user_id, **post_id))
+                }
+            })
+            .filter_map(|(post_id, _)| self.post(*post_id))
+            .filter(|post| cursor_key.is_none_or(|cursor_key| key(post) < cursor_key))
+            .collect::<Vec<_>>();
+        posts.sort_by_key(|post| std::cmp::Reverse(key(post)));
+
+        let limit = usize::try_from(limit)?;
+        let next_cursor = if posts.len() > limit {
+            posts.truncate(limit);
+            posts.last().map(|post| post.cursor(sort))
+        } else {
+            None
+        };
+
+        Ok(PostsPage { posts, next_cursor })
+    }
+
+    /// Removes the post with the rows referencing it.
+    fn remove_post(&mut self, post_id: i32) {
+        self.posts.remove(&post_id);
+        let repost_ids = self
+            .posts
+            .iter()
+            .filter(|(_, post)| post.repost_of_id == Some(post_id))
+            .map(|(repost_id, _)| *repost_id)
+            .collect::<Vec<_>>();
+        for repost_id in repost_ids {
+            self.remove_post(repost_id);
+        }
+        for post in self.posts.values_mut() {
+            if post.quote_of_id == Some(post_id) {
+                post.quote_of_id = None;
+            }
+        }
+        self.reactions
+            .retain(|(reacted_post_id, _, _), _| *reacted_post_id != post_id);
+        self.bookmarks
+            .retain(|(_, bookmarked_post_id)| *bookmarked_post_id != post_id);
+        self.comments
+            .retain(|_, comment| comment.post_id != post_id);
+        self.revisions
+            .retain(|_, revision| revision.post_id != post_id);
+        self.attachments
+            .retain(|_, attachment| attachment.post_id != post_id);
+        self.notifications
+            .retain(|_, notification| notification.post_id != Some(post_id));
+    }
+
+    /// Removes the comment with all replies to it.
+    fn remove_comment(&mut self, comment_id: i32) {
+        let replies = self
+            .comments
+            .iter()
+            .filter(|(_, comment)| comment.parent_comment_id == Some(comment_id))
+            .map(|(reply_id, _)| *reply_id)
+            .collect::<Vec<_>>();
+        for reply_id in replies {
+            self.remove_comment(reply_id);
+        }
+
+        self.comments.remove(&comment_id);
+        self.notifications
+            .retain(|_, notification| notification.comment_id != Some(comment_id));
+    }
+
+    fn notify(
+        &mut self,
+        user_id: i32,
+        actor_id: i32,
+        kind: NotificationKind,
+        post_id: Option<i32>,
+        comment_id: Option<i32>,
+    ) {
+        if user_id == actor_id {
+            return;
+        }
+
+        let notification_id = self.next_id();
+        self.notifications.insert(
+            notification_id,
+            Notification {
+                user_id,
+                actor_id,
+                kind,
+                post_id,
+                comment_id,
+                read_at: None,
+                created_at: now(),
+            },
+        );
+    }
+
+    fn withdraw(
+        &mut self,
+        user_id: i32,
+        actor_id: i32,
+        kind: NotificationKind,
+        post_id: Option<i32>,
+    ) {
+        self.notifications.retain(|_, notification| {
+            !(notification.user_id == user_id
+                && notification.actor_id == actor_id
+                && notification.kind == kind
+                && notification.post_id == post_id
+                && notification.read_at.is_none())
+        });
+    }
+
+    fn revoke_refresh_tokens(&mut self, user_id: i32, now: NaiveDateTime) {
+        for token in self.refresh_tokens.values_mut() {
+            if token.user_id == user_id && token.revoked_at.is_none() {
+                token.revoked_at = Some(now);
+            }
+        }
+    }
+
+    fn log_action(
+        &mut self,
+        moderator_id: i32,
+        action: &str,
+        target_user_id: Option<i32>,
+        target_post_id: Option<i32>,
+        reason: Option<&str>,
+    ) {
+        let action_id = self.next_id();
+        self.moderation_actions.insert(
+            action_id,
+            Action {
+                moderator_id: Some(moderator_id),
+                action: action.to_owned(),
+                target_user_id,
+                target_post_id,
+                reason: reason.map(str::to_owned),
+                created_at: now(),
+            },
+        );
+    }
+
+    fn check_target(
+        &self,
+        moderator_id: i32,
+        moderator_role: Role,
+        user_id: i32,
+    ) -> Option<ModerationResult> {
+        let Some(user) = self.users.get(&user_id) else {
+            return Some(ModerationResult::UserNotFound);
+        };
+
+        if user_id == moderator_id || user.role >= moderator_role {
+            return Some(ModerationResult::NotPermitted);
+        }
+
+        None
+    }
+
+    fn follows(&self, user_id: i32, followers: bool) -> Vec<DatabaseFollow> {
+        let mut follows = self
+            .follows
+            .iter()
+            .filter_map(|(&(follower_id, followee_id), &created_at)| {
+                let other_id = match followers {
+                    true if followee_id == user_id => follower_id,
+                    false if follower_id == user_id => followee_id,
+                    _ => return None,
+                };
+                Some(DatabaseFollow {
+                    user_id: other_id,
+                    username: self.username(other_id)?,
+                    created_at,
+                })
+            })
+            .collect::<Vec<_>>();
+        follows.sort_by_key(|follow| std::cmp::Reverse(follow.created_at));
+        follows
+    }
+}
+
+#[async_trait]
+impl UserRepository for MemoryRepository {
+    async fn register_user(
+        &self,
+        username: &str,
+        password_hash: PasswordHash,
+    ) -> Result<i32, AppError> {
+        let mut state = self.state();
+
+        if state.users.values().any(|user| user.username == username) {
+            return Err(AppError::user_already_exist());
+        }
+
+        let user_id = state.next_id();
+        state.users.insert(
+            user_id,
+            User {
+                username: username.to_owned(),
+                password_hash,
+                role: Role::User,
+                banned_at: None,
+                suspended_until: None,
+                display_name: None,
+                bio: None,
+                avatar: None,
+                created_at: now(),
+            },
+        );
+
+        Ok(user_id)
+    }
+
+    async fn get_login_credentials(&self, username: &str) -> Result<Option<DatabaseUser>> {
+        let state = self.state();
+
+        let Some((&user_id, user)) = state
+            .users
+            .iter()
+            .find(|(_, user)| user.username == username)
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(DatabaseUser {
+            user_id,
+            username: user.username.clone(),
+            password_hash: user.password_hash.clone(),
+            status: state.status(user_id, now()).expect("user exists"),
+            created_at: user.created_at,
+        }))
+    }
+
+    async fn get_username_by_user_id(&self, user_id: i32) -> Result<Option<String>> {
+        Ok(self.state().username(user_id))
+    }
+}
+
+#[async_trait]
+impl PostRepository for MemoryRepository {
+    async fn get_posts(
+        &self,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage> {
+        self.state()
+            .list_posts(PostsFilter::All, sort, cursor, limit)
+    }
+
+    async fn get_post(&self, post_id: i32) -> Result<Option<DatabasePost>> {
+        let state = self.state();
+
+        Ok(state
+            .visible_post(post_id)
+            .and_then(|_| state.post(post_id)))
+    }
+
+    async fn create_post(
+        &self,
+        user_id: i32,
+        title: &str,
+        content: &str,
+        publication: Publication,
+    ) -> Result<i32> {
+        let mut state = self.state();
+
+        let post_id = state.next_id();
+        state.posts.insert(
+            post_id,
+            Post {
+                user_id,
+                title: title.to_owned(),
+                content: content.to_owned(),
+                created_at: now(),
+                edited_at: None,
+                deleted_at: None,
+                repost_of_id: None,
+                quote_of_id: None,
+                status: publication.status(),
+                publish_at: publication.publish_at(),
+            },
+        );
+
+        if publication == Publication::Now {
+            self.events
+                .publish(FeedEvent::PostCreated { post_id, user_id });
+        }
+
+        Ok(post_id)
+    }
+
+    async fn like_post(&self, user_id: i32, post_id: i32) -> Result<Like> {
+        let mut state = self.state();
+
+        let author_id = state.live_post(post_id).map(|post| post.user_id);
+
+        let like = if state
+            .reactions
+            .remove(&(post_id, user_id, ReactionKind::Like))
+            .is_some()
+        {
+            if let Some(author_id) = author_id {
+                state.withdraw(author_id, user_id, NotificationKind::Like, Some(post_id));
+            }
+            Like::Removed
+        } else {
+            // reactions reference posts, as in the database
+            anyhow::ensure!(author_id.is_some(), "post (id = {post_id}) does not exist");
+
+            state
+                .reactions
+                .insert((post_id, user_id, ReactionKind::Like), now());
+            if let Some(author_id) = author_id {
+                state.notify(
+                    author_id,
+                    user_id,
+                    NotificationKind::Like,
+                    Some(post_id),
+                    None,
+                );
+            }
+            Like::Added
+        };
+
+        self.publish_likes_changed(&state, post_id);
+
+        Ok(like)
+    }
+
+    async fn get_like_count(&self, post_id: i32) -> Result<i64> {
+        Ok(self.state().likes_count(post_id))
+    }
+
+    async fn delete_post(&self, post_id: i32, user_id: i32) -> Result<PostDeleteResult> {
+        let mut state = self.state();
+
+        match state.live_post(post_id) {
+            None => Ok(PostDeleteResult::NotFound),
+            Some(post) if post.user_id != user_id => Ok(PostDeleteResult::NotOwned),
+            Some(_) => {
+                let post = state.posts.get_mut(&post_id).expect("post exists");
+                post.deleted_at = Some(now());
+                self.events.publish(FeedEvent::PostDeleted { post_id });
+                self.publish_reposts_deleted(&state, post_id);
+                Ok(PostDeleteResult::Deleted)
+            }
+        }
+    }
+
+    async fn get_deleted_posts(&self, user_id: i32) -> Result<Vec<DeletedPost>> {
+        let state = self.state();
+
+        let mut posts = state
+            .posts
+            .iter()
+            .filter(|(_, post)| post.user_id == user_id)
+            .filter_map(|(post_id, post)| {
+                Some(DeletedPost {
+                    post: state.post(*post_id)?,
+                    deleted_at: post.deleted_at?,
+                })
+            })
+            .collect::<Vec<_>>();
+        posts.sort_by_key(|deleted| std::cmp::Reverse((deleted.deleted_at, deleted.post.post_id)));
+
+        Ok(posts)
+    }
+
+    async fn restore_post(&self, post_id: i32, user_id: i32) -> Result<PostRestoreResult> {
+        let mut state = self.state();
+
+        let Some(post) = state
+            .posts
+            .get_mut(&post_id)
+            .filter(|post| post.user_id == user_id && post.deleted_at.is_some())
+        else {
+            return Ok(PostRestoreResult::NotFound);
+        };
+
+        post.deleted_at = None;
+        if post.status == PostStatus::Published {
+            self.events
+                .publish(FeedEvent::PostCreated { post_id, user_id });
+        }
+
+        Ok(PostRestoreResult::Restored)
+    }
+
+    async fn purge_deleted_posts(
+        &self,
+        deleted_before: NaiveDateTime,
+    ) -> Result<Vec<DatabaseAttachment>> {
+        let mut state = self.state();
+
+        let post_ids = state
+            .posts
+            .iter()
+            .filter(|(_, post)| {
+                post.deleted_at
+                    .is_some_and(|deleted_at| deleted_at < deleted_before)
+            })
+            .map(|(post_id, _)| *post_id)
+            .collect::<Vec<_>>();
+
+        let mut attachments = Vec::new();
+        for post_id in post_ids {
+            attachments.extend(state.attachments(post_id));
+            state.remove_post(post_id);
+        }
+
+        Ok(attachments)
+    }
+
+    async fn get_user_posts(
+        &self,
+        user_id: i32,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage> {
+        self.state()
+            .list_posts(PostsFilter::User(user_id), sort, cursor, limit)
+    }
+}
+
+#[async_trait]
+impl AttachmentRepository for MemoryRepository {
+    async fn create_attachments(
+        &self,
+        post_id: i32,
+        user_id: i32,
+        images: &[StoredImage],
+    ) -> Result<AttachmentCreateResult> {
+        let mut state = self.state();
+
+        let Some(post) = state.authored_post(post_id) else {
+            return Ok(AttachmentCreateResult::PostNotFound);
+        };
+        if post.user_id != user_id {
+            return Ok(AttachmentCreateResult::NotOwned);
+        }
+
+        let attached = state.attachments(post_id).len() + images.len();
+        if i64::try_from(attached)? > MAX_ATTACHMENTS_PER_POST {
+            return Ok(AttachmentCreateResult::TooMany);
+        }
+
+        for image in images {
+            let attachment_id = state.next_id();
+            state.attachments.insert(
+                attachment_id,
+                Attachment {
+                    post_id,
+                    user_id,
+                    file_name: image.file_name.clone(),
+                    thumbnail_name: image.thumbnail_name.clone(),
+                    content_type: image.content_type.clone(),
+                    size_bytes: image.size_bytes,
+                    width: image.width,
+                    height: image.height,
+                },
+            );
+        }
+
+        let created = state
+            .attachments(post_id)
+            .into_iter()
+            .filter(|attachment| {
+                images
+                    .iter()
+                    .any(|image| image.file_name == attachment.file_name)
+            })
+            .collect();
+
+        Ok(AttachmentCreateResult::Created(created))
+    }
+
+    async fn get_attachments(&self, post_id: i32) -> Result<Vec<DatabaseAttachment>> {
+        Ok(self.state().attachments(post_id))
+    }
+}
+
+#[async_trait]
+impl BookmarkRepository for MemoryRepository {
+    async fn add_bookmark(&self, user_id: i32, post_id: i32) -> Result<bool> {
+        let mut state = self.state();
+
+        let Some(original_id) = state.original_id(post_id) else {
+            return Ok(false);
+        };
+
+        state.bookmarks.insert((user_id, original_id));
+
+        Ok(true)
+    }
+
+    async fn remove_bookmark(&self, user_id: i32, post_id: i32) -> Result<bool> {
+        let mut state = self.state();
+
+        let Some(original_id) = state.original_id(post_id) else {
+            return Ok(false);
+        };
+
+        state.bookmarks.remove(&(user_id, original_id));
+
+        Ok(true)
+    }
+
+    async fn get_bookmarks(
+        &self,
+        user_id: i32,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage> {
+        self.state()
+            .list_posts(PostsFilter::BookmarkedBy(user_id), sort, cursor, limit)
+    }
+
+    async fn get_bookmarked(&self, user_id: i32, post_ids: &[i32]) -> Result<HashSet<i32>> {
+        let state = self.state();
+
+        Ok(post_ids
+            .iter()
+            .copied()
+            .filter(|post_id| state.bookmarks.contains(&(user_id, *post_id)))
+            .collect())
+    }
+}
+
+#[async_trait]
+impl CommentRepository for MemoryRepository {
+    async fn create_comment(
+        &self,
+        post_id: i32,
+        user_id: i32,
+        parent_comment_id: Option<i32>,
+        content: &str,
+    ) -> Result<CommentCreateResult> {
+        let mut state = self.state();
+
+        let Some(post_author_id) = state.published_post(post_id).map(|post| post.user_id) else {
+            return Ok(CommentCreateResult::PostNotFound);
+        };
+
+        let parent_author_id = match parent_comment_id {
+            Some(parent_comment_id) => match state.comments.get(&parent_comment_id) {
+                Some(parent) if parent.post_id == post_id => Some(parent.user_id),
+                _ => return Ok(CommentCreateResult::ParentNotFound),
+            },
+            None => None,
+        };
+
+        let comment_id = state.next_id();
+        state.comments.insert(
+            comment_id,
+            Comment {
+                post_id,
+                user_id,
+                parent_comment_id,
+                content: content.to_owned(),
+                created_at: now(),
+            },
+        );
+
+        if let Some(parent_author_id) = parent_author_id {
+            state.notify(
+                parent_author_id,
+                user_id,
+                NotificationKind::Reply,
+                Some(post_id),
+                Some(comment_id),
+            );
+        }
+
+        if parent_author_id != Some(post_author_id) {
+            state.notify(
+                post_author_id,
+                user_id,
+                NotificationKind::Comment,
+                Some(post_id),
+                Some(comment_id),
+            );
+        }
+
+        Ok(CommentCreateResult::Created(comment_id))
+    }
+
+    async fn get_comments(&self, post_id: i32) -> Result<Vec<DatabaseComment>> {
+        let state = self.state();
+
+        let mut comments = state
+            .comments
+            .iter()
+            .filter(|(_, comment)| comment.post_id == post_id)
+            .filter_map(|(comment_id, comment)| {
+                Some(DatabaseComment {
+                    comment_id: *comment_id,
+                    post_id,
+                    user_id: comment.user_id,
+                    username: state.username(comment.user_id)?,
+                    parent_comment_id: comment.parent_comment_id,
+                    content: comment.content.clone(),
+                    created_at: comment.created_at,
+                })
+            })
+            .collect::<Vec<_>>();
+        comments.sort_by_key(|comment| (comment.created_at, comment.comment_id));
+
+        Ok(comments)
+    }
+
+    async fn delete_comment(
+        &self,
+        post_id: i32,
+        comment_id: i32,
+        user_id: i32,
+    ) -> Result<CommentDeleteResult> {
+        let mut state = self.state();
+
+        let Some(post_user_id) = state.live_post(post_id).map(|post| post.user_id) else {
+            return Ok(CommentDeleteResult::NotFound);
+        };
+        let Some(comment) = state
+            .comments
+            .get(&comment_id)
+            .filter(|comment| comment.post_id == post_id)
+        else {
+            return Ok(CommentDeleteResult::NotFound);
+        };
+
+        if user_id != comment.user_id && user_id != post_user_id {
+            return Ok(CommentDeleteResult::NotOwned);
+        }
+
+        state.remove_comment(comment_id);
+
+        Ok(CommentDeleteResult::Deleted)
+    }
+}
+
+#[async_trait]
+impl DraftRepository for MemoryRepository {
+    async fn get_drafts(&self, user_id: i32) -> Result<Vec<DatabasePost>> {
+        let state = self.state();
+
+        let mut posts = state
+            .posts
+            .iter()
+            .filter(|(_, post)| post.user_id == user_id && post.deleted_at.is_none())
+            .filter(|(_, post)| post.status != PostStatus::Published)
+            .filter_map(|(post_id, _)| state.post(*post_id))
+            .collect::<Vec<_>>();
+        posts.sort_by_key(|post| {
+            (
+                post.publish_at.is_none(),
+                post.publish_at,
+                std::cmp::Reverse((post.created_at, post.post_id)),
+            )
+        });
+
+        Ok(posts)
+    }
+
+    async fn get_draft(&self, post_id: i32, user_id: i32) -> Result<Option<DatabasePost>> {
+        let state = self.state();
+
+        Ok(state
+            .live_post(post_id)
+            .filter(|post| post.user_id == user_id && post.status != PostStatus::Published)
+            .and_then(|_| state.post(post_id)))
+    }
+
+    async fn publish_draft(
+        &self,
+        post_id: i32,
+        user_id: i32,
+        publication: Publication,
+    ) -> Result<bool> {
+        let mut state = self.state();
+
+        let Some(post) = state.posts.get_mut(&post_id).filter(|post| {
+            post.user_id == user_id
+                && post.status != PostStatus::Published
+                && post.deleted_at.is_none()
+        }) else {
+            return Ok(false);
+        };
+
+        post.status = publication.status();
+        post.publish_at = publication.publish_at();
+        if publication == Publication::Now {
+            post.created_at = now();
+            self.events
+                .publish(FeedEvent::PostCreated { post_id, user_id });
+        }
+
+        Ok(true)
+    }
+
+    async fn publish_due_posts(&self, now: NaiveDateTime) -> Result<Vec<i32>> {
+        let mut state = self.state();
+
+        let mut post_ids = Vec::new();
+        for (post_id, post) in &mut state.posts {
+            if post.status != PostStatus::Scheduled
+                || post.publish_at.is_none_or(|publish_at| publish_at > now)
+                || post.deleted_at.is_some()
+            {
+                continue;
+            }
+
+            post.status = PostStatus::Published;
+            post.created_at = self::now();
+            self.events.publish(FeedEvent::PostCreated {
+                post_id: *post_id,
+                user_id: post.user_id,
+            });
+            post_ids.push(*post_id);
+        }
+
+        Ok(post_ids)
+    }
+}
+
+#[async_trait]
+impl ExportRepository for MemoryRepository {
+    async fn create_export(&self, user_id: i32) -> Result<ExportCreateResult> {
+        let mut state = self.state();
+
+        if let Some((export_id, export)) = state
+            .exports
+            .iter()
+            .find(|(_, export)| export.user_id == user_id && export.status == ExportStatus::Pending)
+        {
+            return Ok(ExportCreateResult::Pending(export.to_database(*export_id)));
+        }
+
+        let export_id = state.next_id();
+        let export = Export {
+            user_id,
+            status: ExportStatus::Pending,
+            file_name: None,
+            created_at: now(),
+            completed_at: None,
+            expires_at: None,
+        };
+        let created = export.to_database(export_id);
+        state.exports.insert(export_id, export);
+
+        Ok(ExportCreateResult::Created(created))
+    }
+
+    async fn get_export(&self, export_id: i32, user_id: i32) -> Result<Option<DatabaseExport>> {
+        let state = self.state();
+
+        Ok(state
+            .exports
+            .get(&export_id)
+            .filter(|export| export.user_id == user_id)
+            .filter(|export| {
+                export
+                    .expires_at
+                    .is_none_or(|expires_at| expires_at > now())
+            })
+            .map(|export| export.to_database(export_id)))
+    }
+
+    async fn get_user_data(&self, user_id: i32) -> Result<Option<UserData>> {
+        let Some(profile) = self.get_profile(user_id).await? else {
+            return Ok(None);
+        };
+
+        let state = self.state();
+
+        let mut posts = state
+            .posts
+            .iter()
+            .filter(|(_, post)| post.user_id == user_id)
+            .filter_map(|(post_id, post)| {
+                Some(ExportedPost {
+                    post: state.post(*post_id)?,
+                    deleted_at: post.deleted_at,
+                })
+            })
+            .collect::<Vec<_>>();
+        posts.sort_by_key(|exported| (exported.post.created_at, exported.post.post_id));
+
+        let mut reactions = state
+            .reactions
+            .iter()
+            .filter(|((_, reacted_by, _), _)| *reacted_by == user_id)
+            .map(|((post_id, _, kind), created_at)| ExportedReaction {
+                post_id: *post_id,
+                kind: *kind,
+                created_at: *created_at,
+            })
+            .collect::<Vec<_>>();
+        reactions.sort_by_key(|reaction| reaction.created_at);
+
+        let comments = state
+            .comments
+            .iter()
+            .filter(|(_, comment)| comment.user_id == user_id)
+            .map(|(comment_id, comment)| DatabaseComment {
+                comment_id: *comment_id,
+                post_id: comment.post_id,
+                user_id,
+                username: profile.username.clone(),
+                parent_comment_id: comment.parent_comment_id,
+                content: comment.content.clone(),
+                created_at: comment.created_at,
+            })
+            .collect();
+
+        Ok(Some(UserData {
+            profile,
+            posts,
+            reactions,
+            comments,
+        }))
+    }
+
+    async fn finish_export(
+        &self,
+        export_id: i32,
+        file_name: Option<&str>,
+        expires_at: NaiveDateTime,
+    ) -> Result<bool> {
+        let mut state = self.state();
+
+        let Some(export) = state
+            .exports
+            .get_mut(&export_id)
+            .filter(|export| export.status == ExportStatus::Pending)
+        else {
+            return Ok(false);
+        };
+
+        export.status = match file_name {
+            Some(_) => ExportStatus::Ready,
+            None => ExportStatus::Failed,
+        };
+        export.file_name = file_name.map(str::to_owned);
+        export.completed_at = Some(now());
+        export.expires_at = Some(expires_at);
+
+        Ok(true)
+    }
+
+    async fn fail_stale_exports(
+        &self,
+        started_before: NaiveDateTime,
+        expires_at: NaiveDateTime,
+    ) -> Result<u64> {
+        let mut state = self.state();
+
+        let mut failed = 0;
+        for export in state.exports.values_mut() {
+            if export.status == ExportStatus::Pending && export.created_at < started_before {
+                export.status = ExportStatus::Failed;
+                export.completed_at = Some(now());
+                export.expires_at = Some(expires_at);
+                failed += 1;
+            }
+        }
+
+        Ok(failed)
+    }
+
+    async fn purge_expired_exports(&self, now: NaiveDateTime) -> Result<Vec<ExpiredExport>> {
+        let mut state = self.state();
+
+        let mut expired = Vec::new();
+        state.exports.retain(|_, export| {
+            if export.expires_at.is_none_or(|expires_at| expires_at > now) {
+                return true;
+            }
+            expired.push(ExpiredExport {
+                user_id: export.user_id,
+                file_name: export.file_name.take(),
+            });
+            false
+        });
+
+        Ok(expired)
+    }
+}
+
+#[async_trait]
+impl FollowRepository for MemoryRepository {
+    async fn follow_user(&self, follower_id: i32, followee_id: i32) -> Result<()> {
+        let mut state = self.state();
+
+        // follows reference users, as in the database
+        anyhow::ensure!(
+            state.users.contains_key(&followee_id),
+            "user (id = {followee_id}) does not exist"
+        );
+
+        if state
+            .follows
+            .insert((follower_id, followee_id), now())
+            .is_none()
+        {
+            state.notify(
+                followee_id,
+                follower_id,
+                NotificationKind::Follow,
+                None,
+                None,
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn unfollow_user(&self, follower_id: i32, followee_id: i32) -> Result<()> {
+        let mut state = self.state();
+
+        state.follows.remove(&(follower_id, followee_id));
+        state.withdraw(followee_id, follower_id, NotificationKind::Follow, None);
+
+        Ok(())
+    }
+
+    async fn get_follow_counts(
+        &self,
+        user_id: i32,
+        viewer_id: Option<i32>,
+    ) -> Result<FollowCounts> {
+        let state = self.state();
+
+        Ok(FollowCounts {
+            followers_count: state
+                .follows
+                .keys()
+                .filter(|(_, followee_id)| *followee_id == user_id)
+                .count() as i64,
+            following_count: state
+                .follows
+                .keys()
+                .filter(|(follower_id, _)| *follower_id == user_id)
+                .count() as i64,
+            is_followed: viewer_id
+                .is_some_and(|viewer_id| state.follows.contains_key(&(viewer_id, user_id))),
+        })
+    }
+
+    async fn get_followers(&self, user_id: i32) -> Result<Vec<DatabaseFollow>> {
+        Ok(self.state().follows(user_id, true))
+    }
+
+    async fn get_following(&self, user_id: i32) -> Result<Vec<DatabaseFollow>> {
+        Ok(self.state().follows(user_id, false))
+    }
+
+    async fn get_feed(
+        &self,
+        user_id: i32,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage> {
+        self.state()
+            .list_posts(PostsFilter::FollowedBy(user_id), sort, cursor, limit)
+    }
+}
+
+#[async_trait]
+impl ModerationRepository for MemoryRepository {
+    async fn get_user_status(
+        &self,
+        user_id: i32,
+        now: NaiveDateTime,
+    ) -> Result<Option<UserStatus>> {
+        Ok(self.state().status(user_id, now))
+    }
+
+    async fn moderate_delete_post(
+        &self,
+        moderator_id: i32,
+        post_id: i32,
+        reason: Option<&str>,
+    ) -> Result<bool> {
+        let mut state = self.state();
+
+        let Some(user_id) = state.posts.get(&post_id).map(|post| post.user_id) else {
+            return Ok(false);
+        };
+
+        self.publish_reposts_deleted(&state, post_id);
+        state.remove_post(post_id);
+        state.log_action(
+            moderator_id,
+            "delete_post",
+            Some(user_id),
+            Some(post_id),
+            reason,
+        );
+        self.events.publish(FeedEvent::PostDeleted { post_id });
+
+        Ok(true)
+    }
+
+    async fn block_user(
+        &self,
+        moderator_id: i32,
+        moderator_role: Role,
+        user_id: i32,
+        until: Option<NaiveDateTime>,
+        reason: Option<&str>,
+        now: NaiveDateTime,
+    ) -> Result<ModerationResult> {
+        let mut state = self.state();
+
+        if let Some(result) = state.check_target(moderator_id, moderator_role, user_id) {
+            return Ok(result);
+        }
+
+        let user = state.users.get_mut(&user_id).expect("target is checked");
+        let action = match until {
+            Some(until) => {
+                user.suspended_until = Some(until);
+                "suspend_user"
+            }
+            None => {
+                user.banned_at = Some(now);
+                "ban_user"
+            }
+        };
+
+        state.revoke_refresh_tokens(user_id, now);
+        state.log_action(moderator_id, action, Some(user_id), None, reason);
+
+        Ok(ModerationResult::Done)
+    }
+
+    async fn unblock_user(
+        &self,
+        moderator_id: i32,
+        moderator_role: Role,
+        user_id: i32,
+        reason: Option<&str>,
+    ) -> Result<ModerationResult> {
+        let mut state = self.state();
+
+        if let Some(result) = state.check_target(moderator_id, moderator_role, user_id) {
+            return Ok(result);
+        }
+
+        let user = state.users.get_mut(&user_id).expect("target is checked");
+        user.suspended_until = None;
+        user.banned_at = None;
+
+        state.log_action(moderator_id, "unblock_user", Some(user_id), None, reason);
+
+        Ok(ModerationResult::Done)
+    }
+
+    async fn change_role(
+        &self,
+        admin_id: i32,
+        user_id: i32,
+        role: Role,
+    ) -> Result<ModerationResult> {
+        let mut state = self.state();
+
+        if let Some(result) = state.check_target(admin_id, Role::Admin, user_id) {
+            return Ok(result);
+        }
+
+        state
+            .users
+            .get_mut(&user_id)
+            .expect("target is checked")
+            .role = role;
+
+        let reason = format!("New role: {}", role.as_str());
+        state.log_action(admin_id, "change_role", Some(user_id), None, Some(&reason));
+
+        Ok(ModerationResult::Done)
+    }
+
+    async fn get_moderation_actions(&self, limit: i64) -> Result<Vec<ModerationAction>> {
+        let state = self.state();
+
+        state
+            .moderation_actions
+            .iter()
+            .rev()
+            .take(usize::try_from(limit)?)
+            .map(|(action_id, action)| {
+                Ok(ModerationAction {
+                    action_id: *action_id,
+                    moderator_id: action.moderator_id,
+                    moderator_username: action
+                        .moderator_id
+                        .and_then(|user_id| state.username(user_id)),
+                    action: action.action.clone(),
+                    target_user_id: action.target_user_id,
+                    target_username: action
+                        .target_user_id
+                        .and_then(|user_id| state.username(user_id)),
+                    target_post_id: action.target_post_id,
+                    reason: action.reason.clone(),
+                    created_at: action.created_at,
+                })
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl NotificationRepository for MemoryRepository {
+    async fn get_notifications(
+        &self,
+        user_id: i32,
+        before: Option<i32>,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<NotificationsPage> {
+        let state = self.state();
+
+        let mut notifications = state
+            .notifications
+            .iter()
+            .rev()
+            .filter(|(notification_id, notification)| {
+                notification.user_id == user_id
+                    && before.is_none_or(|before| **notification_id < before)
+                    && (!unread_only || notification.read_at.is_none())
+            })
+            .filter_map(|(notification_id, notification)| {
+                Some(DatabaseNotification {
+                    notification_id: *notification_id,
+                    kind: notification.kind,
+                    actor_id: notification.actor_id,
+                    actor_username: state.username(notification.actor_id)?,
+                    post_id: notification.post_id,
+                    post_title: notification
+                        .post_id
+                        .and_then(|post_id| state.posts.get(&post_id))
+                        .map(|post| post.title.clone()),
+                    comment_id: notification.comment_id,
+                    is_read: notification.read_at.is_some(),
+                    created_at: notification.created_at,
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let limit = usize::try_from(limit)?;
+        let next_before = if notifications.len() > limit {
+            notifications.truncate(limit);
+            notifications
+                .last()
+                .map(|notification| notification.notification_id)
+        } else {
+            None
+        };
+
+        let unread_count = state
+            .notifications
+            .values()
+            .filter(|notification| {
+                notification.user_id == user_id && notification.read_at.is_none()
+            })
+            .count() as i64;
+
+        Ok(NotificationsPage {
+            notifications,
+            unread_count,
+            next_before,
+        })
+    }
+
+    async fn mark_notifications_read(
+        &self,
+        user_id: i32,
+        notification_ids: Option<&[i32]>,
+        now: NaiveDateTime,
+    ) -> Result<u64> {
+        let mut state = self.state();
+
+        let mut marked = 0;
+        for (notification_id, notification) in state.notifications.iter_mut() {
+            if notification.user_id == user_id
+                && notification.read_at.is_none()
+                && notification_ids.is_none_or(|ids| ids.contains(notification_id))
+            {
+                notification.read_at = Some(now);
+                marked += 1;
+            }
+        }
+
+        Ok(marked)
+    }
+}
+
+#[async_trait]
+impl ProfileRepository for MemoryRepository {
+    async fn get_profile(&self, user_id: i32) -> Result<Option<UserProfile>> {
+        let state = self.state();
+
+        Ok(state.users.get(&user_id).map(|user| UserProfile {
+            user_id,
+            username: user.username.clone(),
+            display_name: user.display_name.clone(),
+            bio: user.bio.clone(),
+            avatar_file_name: user.avatar.as_ref().map(|avatar| avatar.0.clone()),
+            avatar_thumbnail_name: user.avatar.as_ref().map(|avatar| avatar.1.clone()),
+            role: user.role,
+            created_at: user.created_at,
+        }))
+    }
+
+    async fn update_profile(
+        &self,
+        user_id: i32,
+        display_name: Option<&str>,
+        bio: Option<&str>,
+    ) -> Result<bool> {
+        let mut state = self.state();
+
+        let Some(user) = state.users.get_mut(&user_id) else {
+            return Ok(false);
+        };
+
+        let non_empty = |value: &str| (!value.is_empty()).then(|| value.to_owned());
+        if let Some(display_name) = display_name {
+            user.display_name = non_empty(display_name);
+        }
+        if let Some(bio) = bio {
+            user.bio = non_empty(bio);
+        }
+
+        Ok(true)
+    }
+
+    async fn set_avatar(
+        &self,
+        user_id: i32,
+        avatar: Option<&StoredImage>,
+    ) -> Result<Option<Vec<String>>> {
+        let mut state = self.state();
+
+        let Some(user) = state.users.get_mut(&user_id) else {
+            return Ok(None);
+        };
+
+        let previous = std::mem::replace(
+            &mut user.avatar,
+            avatar.map(|avatar| (avatar.file_name.clone(), avatar.thumbnail_name.clone())),
+        );
+
+        Ok(Some(
+            previous
+                .map(|(file_name, thumbnail_name)| vec![file_name, thumbnail_name])
+                .unwrap_or_default(),
+        ))
+    }
+
+    async fn get_password_hash(&self, user_id: i32) -> Result<Option<PasswordHash>> {
+        Ok(self
+            .state()
+            .users
+            .get(&user_id)
+            .map(|user| user.password_hash.clone()))
+    }
+
+    async fn change_password(
+        &self,
+        user_id: i32,
+        password_hash: PasswordHash,
+        now: NaiveDateTime,
+    ) -> Result<()> {
+        let mut state = self.state();
+
+        if let Some(user) = state.users.get_mut(&user_id) {
+            user.password_hash = password_hash;
+        }
+        state.revoke_refresh_tokens(user_id, now);
+
+        Ok(())
+    }
+
+    async fn delete_user(&self, user_id: i32) -> Result<Option<Vec<String>>> {
+        let mut state = self.state();
+
+        let Some(user) = state.users.remove(&user_id) else {
+            return Ok(None);
+        };
+
+        let mut files = user
+            .avatar
+            .map(|(file_name, thumbnail_name)| vec![file_name, thumbnail_name])
+            .unwrap_or_default();
+        for attachment in state.attachments.values() {
+            if attachment.user_id == user_id {
+                files.push(attachment.file_name.clone());
+                files.push(attachment.thumbnail_name.clone());
+            }
+        }
+
+        let post_ids = state
+            .posts
+            .iter()
+            .filter(|(_, post)| post.user_id == user_id)
+            .map(|(post_id, _)| *post_id)
+            .collect::<Vec<_>>();
+        for post_id in post_ids {
+            state.remove_post(post_id);
+            self.events.publish(FeedEvent::PostDeleted { post_id });
+        }
+
+        let comment_ids = state
+            .comments
+            .iter()
+            .filter(|(_, comment)| comment.user_id == user_id)
+            .map(|(comment_id, _)| *comment_id)
+            .collect::<Vec<_>>();
+        for comment_id in comment_ids {
+            state.remove_comment(comment_id);
+        }
+
+        state
+            .reactions
+            .retain(|(_, reacted_by, _), _| *reacted_by != user_id);
+        state
+            .bookmarks
+            .retain(|(bookmarked_by, _)| *bookmarked_by != user_id);
+        state.follows.retain(|(follower_id, followee_id), _| {
+            *follower_id != user_id && *followee_id != user_id
+        });
+        state.exports.retain(|_, export| export.user_id != user_id);
+        state
+            .refresh_tokens
+            .retain(|_, token| token.user_id != user_id);
+        state.notifications.retain(|_, notification| {
+            notification.user_id != user_id && notification.actor_id != user_id
+        });
+        for action in state.moderation_actions.values_mut() {
+            if action.moderator_id == Some(user_id) {
+                action.moderator_id = None;
+            }
+            if action.target_user_id == Some(user_id) {
+                action.target_user_id = None;
+            }
+        }
+
+        Ok(Some(files))
+    }
+}
+
+#[async_trait]
+impl ReactionRepository for MemoryRepository {
+    async fn add_reaction(
+        &self,
+        user_id: i32,
+        post_id: i32,
+        kind: ReactionKind,
+    ) -> Result<Option<ReactionCounts>> {
+        let mut state = self.state();
+
+        let Some(author_id) = state.published_post(post_id).map(|post| post.user_id) else {
+            return Ok(None);
+        };
+
+        let added = !state.reactions.contains_key(&(post_id, user_id, kind));
+        if added {
+            state.reactions.insert((post_id, user_id, kind), now());
+        }
+        if added && kind == ReactionKind::Like {
+            state.notify(
+                author_id,
+                user_id,
+                NotificationKind::Like,
+                Some(post_id),
+                None,
+            );
+            self.publish_likes_changed(&state, post_id);
+        }
+
+        Ok(Some(state.reactions(post_id)))
+    }
+
+    async fn remove_reaction(
+        &self,
+        user_id: i32,
+        post_id: i32,
+        kind: ReactionKind,
+    ) -> Result<Option<ReactionCounts>> {
+        let mut state = self.state();
+
+        let Some(author_id) = state.published_post(post_id).map(|post| post.user_id) else {
+            return Ok(None);
+        };
+
+        let removed = state.reactions.remove(&(post_id, user_id, kind)).is_some();
+        if removed && kind == ReactionKind::Like {
+            state.withdraw(author_id, user_id, NotificationKind::Like, Some(post_id));
+            self.publish_likes_changed(&state, post_id);
+        }
+
+        Ok(Some(state.reactions(post_id)))
+    }
+}
+
+#[async_trait]
+impl RepostRepository for MemoryRepository {
+    async fn repost(&self, user_id: i32, post_id: i32) -> Result<Option<RepostState>> {
+        let mut state = self.state();
+
+        let Some(original_id) = state.original_id(post_id) else {
+            return Ok(None);
+        };
+
+        let existing = state
+            .posts
+            .iter()
+            .find(|(_, post)| post.user_id == user_id && post.repost_of_id == Some(original_id))
+            .map(|(repost_id, post)| (*repost_id, post.deleted_at.is_some()));
+        let repost_id = match existing {
+            Some((repost_id, is_deleted)) => {
+                if is_deleted {
+                    state
+                        .posts
+                        .get_mut(&repost_id)
+                        .expect("repost exists")
+                        .deleted_at = None;
+                    self.events.publish(FeedEvent::PostCreated {
+                        post_id: repost_id,
+                        user_id,
+                    });
+                }
+                repost_id
+            }
+            None => {
+                let repost_id = state.next_id();
+                state.posts.insert(
+                    repost_id,
+                    Post {
+                        user_id,
+                        title: String::new(),
+                        content: String::new(),
+                        created_at: now(),
+                        edited_at: None,
+                        deleted_at: None,
+                        repost_of_id: Some(original_id),
+                        quote_of_id: None,
+                        status: PostStatus::Published,
+                        publish_at: None,
+                    },
+                );
+                self.events.publish(FeedEvent::PostCreated {
+                    post_id: repost_id,
+                    user_id,
+                });
+                repost_id
+            }
+        };
+
+        Ok(Some(RepostState {
+            repost_id: Some(repost_id),
+            reposts_count: state.reposts_count(original_id),
+        }))
+    }
+
+    async fn remove_repost(&self, user_id: i32, post_id: i32) -> Result<Option<RepostState>> {
+        let mut state = self.state();
+
+        let Some(original_id) = state.original_id(post_id) else {
+            return Ok(None);
+        };
+
+        let repost_id = state
+            .posts
+            .iter()
+            .find(|(_, post)| post.user_id == user_id && post.repost_of_id == Some(original_id))
+            .map(|(repost_id, _)| *repost_id);
+        if let Some(repost_id) = repost_id {
+            state.remove_post(repost_id);
+            self.events
+                .publish(FeedEvent::PostDeleted { post_id: repost_id });
+        }
+
+        Ok(Some(RepostState {
+            repost_id: None,
+            reposts_count: state.reposts_count(original_id),
+        }))
+    }
+
+    async fn create_quote(
+        &self,
+        user_id: i32,
+        quote_of_id: i32,
+        title: &str,
+        content: &str,
+        publication: Publication,
+    ) -> Result<Option<i32>> {
+        let mut state = self.state();
+
+        let Some(original_id) = state.original_id(quote_of_id) else {
+            return Ok(None);
+        };
+
+        let post_id = state.next_id();
+        state.posts.insert(
+            post_id,
+            Post {
+                user_id,
+                title: title.to_owned(),
+                content: content.to_owned(),
+                created_at: now(),
+                edited_at: None,
+                deleted_at: None,
+                repost_of_id: None,
+                quote_of_id: Some(original_id),
+                status: publication.status(),
+                publish_at: publication.publish_at(),
+            },
+        );
+
+        if publication == Publication::Now {
+            self.events
+                .publish(FeedEvent::PostCreated { post_id, user_id });
+        }
+
+        Ok(Some(post_id))
+    }
+}
+
+#[async_trait]
+impl RevisionRepository for MemoryRepository {
+    async fn edit_post(
+        &self,
+        post_id: i32,
+        user_id: i32,
+        title: Option<&str>,
+        content: Option<&str>,
+    ) -> Result<PostEditResult> {
+        let mut state = self.state();
+
+        let Some(post) = state.authored_post(post_id) else {
+            return Ok(PostEditResult::NotFound);
+        };
+        if post.user_id != user_id {
+            return Ok(PostEditResult::NotOwned);
+        }
+
+        let new_title = title.unwrap_or(&post.title).to_owned();
+        let new_content = content.unwrap_or(&post.content).to_owned();
+        if new_title == post.title && new_content == post.content {
+            return Ok(PostEditResult::Unchanged);
+        }
+
+        let revision = Revision {
+            post_id,
+            title: post.title.clone(),
+            content: post.content.clone(),
+            created_at: now(),
+        };
+        let revision_id = state.next_id();
+        state.revisions.insert(revision_id, revision);
+
+        let post = state.posts.get_mut(&post_id).expect("post exists");
+        post.title = new_title;
+        post.content = new_content;
+        post.edited_at = Some(now());
+
+        Ok(PostEditResult::Edited)
+    }
+
+    async fn get_post_revisions(&self, post_id: i32) -> Result<Vec<DatabaseRevision>> {
+        Ok(self
+            .state()
+            .revisions
+            .iter()
+            .rev()
+            .filter(|(_, revision)| revision.post_id == post_id)
+            .map(|(revision_id, revision)| DatabaseRevision {
+                revision_id: *revision_id,
+                post_id,
+                title: revision.title.clone(),
+                content: revision.content.clone(),
+                created_at: revision.created_at,
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl SearchRepository for MemoryRepository {
+    /// Every word of the query must occur in the title or the content, case insensitive.
+    /// There is no ranking, newer posts come first. The snippets are the whole title and
+    /// content, highlighted the same way as `ts_headline` output.
+    async fn search_posts(&self, query: &str, limit: i64) -> Result<Vec<SearchedPost>> {
+        let state = self.state();
+
+        let words = query
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect::<Vec<_>>();
+
+        Ok(state
+            .posts
+            .iter()
+            .rev()
+            .filter(|(_, post)| post.deleted_at.is_none() && post.status == PostStatus::Published)
+            .filter(|(_, post)| {
+                let text = format!("{} {}", post.title, post.content).to_lowercase();
+                !words.is_empty() && words.iter().all(|word| text.contains(word))
+            })
+            .take(usize::try_from(limit)?)
+            .filter_map(|(post_id, _)| {
+                let post = state.post(*post_id)?;
+                Some(SearchedPost {
+                    rank: 1.0,
+                    title_snippet: search::highlight(&mark_matches(&post.title, &words)),
+                    content_snippet: search::highlight(&mark_matches(&post.content, &words)),
+                    post,
+                })
+            })
+            .collect())
+    }
+
+    async fn search_users(&self, prefix: &str, limit: i64) -> Result<Vec<SearchedUser>> {
+        let state = self.state();
+
+        let prefix = prefix.to_lowercase();
+        let mut users = state
+            .users
+            .iter()
+            .filter(|(_, user)| user.username.to_lowercase().starts_with(&prefix))
+            .map(|(user_id, user)| SearchedUser {
+                user_id: *user_id,
+                username: user.username.clone(),
+            })
+            .collect::<Vec<_>>();
+        users.sort_by(|a, b| a.username.cmp(&b.username));
+        users.truncate(usize::try_from(limit)?);
+
+        Ok(users)
+    }
+}
+
+/// Wraps the words of `text` that contain a word of the query into the delimiters of
+/// matches, like `ts_headline` does.
+fn mark_matches(text: &str, words: &[String]) -> String {
+    let mut marked = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find(char::is_alphanumeric) {
+        marked.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let end = rest
+            .find(|c: char| !c.is_alphanumeric())
+            .unwrap_or(rest.len());
+        let word = &rest[..end];
+        let lowercase = word.to_lowercase();
+        if words
+            .iter()
+            .any(|query_word| lowercase.contains(query_word))
+        {
+            marked.push(search::MATCH_START);
+            marked.push_str(word);
+            marked.push(search::MATCH_END);
+        } else {
+            marked.push_str(word);
+        }
+        rest = &rest[end..];
+    }
+    marked.push_str(rest);
+
+    marked
+}
+
+#[async_trait]
+impl TokenRepository for MemoryRepository {
+    async fn store_refresh_token(
+        &self,
+        user_id: i32,
+        token_hash: &str,
+        expires_at: NaiveDateTime,
+    ) -> Result<()> {
+        self.state().refresh_tokens.insert(
+            token_hash.to_owned(),
+            RefreshToken {
+                user_id,
+                expires_at,
+                revoked_at: None,
+            },
+        );
+
+        Ok(())
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        token_hash: &str,
+        new_token_hash: &str,
+        new_expires_at: NaiveDateTime,
+        now: NaiveDateTime,
+    ) -> Result<RefreshTokenRotation> {
+        let mut state = self.state();
+
+        let Some(token) = state.refresh_tokens.get_mut(token_hash) else {
+            return Ok(RefreshTokenRotation::NotFound);
+        };
+        let user_id = token.user_id;
+
+        if token.revoked_at.is_some() {
+            state.revoke_refresh_tokens(user_id, now);
+            return Ok(RefreshTokenRotation::Reused);
+        }
+
+        if token.expires_at <= now {
+            return Ok(RefreshTokenRotation::Expired);
+        }
+
+        token.revoked_at = Some(now);
+        state.refresh_tokens.insert(
+            new_token_hash.to_owned(),
+            RefreshToken {
+                user_id,
+                expires_at: new_expires_at,
+                revoked_at: None,
+            },
+        );
+
+        Ok(RefreshTokenRotation::Rotated { user_id })
+    }
+
+    async fn revoke_session(
+        &self,
+        user_id: i32,
+        jti: &str,
+        _access_expires_at: NaiveDateTime,
+        refresh_token_hash: Option<&str>,
+        now: NaiveDateTime,
+    ) -> Result<()> {
+        let mut state = self.state();
+
+        state.revoked_jtis.insert(jti.to_owned());
+
+        if let Some(token) =
+            refresh_token_hash.and_then(|token_hash| state.refresh_tokens.get_mut(token_hash))
+        {
+            if token.user_id == user_id && token.revoked_at.is_none() {
+                token.revoked_at = Some(now);
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn check_access(
+        &self,
+        user_id: i32,
+        jti: &str,
+        now: NaiveDateTime,
+    ) -> Result<AccessCheck> {
+        let state = self.state();
+
+        let Some(status) = state.status(user_id, now) else {
+            return Ok(AccessCheck::Revoked);
+        };
+
+        if state.revoked_jtis.contains(jti) {
+            return Ok(AccessCheck::Revoked);
+        }
+
+        match status.block {
+            Some(block) => Ok(AccessCheck::Blocked(block)),
+            None => Ok(AccessCheck::Allowed { role: status.role }),
+        }
+    }
+}
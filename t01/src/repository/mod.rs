@@ -1,19 +1,158 @@
 use anyhow::Result;
+use async_trait::async_trait;
+use deadpool_postgres::GenericClient;
 use log::info;
 use refinery::embed_migrations;
 use serde::Serialize;
-use tokio_postgres::Row;
+use std::{collections::HashMap, sync::Arc};
+use tokio_postgres::{types::ToSql, Row};
+use utoipa::ToSchema;
+
+use crate::{
+    error::AppError,
+    events::{FeedEvent, FEED_EVENTS_CHANNEL},
+    markdown,
+    model::{NotificationKind, PostCursor, PostSort, PostStatus, ReactionKind},
+    utils::PasswordHash,
+};
+
+mod attachments;
+mod bookmarks;
+mod comments;
+mod drafts;
+mod exports;
+mod follows;
+mod moderation;
+mod notifications;
+mod profiles;
+mod reactions;
+mod reposts;
+mod revisions;
+mod search;
+mod tokens;
+
+#[cfg(test)]
+mod memory;
+
+pub(crate) use attachments::{
+    AttachmentCreateResult, AttachmentRepository, DatabaseAttachment, MAX_ATTACHMENTS_PER_POST,
+};
+pub(crate) use bookmarks::BookmarkRepository;
+pub(crate) use comments::{
+    CommentCreateResult, CommentDeleteResult, CommentRepository, CommentThread, DatabaseComment,
+};
+pub(crate) use drafts::{DraftRepository, Publication};
+pub(crate) use exports::{DatabaseExport, ExportCreateResult, ExportRepository, UserData};
+pub(crate) use follows::{DatabaseFollow, FollowCounts, FollowRepository};
+#[cfg(test)]
+pub(crate) use memory::MemoryRepository;
+pub(crate) use moderation::{
+    ModerationAction, ModerationRepository, ModerationResult, UserBlock, UserStatus,
+};
+pub(crate) use notifications::{DatabaseNotification, NotificationRepository, NotificationsPage};
+pub(crate) use profiles::{ProfileRepository, UserProfile};
+pub(crate) use reactions::{ReactionCounts, ReactionRepository};
+pub(crate) use reposts::RepostRepository;
+pub(crate) use revisions::{DatabaseRevision, PostEditResult, RevisionRepository};
+pub(crate) use search::{SearchRepository, SearchedPost, SearchedUser};
+pub(crate) use tokens::{AccessCheck, RefreshTokenRotation, TokenRepository};
+
+/// Every storage operation of the application. Handlers only see this trait, so the
+/// router works the same over Postgres and over the in-memory backend of the tests.
+pub(crate) trait Repository:
+    UserRepository
+    + PostRepository
+    + AttachmentRepository
+    + BookmarkRepository
+    + CommentRepository
+    + DraftRepository
+    + ExportRepository
+    + FollowRepository
+    + ModerationRepository
+    + NotificationRepository
+    + ProfileRepository
+    + ReactionRepository
+    + RepostRepository
+    + RevisionRepository
+    + SearchRepository
+    + TokenRepository
+{
+}
+
+impl<T> Repository for T where
+    T: UserRepository
+        + PostRepository
+        + AttachmentRepository
+        + BookmarkRepository
+        + CommentRepository
+        + DraftRepository
+        + ExportRepository
+        + FollowRepository
+        + ModerationRepository
+        + NotificationRepository
+        + ProfileRepository
+        + ReactionRepository
+        + RepostRepository
+        + RevisionRepository
+        + SearchRepository
+        + TokenRepository
+{
+}
 
-use crate::{error::AppError, utils::PasswordHash};
+/// Repository shared by all handlers.
+pub(crate) type SharedRepository = Arc<dyn Repository>;
 
 #[derive(Clone)]
-pub(crate) struct Repository {
+pub(crate) struct PostgresRepository {
     pool: deadpool_postgres::Pool,
 }
 
 embed_migrations!("migrations");
 
-impl Repository {
+/// Columns of [`DatabasePost`] and of [`DeletedPost`], followed by `where`/`order by`
+/// clauses of the caller. Posts in the trash have `deleted_at` set, callers listing
+/// published posts filter them out.
+const POST_SELECT: &str = "
+    select
+        p.post_id,
+        p.user_id,
+        u.username,
+        p.title,
+        p.content,
+        p.created_at,
+        p.edited_at,
+        p.likes_count,
+        p.comments_count,
+        p.repost_of_id,
+        p.quote_of_id,
+        (
+            select count(*)
+            from posts r
+            where r.repost_of_id = p.post_id and r.deleted_at is null
+        ) as reposts_count,
+        p.status,
+        p.publish_at,
+        p.deleted_at
+    from posts p
+    join users u on p.user_id = u.user_id
+";
+
+/// Condition on `p` of [`POST_SELECT`] for the posts shown to readers: published and
+/// not in the trash, and for reposts, the reposted post is not in the trash either.
+const VISIBLE_POST: &str = "
+    p.deleted_at is null
+    and p.status = 'published'
+    and (
+        p.repost_of_id is null
+        or exists (
+            select 1
+            from posts o
+            where o.post_id = p.repost_of_id and o.deleted_at is null
+        )
+    )
+";
+
+impl PostgresRepository {
     pub(crate) async fn initialize(config: deadpool_postgres::Config) -> Result<Self> {
         info!("Initializing repository");
         info!("Trying to connect to database...");
@@ -31,7 +170,118 @@ impl Repository {
         Ok(Self { pool })
     }
 
-    pub(crate) async fn register_user(
+    /// Keyset pagination over `posts`: every sort mode has a matching index, so a page
+    /// costs `limit` index entries no matter how deep the cursor is.
+    async fn list_posts(
+        &self,
+        filter: PostsFilter,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage> {
+        let connection = self.pool.get().await?;
+
+        let mut params: Vec<&(dyn ToSql + Sync)> = Vec::new();
+        let mut conditions = vec![VISIBLE_POST.to_owned()];
+
+        match &filter {
+            PostsFilter::All => {}
+            PostsFilter::User(user_id) => {
+                params.push(user_id);
+                conditions.push(format!("p.user_id = ${}", params.len()));
+            }
+            PostsFilter::FollowedBy(user_id) => {
+                params.push(user_id);
+                conditions.push(format!(
+                    "p.user_id in (select followee_id from follows where follower_id = ${})",
+                    params.len()
+                ));
+            }
+            PostsFilter::BookmarkedBy(user_id) => {
+                params.push(user_id);
+                conditions.push(format!(
+                    "p.post_id in (select post_id from bookmarks where user_id = ${})",
+                    params.len()
+                ));
+            }
+        }
+
+        if let Some(cursor) = cursor {
+            match sort {
+                PostSort::Newest => {
+                    params.push(&cursor.created_at);
+                    params.push(&cursor.post_id);
+                    conditions.push(format!(
+                        "(p.created_at, p.post_id) < (${}, ${})",
+                        params.len() - 1,
+                        params.len()
+                    ));
+                }
+                PostSort::MostLiked => {
+                    params.push(&cursor.likes_count);
+                    params.push(&cursor.created_at);
+                    params.push(&cursor.post_id);
+                    conditions.push(format!(
+                        "(p.likes_count, p.created_at, p.post_id) < (${}, ${}, ${})",
+                        params.len() - 2,
+                        params.len() - 1,
+                        params.len()
+                    ));
+                }
+            }
+        }
+
+        let order = match sort {
+            PostSort::Newest => "p.created_at desc, p.post_id desc",
+            PostSort::MostLiked => "p.likes_count desc, p.created_at desc, p.post_id desc",
+        };
+
+        // one extra row tells whether there is a next page
+        let fetch_limit = limit + 1;
+        params.push(&fetch_limit);
+
+        let query = format!(
+            "{POST_SELECT} where {} order by {order} limit ${};",
+            conditions.join(" and "),
+            params.len()
+        );
+        let rows = connection.query(&query, &params).await?;
+
+        let mut posts = rows
+            .into_iter()
+            .map(DatabasePost::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        let limit = usize::try_from(limit)?;
+        let next_cursor = if posts.len() > limit {
+            posts.truncate(limit);
+            posts.last().map(|post| post.cursor(sort))
+        } else {
+            None
+        };
+
+        load_details(&connection, &mut posts).await?;
+
+        Ok(PostsPage { posts, next_cursor })
+    }
+}
+
+#[async_trait]
+pub(crate) trait UserRepository: Send + Sync {
+    async fn register_user(
+        &self,
+        username: &str,
+        password_hash: PasswordHash,
+    ) -> Result<i32, AppError>;
+
+    async fn get_login_credentials(&self, username: &str) -> Result<Option<DatabaseUser>>;
+
+    async fn get_username_by_user_id(&self, user_id: i32) -> Result<Option<String>>;
+}
+
+#[async_trait]
+impl UserRepository for PostgresRepository {
+    async fn register_user(
         &self,
         username: &str,
         password_hash: PasswordHash,
@@ -71,10 +321,7 @@ impl Repository {
         Ok(user_id)
     }
 
-    pub(crate) async fn get_login_credentials(
-        &self,
-        username: &str,
-    ) -> Result<Option<DatabaseUser>> {
+    async fn get_login_credentials(&self, username: &str) -> Result<Option<DatabaseUser>> {
         info!("Verifying user credentials");
         let mut connection = self.pool.get().await?;
 
@@ -83,7 +330,7 @@ impl Repository {
         let transaction = connection.transaction().await?;
 
         let query = "
-            select user_id, username, password_hash, created_at
+            select user_id, username, password_hash, role, banned_at, suspended_until, created_at
             from users
             where username = $1;
         ";
@@ -93,7 +340,7 @@ impl Repository {
         };
 
         info!("User found in database with username: '{username}'");
-        let user = DatabaseUser::try_from(row)?;
+        let user = DatabaseUser::from_row(row, chrono::Utc::now().naive_utc())?;
 
         transaction.commit().await?;
         info!("Transaction for user credentials successfully ended");
@@ -101,67 +348,114 @@ impl Repository {
         Ok(Some(user))
     }
 
-    pub(crate) async fn get_posts(&self) -> Result<Vec<DatabasePost>> {
+    async fn get_username_by_user_id(&self, user_id: i32) -> Result<Option<String>> {
         let mut connection = self.pool.get().await?;
 
-        info!("Transaction for listing posts started");
+        info!("Transaction for listing user posts started");
 
         let transaction = connection.transaction().await?;
 
         let query = "
-            select
-                p.post_id,
-                p.user_id,
-                u.username,
-                p.title,
-                p.content,
-                p.created_at,
-                count(l.like_id) as likes_count
-                from posts p
-            join users u on p.user_id = u.user_id
-            left join likes l on p.post_id = l.post_id
-            group by p.post_id, p.user_id, u.username, p.title, p.content, p.created_at
-            order by p.created_at desc;
+            select username
+            from users
+            where user_id = $1;
         ";
-        let rows = transaction.query(query, &[]).await?;
+        let Some(row) = transaction.query_opt(query, &[&user_id]).await? else {
+            return Ok(None);
+        };
 
-        let posts = rows
-            .into_iter()
-            .map(DatabasePost::try_from)
-            .collect::<Result<Vec<_>>>()?;
+        let username: String = row.try_get("username")?;
 
         transaction.commit().await?;
 
-        info!("Transaction for listing posts successfully ended");
+        info!("Transaction for listing user posts successfully ended");
+
+        Ok(Some(username))
+    }
+}
+
+#[async_trait]
+pub(crate) trait PostRepository: Send + Sync {
+    async fn get_posts(
+        &self,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage>;
+
+    async fn get_post(&self, post_id: i32) -> Result<Option<DatabasePost>>;
+
+    async fn create_post(
+        &self,
+        user_id: i32,
+        title: &str,
+        content: &str,
+        publication: Publication,
+    ) -> Result<i32>;
+
+    /// Toggles the `like` reaction of the user.
+    async fn like_post(&self, user_id: i32, post_id: i32) -> Result<Like>;
+
+    async fn get_like_count(&self, post_id: i32) -> Result<i64>;
+
+    /// Moves the post to the trash of its author. Likes, comments and attachments are kept
+    /// until the post is restored or purged.
+    async fn delete_post(&self, post_id: i32, user_id: i32) -> Result<PostDeleteResult>;
+
+    /// Posts in the trash of the user, most recently deleted first.
+    async fn get_deleted_posts(&self, user_id: i32) -> Result<Vec<DeletedPost>>;
+
+    async fn restore_post(&self, post_id: i32, user_id: i32) -> Result<PostRestoreResult>;
+
+    /// Deletes the posts moved to the trash before `deleted_before` for good and returns
+    /// their attachments, whose files are left to the caller.
+    async fn purge_deleted_posts(
+        &self,
+        deleted_before: chrono::NaiveDateTime,
+    ) -> Result<Vec<DatabaseAttachment>>;
+
+    async fn get_user_posts(
+        &self,
+        user_id: i32,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage>;
+}
+
+#[async_trait]
+impl PostRepository for PostgresRepository {
+    async fn get_posts(
+        &self,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage> {
+        info!("Listing posts started");
+
+        let posts = self
+            .list_posts(PostsFilter::All, sort, cursor, limit)
+            .await?;
+
+        info!("Listing posts successfully ended");
 
         Ok(posts)
     }
 
-    pub(crate) async fn get_post(&self, post_id: i32) -> Result<Option<DatabasePost>> {
+    async fn get_post(&self, post_id: i32) -> Result<Option<DatabasePost>> {
         let mut connection = self.pool.get().await?;
 
         info!("Transaction for getting post with id = {post_id} started");
 
         let transaction = connection.transaction().await?;
 
-        let query = "
-        select
-                p.post_id,
-                p.user_id,
-                u.username,
-                p.title,
-                p.content,
-                p.created_at,
-                count(l.like_id) as likes_count
-                from posts p
-                join users u on p.user_id = u.user_id
-            left join likes l on p.post_id = l.post_id
-            where p.post_id = $1
-            group by p.post_id, p.user_id, u.username, p.title, p.content, p.created_at;
-        ";
-        let row = transaction.query_opt(query, &[&post_id]).await?;
+        let query = format!("{POST_SELECT} where p.post_id = $1 and {VISIBLE_POST};");
+        let row = transaction.query_opt(&query, &[&post_id]).await?;
 
-        let post = row.map(DatabasePost::try_from).transpose()?;
+        let mut post = row.map(DatabasePost::try_from).transpose()?;
+        if let Some(post) = &mut post {
+            load_details(&transaction, std::slice::from_mut(post)).await?;
+        }
 
         transaction.commit().await?;
 
@@ -170,11 +464,12 @@ impl Repository {
         Ok(post)
     }
 
-    pub(crate) async fn create_post(
+    async fn create_post(
         &self,
         user_id: i32,
         title: &str,
         content: &str,
+        publication: Publication,
     ) -> Result<i32> {
         let mut connection = self.pool.get().await?;
 
@@ -183,16 +478,29 @@ impl Repository {
         let transaction = connection.transaction().await?;
 
         let query = "
-            insert into posts (user_id, title, content)
-            values ($1, $2, $3)
+            insert into posts (user_id, title, content, status, publish_at)
+            values ($1, $2, $3, $4, $5)
             returning post_id;
         ";
         let row = transaction
-            .query_one(query, &[&user_id, &title, &content])
+            .query_one(
+                query,
+                &[
+                    &user_id,
+                    &title,
+                    &content,
+                    &publication.status().as_str(),
+                    &publication.publish_at(),
+                ],
+            )
             .await?;
 
         let post_id: i32 = row.try_get(0)?;
 
+        if publication == Publication::Now {
+            publish(&transaction, &FeedEvent::PostCreated { post_id, user_id }).await?;
+        }
+
         transaction.commit().await?;
 
         info!("Transaction for creating post by user with id = {user_id} successfully ended");
@@ -200,28 +508,57 @@ impl Repository {
         Ok(post_id)
     }
 
-    pub(crate) async fn like_post(&self, user_id: i32, post_id: i32) -> Result<Like> {
+    async fn like_post(&self, user_id: i32, post_id: i32) -> Result<Like> {
         let mut connection = self.pool.get().await?;
 
         info!("Transaction for liking/disliking post (id = {post_id}) by user (id = {user_id}) started");
 
         let transaction = connection.transaction().await?;
 
+        let kind = ReactionKind::Like.as_str();
+
+        let query = "
+            select reaction_id
+            from reactions
+            where user_id = $1 and post_id = $2 and kind = $3;
+        ";
+        let row = transaction
+            .query_opt(query, &[&user_id, &post_id, &kind])
+            .await?;
+
         let query = "
-            select like_id
-            from likes
-            where user_id = $1 and post_id = $2;
+            select user_id
+            from posts
+            where post_id = $1 and deleted_at is null;
         ";
-        let row = transaction.query_opt(query, &[&user_id, &post_id]).await?;
+        let author_id: Option<i32> = transaction
+            .query_opt(query, &[&post_id])
+            .await?
+            .map(|row| row.try_get("user_id"))
+            .transpose()?;
 
         if row.is_some() {
             let delete_query = "
-                delete from likes
-                where user_id = $1 and post_id = $2;
+                delete from reactions
+                where user_id = $1 and post_id = $2 and kind = $3;
             ";
             transaction
-                .execute(delete_query, &[&user_id, &post_id])
+                .execute(delete_query, &[&user_id, &post_id, &kind])
+                .await?;
+
+            if let Some(author_id) = author_id {
+                notifications::withdraw(
+                    &transaction,
+                    author_id,
+                    user_id,
+                    NotificationKind::Like,
+                    Some(post_id),
+                )
                 .await?;
+            }
+
+            publish_likes_changed(&transaction, post_id).await?;
+
             transaction.commit().await?;
 
             info!("Transaction for liking/disliking post (id = {post_id}) by user (id = {user_id}) successfully ended");
@@ -229,13 +566,27 @@ impl Repository {
             Ok(Like::Removed)
         } else {
             let insert_query = "
-                insert into likes (user_id, post_id)
-                values ($1, $2);
+                insert into reactions (user_id, post_id, kind)
+                values ($1, $2, $3);
             ";
             transaction
-                .execute(insert_query, &[&user_id, &post_id])
+                .execute(insert_query, &[&user_id, &post_id, &kind])
                 .await?;
 
+            if let Some(author_id) = author_id {
+                notifications::notify(
+                    &transaction,
+                    author_id,
+                    user_id,
+                    NotificationKind::Like,
+                    Some(post_id),
+                    None,
+                )
+                .await?;
+            }
+
+            publish_likes_changed(&transaction, post_id).await?;
+
             transaction.commit().await?;
 
             info!("Transaction for liking/disliking post (id = {post_id}) by user (id = {user_id}) successfully ended");
@@ -244,7 +595,7 @@ impl Repository {
         }
     }
 
-    pub(crate) async fn get_like_count(&self, post_id: i32) -> Result<i64> {
+    async fn get_like_count(&self, post_id: i32) -> Result<i64> {
         let mut connection = self.pool.get().await?;
 
         info!("Transaction for obtaining likes count for post (id = {post_id}) started");
@@ -253,11 +604,13 @@ impl Repository {
 
         let query = "
             select count(*)
-            from likes
-            where post_id = $1;
+            from reactions
+            where post_id = $1 and kind = $2;
         ";
 
-        let row = transaction.query_one(query, &[&post_id]).await?;
+        let row = transaction
+            .query_one(query, &[&post_id, &ReactionKind::Like.as_str()])
+            .await?;
 
         let likes_count: i64 = row.try_get(0)?;
 
@@ -268,7 +621,7 @@ impl Repository {
         Ok(likes_count)
     }
 
-    pub(crate) async fn delete_post(&self, post_id: i32, user_id: i32) -> Result<PostDeleteResult> {
+    async fn delete_post(&self, post_id: i32, user_id: i32) -> Result<PostDeleteResult> {
         let mut connection = self.pool.get().await?;
 
         info!("Transaction for deleting post (id = {post_id}) by user (id = {user_id}) started");
@@ -278,111 +631,162 @@ impl Repository {
         let query = "
             select user_id
             from posts
-            where post_id=$1;
+            where post_id = $1 and deleted_at is null
+            for update;
         ";
-        let row = transaction.query_opt(query, &[&post_id]).await?;
-        let rows_owned = match row {
-            Some(row) => {
-                let post_user_id: i32 = row.try_get("user_id")?;
-                if post_user_id == user_id {
-                    1 // post exists and is owned by the user
-                } else {
-                    0 // post exists but is not owned by the user
-                }
-            }
-            None => 0, // post does not exist
+        let Some(row) = transaction.query_opt(query, &[&post_id]).await? else {
+            return Ok(PostDeleteResult::NotFound);
         };
 
+        let post_user_id: i32 = row.try_get("user_id")?;
+        if post_user_id != user_id {
+            return Ok(PostDeleteResult::NotOwned);
+        }
+
         let query = "
-            delete from posts
-            where post_id=$1 and user_id=$2
-            returning post_id;
+            update posts
+            set deleted_at = current_timestamp
+            where post_id = $1;
         ";
-        let row = transaction.query_opt(query, &[&post_id, &user_id]).await?;
-        let rows_deleted = match row {
-            Some(_) => 1, // Post was deleted
-            None => 0,    // Post was not deleted
-        };
+        transaction.execute(query, &[&post_id]).await?;
 
-        let delete_result = match (rows_owned, rows_deleted) {
-            (0, 0) => PostDeleteResult::NotFound,
-            (0, 1) => unreachable!("Wrong state: post is not found, but was deleted"),
-            (1, 0) => PostDeleteResult::NotOwned,
-            (1, 1) => PostDeleteResult::Deleted,
-            (_, _) => unreachable!(),
-        };
+        publish(&transaction, &FeedEvent::PostDeleted { post_id }).await?;
+        reposts::publish_reposts_deleted(&transaction, post_id).await?;
 
         transaction.commit().await?;
 
         info!("Transaction for deleting post (id = {post_id}) by user (id = {user_id}) successfully ended");
 
-        Ok(delete_result)
+        Ok(PostDeleteResult::Deleted)
+    }
+
+    async fn get_deleted_posts(&self, user_id: i32) -> Result<Vec<DeletedPost>> {
+        let connection = self.pool.get().await?;
+
+        let query = format!(
+            "{POST_SELECT} where p.user_id = $1 and p.deleted_at is not null
+             order by p.deleted_at desc, p.post_id desc;"
+        );
+        let rows = connection.query(&query, &[&user_id]).await?;
+
+        let mut deleted_at = Vec::with_capacity(rows.len());
+        let mut posts = Vec::with_capacity(rows.len());
+        for row in rows {
+            deleted_at.push(row.try_get("deleted_at")?);
+            posts.push(DatabasePost::try_from(row)?);
+        }
+
+        load_details(&connection, &mut posts).await?;
+
+        Ok(posts
+            .into_iter()
+            .zip(deleted_at)
+            .map(|(post, deleted_at)| DeletedPost { post, deleted_at })
+            .collect())
     }
 
-    pub(crate) async fn get_user_posts(&self, user_id: i32) -> Result<Vec<DatabasePost>> {
+    async fn restore_post(&self, post_id: i32, user_id: i32) -> Result<PostRestoreResult> {
         let mut connection = self.pool.get().await?;
 
-        info!("Transaction for listing user posts started");
+        info!("Transaction for restoring post (id = {post_id}) by user (id = {user_id}) started");
 
         let transaction = connection.transaction().await?;
 
         let query = "
-            select
-                p.post_id,
-                p.user_id,
-                u.username,
-                p.title,
-                p.content,
-                p.created_at,
-                count(l.like_id) as likes_count
-                from posts p
-            join users u on p.user_id = u.user_id
-            left join likes l on p.post_id = l.post_id
-            where p.user_id = $1
-            group by p.post_id, p.user_id, u.username, p.title, p.content, p.created_at
-            order by p.created_at desc;
+            update posts
+            set deleted_at = null
+            where post_id = $1 and user_id = $2 and deleted_at is not null
+            returning status;
         ";
-        let rows = transaction.query(query, &[&user_id]).await?;
+        let Some(row) = transaction.query_opt(query, &[&post_id, &user_id]).await? else {
+            return Ok(PostRestoreResult::NotFound);
+        };
 
-        let posts = rows
-            .into_iter()
-            .map(DatabasePost::try_from)
-            .collect::<Result<Vec<_>>>()?;
+        // drafts come back to the drafts, unseen by the others
+        let status: String = row.try_get("status")?;
+        if status.parse::<PostStatus>()? == PostStatus::Published {
+            publish(&transaction, &FeedEvent::PostCreated { post_id, user_id }).await?;
+        }
 
         transaction.commit().await?;
 
-        info!("Transaction for listing user posts successfully ended");
+        info!("Transaction for restoring post (id = {post_id}) by user (id = {user_id}) successfully ended");
 
-        Ok(posts)
+        Ok(PostRestoreResult::Restored)
     }
 
-    pub(crate) async fn get_username_by_user_id(&self, user_id: i32) -> Result<Option<String>> {
+    async fn purge_deleted_posts(
+        &self,
+        deleted_before: chrono::NaiveDateTime,
+    ) -> Result<Vec<DatabaseAttachment>> {
         let mut connection = self.pool.get().await?;
 
-        info!("Transaction for listing user posts started");
+        info!("Transaction for purging posts deleted before {deleted_before} started");
 
         let transaction = connection.transaction().await?;
 
+        // locked, so that a post restored meanwhile is not purged
         let query = "
-            select username
-            from users
-            where user_id = $1;
+            select post_id
+            from posts
+            where deleted_at < $1
+            for update;
         ";
-        let Some(row) = transaction.query_opt(query, &[&user_id]).await? else {
-            return Ok(None);
-        };
+        let post_ids = transaction
+            .query(query, &[&deleted_before])
+            .await?
+            .into_iter()
+            .map(|row| row.try_get("post_id"))
+            .collect::<Result<Vec<i32>, _>>()?;
 
-        let username: String = row.try_get("username")?;
+        let query = "
+            select attachment_id, post_id, file_name, thumbnail_name, content_type, size_bytes, width, height
+            from attachments
+            where post_id = any($1);
+        ";
+        let attachments = transaction
+            .query(query, &[&post_ids])
+            .await?
+            .into_iter()
+            .map(DatabaseAttachment::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        let query = "
+            delete from posts
+            where post_id = any($1);
+        ";
+        transaction.execute(query, &[&post_ids]).await?;
 
         transaction.commit().await?;
 
-        info!("Transaction for listing user posts successfully ended");
+        info!(
+            "Transaction for purging posts deleted before {deleted_before} successfully ended, {} posts purged",
+            post_ids.len()
+        );
 
-        Ok(Some(username))
+        Ok(attachments)
+    }
+
+    async fn get_user_posts(
+        &self,
+        user_id: i32,
+        sort: PostSort,
+        cursor: Option<&PostCursor>,
+        limit: i64,
+    ) -> Result<PostsPage> {
+        info!("Listing posts of user (id = {user_id}) started");
+
+        let posts = self
+            .list_posts(PostsFilter::User(user_id), sort, cursor, limit)
+            .await?;
+
+        info!("Listing posts of user (id = {user_id}) successfully ended");
+
+        Ok(posts)
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub(crate) enum Like {
     Added,
     Removed,
@@ -394,50 +798,245 @@ pub(crate) enum PostDeleteResult {
     NotOwned,
 }
 
+pub(crate) enum PostRestoreResult {
+    Restored,
+    /// The post is not in the trash of the user.
+    NotFound,
+}
+
 #[derive(Debug)]
 pub(crate) struct DatabaseUser {
     pub(crate) user_id: i32,
     pub(crate) username: String,
     pub(crate) password_hash: PasswordHash,
+    pub(crate) status: UserStatus,
     pub(crate) created_at: chrono::NaiveDateTime,
 }
 
-impl TryFrom<Row> for DatabaseUser {
-    type Error = anyhow::Error;
-
-    fn try_from(row: Row) -> Result<Self> {
+impl DatabaseUser {
+    fn from_row(row: Row, now: chrono::NaiveDateTime) -> Result<Self> {
         Ok(Self {
             user_id: row.try_get("user_id")?,
             username: row.try_get("username")?,
             password_hash: PasswordHash::try_from(&row)?,
+            status: UserStatus::from_row(&row, now)?,
             created_at: row.try_get("created_at")?,
         })
     }
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize, ToSchema)]
 pub(crate) struct DatabasePost {
     pub(crate) post_id: i32,
     pub(crate) user_id: i32,
     pub(crate) username: String,
     pub(crate) title: String,
+    /// Markdown source, returned as is for editing.
     pub(crate) content: String,
+    /// Sanitized HTML rendered from `content`.
+    pub(crate) content_html: String,
     pub(crate) created_at: chrono::NaiveDateTime,
+    pub(crate) edited_at: Option<chrono::NaiveDateTime>,
+    /// Number of `like` reactions, kept for the `most_liked` order.
     pub(crate) likes_count: i64,
+    pub(crate) comments_count: i64,
+    pub(crate) attachments: Vec<DatabaseAttachment>,
+    pub(crate) reactions: ReactionCounts,
+    /// Set on reposts, which have no title and content of their own.
+    pub(crate) repost_of_id: Option<i32>,
+    /// Set on quote posts, until the quoted post is deleted for good.
+    pub(crate) quote_of_id: Option<i32>,
+    pub(crate) reposts_count: i64,
+    /// The reposted or the quoted post, `null` while it is in the trash.
+    #[schema(no_recursion)]
+    pub(crate) original: Option<Box<DatabasePost>>,
+    /// Whether the current user bookmarked the post.
+    pub(crate) bookmarked: bool,
+    /// Drafts and scheduled posts are shown to their author only.
+    pub(crate) status: PostStatus,
+    /// Set on scheduled posts.
+    pub(crate) publish_at: Option<chrono::NaiveDateTime>,
+}
+
+/// Post in the trash, purged once the retention period is over.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct DeletedPost {
+    #[serde(flatten)]
+    pub(crate) post: DatabasePost,
+    pub(crate) deleted_at: chrono::NaiveDateTime,
+}
+
+impl DatabasePost {
+    /// The post to show in its place: the reposted post for reposts, `None` if it is
+    /// unavailable, the post itself otherwise.
+    pub(crate) fn shown_post(&self) -> Option<&DatabasePost> {
+        match self.repost_of_id {
+            Some(_) => self.original.as_deref(),
+            None => Some(self),
+        }
+    }
+
+    fn original_id(&self) -> Option<i32> {
+        self.repost_of_id.or(self.quote_of_id)
+    }
+
+    fn cursor(&self, sort: PostSort) -> PostCursor {
+        PostCursor {
+            sort,
+            likes_count: self.likes_count,
+            created_at: self.created_at,
+            post_id: self.post_id,
+        }
+    }
 }
 
 impl TryFrom<Row> for DatabasePost {
     type Error = anyhow::Error;
 
     fn try_from(row: Row) -> Result<Self> {
+        let content: String = row.try_get("content")?;
+
         Ok(Self {
             post_id: row.try_get("post_id")?,
             user_id: row.try_get("user_id")?,
             username: row.try_get("username")?,
             title: row.try_get("title")?,
-            content: row.try_get("content")?,
+            content_html: markdown::render(&content),
+            content,
             created_at: row.try_get("created_at")?,
+            edited_at: row.try_get("edited_at")?,
             likes_count: row.try_get("likes_count")?,
+            comments_count: row.try_get("comments_count")?,
+            attachments: Vec::new(),
+            reactions: reactions::empty_counts(),
+            repost_of_id: row.try_get("repost_of_id")?,
+            quote_of_id: row.try_get("quote_of_id")?,
+            reposts_count: row.try_get("reposts_count")?,
+            original: None,
+            bookmarked: false,
+            status: row.try_get::<_, String>("status")?.parse()?,
+            publish_at: row.try_get("publish_at")?,
+        })
+    }
+}
+
+/// Fills attachments, reactions and the reposted or quoted posts of `posts`.
+pub(super) async fn load_details(
+    client: &impl GenericClient,
+    posts: &mut [DatabasePost],
+) -> Result<()> {
+    attachments::load_attachments(client, posts).await?;
+    reactions::load_reactions(client, posts).await?;
+    load_originals(client, posts).await?;
+
+    // a reposted quote post is shown in full, so with the post it quotes
+    let reposted_quotes = posts
+        .iter()
+        .enumerate()
+        .filter(|(_, post)| post.repost_of_id.is_some())
+        .filter(|(_, post)| {
+            post.original
+                .as_ref()
+                .is_some_and(|original| original.quote_of_id.is_some())
         })
+        .map(|(index, _)| index)
+        .collect::<Vec<_>>();
+    let mut quotes = reposted_quotes
+        .iter()
+        .filter_map(|index| posts[*index].original.take())
+        .map(|quote| *quote)
+        .collect::<Vec<_>>();
+    load_originals(client, &mut quotes).await?;
+    for (index, quote) in reposted_quotes.into_iter().zip(quotes) {
+        posts[index].original = Some(Box::new(quote));
+    }
+
+    Ok(())
+}
+
+/// Fills the reposted or quoted posts of `posts`, one level only: a quoted quote post
+/// is shown without the post it quotes.
+async fn load_originals(client: &impl GenericClient, posts: &mut [DatabasePost]) -> Result<()> {
+    let original_ids = posts
+        .iter()
+        .filter_map(DatabasePost::original_id)
+        .collect::<Vec<_>>();
+    if original_ids.is_empty() {
+        return Ok(());
     }
+
+    let query = format!("{POST_SELECT} where p.post_id = any($1) and p.deleted_at is null;");
+    let mut originals = client
+        .query(&query, &[&original_ids])
+        .await?
+        .into_iter()
+        .map(DatabasePost::try_from)
+        .collect::<Result<Vec<_>>>()?;
+    attachments::load_attachments(client, &mut originals).await?;
+    reactions::load_reactions(client, &mut originals).await?;
+
+    let originals = originals
+        .into_iter()
+        .map(|original| (original.post_id, original))
+        .collect::<HashMap<_, _>>();
+    for post in posts {
+        post.original = post
+            .original_id()
+            .and_then(|original_id| originals.get(&original_id))
+            .cloned()
+            .map(Box::new);
+    }
+
+    Ok(())
+}
+
+/// Sends the event to every server instance once the transaction of `client` commits.
+pub(super) async fn publish(client: &impl GenericClient, event: &FeedEvent) -> Result<()> {
+    let query = "select pg_notify($1, $2);";
+    client
+        .execute(
+            query,
+            &[&FEED_EVENTS_CHANNEL, &serde_json::to_string(event)?],
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub(super) async fn publish_likes_changed(client: &impl GenericClient, post_id: i32) -> Result<()> {
+    let query = "
+        select likes_count
+        from posts
+        where post_id = $1;
+    ";
+    let Some(row) = client.query_opt(query, &[&post_id]).await? else {
+        return Ok(());
+    };
+
+    let likes_count: i64 = row.try_get("likes_count")?;
+    publish(
+        client,
+        &FeedEvent::LikesChanged {
+            post_id,
+            likes_count,
+        },
+    )
+    .await
+}
+
+/// Which posts [`PostgresRepository::list_posts`] returns.
+enum PostsFilter {
+    All,
+    /// Posts written by the user.
+    User(i32),
+    /// Posts of the users followed by the user.
+    FollowedBy(i32),
+    /// Posts bookmarked by the user.
+    BookmarkedBy(i32),
+}
+
+#[derive(Debug)]
+pub(crate) struct PostsPage {
+    pub(crate) posts: Vec<DatabasePost>,
+    pub(crate) next_cursor: Option<PostCursor>,
 }
@@ -0,0 +1,438 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use deadpool_postgres::GenericClient;
+use log::info;
+use serde::Serialize;
+use tokio_postgres::Row;
+use utoipa::ToSchema;
+
+use super::PostgresRepository;
+use crate::{events::FeedEvent, model::Role};
+
+#[async_trait]
+pub(crate) trait ModerationRepository: Send + Sync {
+    async fn get_user_status(&self, user_id: i32, now: NaiveDateTime)
+        -> Result<Option<UserStatus>>;
+
+    /// Deletes any post regardless of its owner, for good: unlike a delete by the owner
+    /// it does not go through the trash, so that the owner can not restore it.
+    async fn moderate_delete_post(
+        &self,
+        moderator_id: i32,
+        post_id: i32,
+        reason: Option<&str>,
+    ) -> Result<bool>;
+
+    /// Suspends the user until `until`, or bans them if `until` is `None`.
+    /// All refresh tokens of the user are revoked.
+    async fn block_user(
+        &self,
+        moderator_id: i32,
+        moderator_role: Role,
+        user_id: i32,
+        until: Option<NaiveDateTime>,
+        reason: Option<&str>,
+        now: NaiveDateTime,
+    ) -> Result<ModerationResult>;
+
+    /// Lifts both suspension and ban of the user.
+    async fn unblock_user(
+        &self,
+        moderator_id: i32,
+        moderator_role: Role,
+        user_id: i32,
+        reason: Option<&str>,
+    ) -> Result<ModerationResult>;
+
+    async fn change_role(
+        &self,
+        admin_id: i32,
+        user_id: i32,
+        role: Role,
+    ) -> Result<ModerationResult>;
+
+    /// Audit trail of moderator actions, the most recent first.
+    async fn get_moderation_actions(&self, limit: i64) -> Result<Vec<ModerationAction>>;
+}
+
+#[async_trait]
+impl ModerationRepository for PostgresRepository {
+    async fn get_user_status(
+        &self,
+        user_id: i32,
+        now: NaiveDateTime,
+    ) -> Result<Option<UserStatus>> {
+        let connection = self.pool.get().await?;
+
+        let query = "
+            select role, banned_at, suspended_until
+            from users
+            where user_id = $1;
+        ";
+        let Some(row) = connection.query_opt(query, &[&user_id]).await? else {
+            return Ok(None);
+        };
+
+        Ok(Some(UserStatus::from_row(&row, now)?))
+    }
+
+    async fn moderate_delete_post(
+        &self,
+        moderator_id: i32,
+        post_id: i32,
+        reason: Option<&str>,
+    ) -> Result<bool> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for deleting post (id = {post_id}) by moderator (id = {moderator_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        // before the delete, which takes the reposts along
+        super::reposts::publish_reposts_deleted(&transaction, post_id).await?;
+
+        let query = "
+            delete from posts
+            where post_id = $1
+            returning user_id;
+        ";
+        let Some(row) = transaction.query_opt(query, &[&post_id]).await? else {
+            return Ok(false);
+        };
+        let user_id: i32 = row.try_get("user_id")?;
+
+        log_action(
+            &transaction,
+            moderator_id,
+            "delete_post",
+            Some(user_id),
+            Some(post_id),
+            reason,
+        )
+        .await?;
+
+        super::publish(&transaction, &FeedEvent::PostDeleted { post_id }).await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for deleting post (id = {post_id}) by moderator (id = {moderator_id}) successfully ended");
+
+        Ok(true)
+    }
+
+    async fn block_user(
+        &self,
+        moderator_id: i32,
+        moderator_role: Role,
+        user_id: i32,
+        until: Option<NaiveDateTime>,
+        reason: Option<&str>,
+        now: NaiveDateTime,
+    ) -> Result<ModerationResult> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for blocking user (id = {user_id}) by moderator (id = {moderator_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        if let Some(result) =
+            check_target(&transaction, moderator_id, moderator_role, user_id).await?
+        {
+            return Ok(result);
+        }
+
+        let (query, action) = match until {
+            Some(_) => (
+                "update users set suspended_until = $2 where user_id = $1;",
+                "suspend_user",
+            ),
+            None => (
+                "update users set banned_at = $2 where user_id = $1;",
+                "ban_user",
+            ),
+        };
+        transaction
+            .execute(query, &[&user_id, &until.unwrap_or(now)])
+            .await?;
+
+        let query = "
+            update refresh_tokens
+            set revoked_at = $2
+            where user_id = $1 and revoked_at is null;
+        ";
+        transaction.execute(query, &[&user_id, &now]).await?;
+
+        log_action(
+            &transaction,
+            moderator_id,
+            action,
+            Some(user_id),
+            None,
+            reason,
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for blocking user (id = {user_id}) by moderator (id = {moderator_id}) successfully ended");
+
+        Ok(ModerationResult::Done)
+    }
+
+    async fn unblock_user(
+        &self,
+        moderator_id: i32,
+        moderator_role: Role,
+        user_id: i32,
+        reason: Option<&str>,
+    ) -> Result<ModerationResult> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for unblocking user (id = {user_id}) by moderator (id = {moderator_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        if let Some(result) =
+            check_target(&transaction, moderator_id, moderator_role, user_id).await?
+        {
+            return Ok(result);
+        }
+
+        let query = "
+            update users
+            set suspended_until = null, banned_at = null
+            where user_id = $1;
+        ";
+        transaction.execute(query, &[&user_id]).await?;
+
+        log_action(
+            &transaction,
+            moderator_id,
+            "unblock_user",
+            Some(user_id),
+            None,
+            reason,
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for unblocking user (id = {user_id}) by moderator (id = {moderator_id}) successfully ended");
+
+        Ok(ModerationResult::Done)
+    }
+
+    async fn change_role(
+        &self,
+        admin_id: i32,
+        user_id: i32,
+        role: Role,
+    ) -> Result<ModerationResult> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for changing role of user (id = {user_id}) by admin (id = {admin_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        if let Some(result) = check_target(&transaction, admin_id, Role::Admin, user_id).await? {
+            return Ok(result);
+        }
+
+        let query = "
+            update users
+            set role = $2
+            where user_id = $1;
+        ";
+        transaction
+            .execute(query, &[&user_id, &role.as_str()])
+            .await?;
+
+        let reason = format!("New role: {}", role.as_str());
+        log_action(
+            &transaction,
+            admin_id,
+            "change_role",
+            Some(user_id),
+            None,
+            Some(&reason),
+        )
+        .await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for changing role of user (id = {user_id}) by admin (id = {admin_id}) successfully ended");
+
+        Ok(ModerationResult::Done)
+    }
+
+    async fn get_moderation_actions(&self, limit: i64) -> Result<Vec<ModerationAction>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for listing moderation actions started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select
+                a.action_id,
+                a.moderator_id,
+                m.username as moderator_username,
+                a.action,
+                a.target_user_id,
+                t.username as target_username,
+                a.target_post_id,
+                a.reason,
+                a.created_at
+            from moderation_actions a
+            left join users m on a.moderator_id = m.user_id
+            left join users t on a.target_user_id = t.user_id
+            order by a.created_at desc, a.action_id desc
+            limit $1;
+        ";
+        let rows = transaction.query(query, &[&limit]).await?;
+
+        let actions = rows
+            .into_iter()
+            .map(ModerationAction::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for listing moderation actions successfully ended");
+
+        Ok(actions)
+    }
+}
+
+/// Moderators can act only on users with lower role, and never on themselves.
+async fn check_target(
+    client: &impl GenericClient,
+    moderator_id: i32,
+    moderator_role: Role,
+    user_id: i32,
+) -> Result<Option<ModerationResult>> {
+    let query = "
+        select role
+        from users
+        where user_id = $1
+        for update;
+    ";
+    let Some(row) = client.query_opt(query, &[&user_id]).await? else {
+        return Ok(Some(ModerationResult::UserNotFound));
+    };
+    let role: Role = row.try_get::<_, &str>("role")?.parse()?;
+
+    if user_id == moderator_id || role >= moderator_role {
+        return Ok(Some(ModerationResult::NotPermitted));
+    }
+
+    Ok(None)
+}
+
+async fn log_action(
+    client: &impl GenericClient,
+    moderator_id: i32,
+    action: &str,
+    target_user_id: Option<i32>,
+    target_post_id: Option<i32>,
+    reason: Option<&str>,
+) -> Result<()> {
+    let query = "
+        insert into moderation_actions (moderator_id, action, target_user_id, target_post_id, reason)
+        values ($1, $2, $3, $4, $5);
+    ";
+    client
+        .execute(
+            query,
+            &[
+                &moderator_id,
+                &action,
+                &target_user_id,
+                &target_post_id,
+                &reason,
+            ],
+        )
+        .await?;
+
+    Ok(())
+}
+
+pub(crate) enum ModerationResult {
+    Done,
+    UserNotFound,
+    NotPermitted,
+}
+
+/// Reason why a user can not log in or use their tokens.
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum UserBlock {
+    Banned,
+    Suspended { until: NaiveDateTime },
+}
+
+impl UserBlock {
+    pub(super) fn from_columns(
+        banned_at: Option<NaiveDateTime>,
+        suspended_until: Option<NaiveDateTime>,
+        now: NaiveDateTime,
+    ) -> Option<Self> {
+        if banned_at.is_some() {
+            return Some(UserBlock::Banned);
+        }
+        suspended_until
+            .filter(|until| *until > now)
+            .map(|until| UserBlock::Suspended { until })
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct UserStatus {
+    pub(crate) role: Role,
+    pub(crate) block: Option<UserBlock>,
+}
+
+impl UserStatus {
+    /// Reads `role`, `banned_at` and `suspended_until` columns.
+    pub(super) fn from_row(row: &Row, now: NaiveDateTime) -> Result<Self> {
+        Ok(Self {
+            role: row.try_get::<_, &str>("role")?.parse()?,
+            block: UserBlock::from_columns(
+                row.try_get("banned_at")?,
+                row.try_get("suspended_until")?,
+                now,
+            ),
+        })
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct ModerationAction {
+    pub(crate) action_id: i32,
+    pub(crate) moderator_id: Option<i32>,
+    pub(crate) moderator_username: Option<String>,
+    pub(crate) action: String,
+    pub(crate) target_user_id: Option<i32>,
+    pub(crate) target_username: Option<String>,
+    pub(crate) target_post_id: Option<i32>,
+    pub(crate) reason: Option<String>,
+    pub(crate) created_at: chrono::NaiveDateTime,
+}
+
+impl TryFrom<Row> for ModerationAction {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self> {
+        Ok(Self {
+            action_id: row.try_get("action_id")?,
+            moderator_id: row.try_get("moderator_id")?,
+            moderator_username: row.try_get("moderator_username")?,
+            action: row.try_get("action")?,
+            target_user_id: row.try_get("target_user_id")?,
+            target_username: row.try_get("target_username")?,
+            target_post_id: row.try_get("target_post_id")?,
+            reason: row.try_get("reason")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
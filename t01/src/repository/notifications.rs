@@ -0,0 +1,233 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use deadpool_postgres::GenericClient;
+use log::info;
+use serde::Serialize;
+use tokio_postgres::Row;
+use utoipa::ToSchema;
+
+use super::PostgresRepository;
+use crate::model::NotificationKind;
+
+#[async_trait]
+pub(crate) trait NotificationRepository: Send + Sync {
+    /// Newest notifications of the user, older than `before` if it is set.
+    async fn get_notifications(
+        &self,
+        user_id: i32,
+        before: Option<i32>,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<NotificationsPage>;
+
+    /// Marks the listed notifications of the user as read, or all of them if `notification_ids`
+    /// is `None`. Returns how many notifications were marked.
+    async fn mark_notifications_read(
+        &self,
+        user_id: i32,
+        notification_ids: Option<&[i32]>,
+        now: chrono::NaiveDateTime,
+    ) -> Result<u64>;
+}
+
+#[async_trait]
+impl NotificationRepository for PostgresRepository {
+    async fn get_notifications(
+        &self,
+        user_id: i32,
+        before: Option<i32>,
+        unread_only: bool,
+        limit: i64,
+    ) -> Result<NotificationsPage> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for listing notifications of user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        // one extra row tells whether there is a next page
+        let fetch_limit = limit + 1;
+        let query = "
+            select
+                n.notification_id,
+                n.kind,
+                n.actor_id,
+                u.username as actor_username,
+                n.post_id,
+                p.title as post_title,
+                n.comment_id,
+                n.read_at is not null as is_read,
+                n.created_at
+            from notifications n
+            join users u on n.actor_id = u.user_id
+            left join posts p on n.post_id = p.post_id
+            where n.user_id = $1
+                and ($2::int is null or n.notification_id < $2)
+                and (not $3 or n.read_at is null)
+            order by n.notification_id desc
+            limit $4;
+        ";
+        let rows = transaction
+            .query(query, &[&user_id, &before, &unread_only, &fetch_limit])
+            .await?;
+
+        let mut notifications = rows
+            .into_iter()
+            .map(DatabaseNotification::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        let limit = usize::try_from(limit)?;
+        let next_before = if notifications.len() > limit {
+            notifications.truncate(limit);
+            notifications
+                .last()
+                .map(|notification| notification.notification_id)
+        } else {
+            None
+        };
+
+        let unread_count = count_unread(&transaction, user_id).await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for listing notifications of user (id = {user_id}) successfully ended");
+
+        Ok(NotificationsPage {
+            notifications,
+            unread_count,
+            next_before,
+        })
+    }
+
+    async fn mark_notifications_read(
+        &self,
+        user_id: i32,
+        notification_ids: Option<&[i32]>,
+        now: chrono::NaiveDateTime,
+    ) -> Result<u64> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for reading notifications of user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            update notifications
+            set read_at = $3
+            where user_id = $1
+                and read_at is null
+                and ($2::int[] is null or notification_id = any($2));
+        ";
+        let marked = transaction
+            .execute(query, &[&user_id, &notification_ids, &now])
+            .await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for reading notifications of user (id = {user_id}) successfully ended");
+
+        Ok(marked)
+    }
+}
+
+async fn count_unread(client: &impl GenericClient, user_id: i32) -> Result<i64> {
+    let query = "
+        select count(*)
+        from notifications
+        where user_id = $1 and read_at is null;
+    ";
+    let row = client.query_one(query, &[&user_id]).await?;
+
+    Ok(row.try_get(0)?)
+}
+
+/// Notifies `user_id` about an action of `actor_id`. Must be called in the transaction of
+/// the action itself, so that a notification exists exactly when the action does.
+/// Users are never notified about their own actions.
+pub(super) async fn notify(
+    client: &impl GenericClient,
+    user_id: i32,
+    actor_id: i32,
+    kind: NotificationKind,
+    post_id: Option<i32>,
+    comment_id: Option<i32>,
+) -> Result<()> {
+    if user_id == actor_id {
+        return Ok(());
+    }
+
+    let query = "
+        insert into notifications (user_id, actor_id, kind, post_id, comment_id)
+        values ($1, $2, $3, $4, $5);
+    ";
+    client
+        .execute(
+            query,
+            &[&user_id, &actor_id, &kind.as_str(), &post_id, &comment_id],
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Withdraws unread notifications about an action that was undone, like an unlike or
+/// an unfollow. Already read ones are kept as history.
+pub(super) async fn withdraw(
+    client: &impl GenericClient,
+    user_id: i32,
+    actor_id: i32,
+    kind: NotificationKind,
+    post_id: Option<i32>,
+) -> Result<()> {
+    let query = "
+        delete from notifications
+        where user_id = $1
+            and actor_id = $2
+            and kind = $3
+            and post_id is not distinct from $4
+            and read_at is null;
+    ";
+    client
+        .execute(query, &[&user_id, &actor_id, &kind.as_str(), &post_id])
+        .await?;
+
+    Ok(())
+}
+
+pub(crate) struct NotificationsPage {
+    pub(crate) notifications: Vec<DatabaseNotification>,
+    pub(crate) unread_count: i64,
+    /// Id to pass as `before` to get the next page.
+    pub(crate) next_before: Option<i32>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct DatabaseNotification {
+    pub(crate) notification_id: i32,
+    pub(crate) kind: NotificationKind,
+    pub(crate) actor_id: i32,
+    pub(crate) actor_username: String,
+    pub(crate) post_id: Option<i32>,
+    pub(crate) post_title: Option<String>,
+    pub(crate) comment_id: Option<i32>,
+    pub(crate) is_read: bool,
+    pub(crate) created_at: chrono::NaiveDateTime,
+}
+
+impl TryFrom<Row> for DatabaseNotification {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self> {
+        Ok(Self {
+            notification_id: row.try_get("notification_id")?,
+            kind: row.try_get::<_, &str>("kind")?.parse()?,
+            actor_id: row.try_get("actor_id")?,
+            actor_username: row.try_get("actor_username")?,
+            post_id: row.try_get("post_id")?,
+            post_title: row.try_get("post_title")?,
+            comment_id: row.try_get("comment_id")?,
+            is_read: row.try_get("is_read")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
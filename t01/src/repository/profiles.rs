@@ -0,0 +1,286 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use log::info;
+use serde::Serialize;
+use tokio_postgres::Row;
+use utoipa::ToSchema;
+
+use super::PostgresRepository;
+use crate::{events::FeedEvent, model::Role, storage::StoredImage, utils::PasswordHash};
+
+#[async_trait]
+pub(crate) trait ProfileRepository: Send + Sync {
+    async fn get_profile(&self, user_id: i32) -> Result<Option<UserProfile>>;
+
+    /// Updates the fields which are `Some`, empty strings clear them.
+    /// Returns `false` if the user does not exist.
+    async fn update_profile(
+        &self,
+        user_id: i32,
+        display_name: Option<&str>,
+        bio: Option<&str>,
+    ) -> Result<bool>;
+
+    /// Replaces the avatar, or removes it if `avatar` is `None`. Returns files of the previous
+    /// avatar to remove, or `None` if the user does not exist.
+    async fn set_avatar(
+        &self,
+        user_id: i32,
+        avatar: Option<&StoredImage>,
+    ) -> Result<Option<Vec<String>>>;
+
+    async fn get_password_hash(&self, user_id: i32) -> Result<Option<PasswordHash>>;
+
+    /// Sets the new password and revokes all refresh tokens of the user,
+    /// so that every session has to log in with it.
+    async fn change_password(
+        &self,
+        user_id: i32,
+        password_hash: PasswordHash,
+        now: NaiveDateTime,
+    ) -> Result<()>;
+
+    /// Deletes the user with everything they wrote: posts, comments, likes, follows,
+    /// tokens and notifications are removed by `on delete cascade`, the audit trail keeps
+    /// the actions with the user set to `null`. Access tokens stop working at once, as
+    /// their owner no longer exists. Returns media files to remove, or `None` if the user
+    /// does not exist.
+    async fn delete_user(&self, user_id: i32) -> Result<Option<Vec<String>>>;
+}
+
+#[async_trait]
+impl ProfileRepository for PostgresRepository {
+    async fn get_profile(&self, user_id: i32) -> Result<Option<UserProfile>> {
+        let connection = self.pool.get().await?;
+
+        let query = "
+            select user_id, username, display_name, bio, avatar_file_name, avatar_thumbnail_name, role, created_at
+            from users
+            where user_id = $1;
+        ";
+        let row = connection.query_opt(query, &[&user_id]).await?;
+
+        row.map(UserProfile::try_from).transpose()
+    }
+
+    async fn update_profile(
+        &self,
+        user_id: i32,
+        display_name: Option<&str>,
+        bio: Option<&str>,
+    ) -> Result<bool> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for updating profile of user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            update users
+            set
+                display_name = case when $2 then nullif($3, '') else display_name end,
+                bio = case when $4 then nullif($5, '') else bio end
+            where user_id = $1;
+        ";
+        let updated = transaction
+            .execute(
+                query,
+                &[
+                    &user_id,
+                    &display_name.is_some(),
+                    &display_name,
+                    &bio.is_some(),
+                    &bio,
+                ],
+            )
+            .await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for updating profile of user (id = {user_id}) successfully ended");
+
+        Ok(updated > 0)
+    }
+
+    async fn set_avatar(
+        &self,
+        user_id: i32,
+        avatar: Option<&StoredImage>,
+    ) -> Result<Option<Vec<String>>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for setting avatar of user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select avatar_file_name, avatar_thumbnail_name
+            from users
+            where user_id = $1
+            for update;
+        ";
+        let Some(row) = transaction.query_opt(query, &[&user_id]).await? else {
+            return Ok(None);
+        };
+        let previous = avatar_files(&row)?;
+
+        let query = "
+            update users
+            set avatar_file_name = $2, avatar_thumbnail_name = $3
+            where user_id = $1;
+        ";
+        transaction
+            .execute(
+                query,
+                &[
+                    &user_id,
+                    &avatar.map(|avatar| &avatar.file_name),
+                    &avatar.map(|avatar| &avatar.thumbnail_name),
+                ],
+            )
+            .await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for setting avatar of user (id = {user_id}) successfully ended");
+
+        Ok(Some(previous))
+    }
+
+    async fn get_password_hash(&self, user_id: i32) -> Result<Option<PasswordHash>> {
+        let connection = self.pool.get().await?;
+
+        let query = "
+            select password_hash
+            from users
+            where user_id = $1;
+        ";
+        let row = connection.query_opt(query, &[&user_id]).await?;
+
+        row.as_ref().map(PasswordHash::try_from).transpose()
+    }
+
+    async fn change_password(
+        &self,
+        user_id: i32,
+        password_hash: PasswordHash,
+        now: NaiveDateTime,
+    ) -> Result<()> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for changing password of user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            update users
+            set password_hash = $2
+            where user_id = $1;
+        ";
+        transaction
+            .execute(query, &[&user_id, &password_hash.as_str()])
+            .await?;
+
+        let query = "
+            update refresh_tokens
+            set revoked_at = $2
+            where user_id = $1 and revoked_at is null;
+        ";
+        transaction.execute(query, &[&user_id, &now]).await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for changing password of user (id = {user_id}) successfully ended");
+
+        Ok(())
+    }
+
+    async fn delete_user(&self, user_id: i32) -> Result<Option<Vec<String>>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for deleting user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select avatar_file_name, avatar_thumbnail_name
+            from users
+            where user_id = $1
+            for update;
+        ";
+        let Some(row) = transaction.query_opt(query, &[&user_id]).await? else {
+            return Ok(None);
+        };
+        let mut files = avatar_files(&row)?;
+
+        let query = "
+            select file_name, thumbnail_name
+            from attachments
+            where user_id = $1;
+        ";
+        for row in transaction.query(query, &[&user_id]).await? {
+            files.push(row.try_get("file_name")?);
+            files.push(row.try_get("thumbnail_name")?);
+        }
+
+        let query = "
+            select post_id
+            from posts
+            where user_id = $1;
+        ";
+        for row in transaction.query(query, &[&user_id]).await? {
+            let post_id: i32 = row.try_get("post_id")?;
+            super::publish(&transaction, &FeedEvent::PostDeleted { post_id }).await?;
+        }
+
+        let query = "
+            delete from users
+            where user_id = $1;
+        ";
+        transaction.execute(query, &[&user_id]).await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for deleting user (id = {user_id}) successfully ended");
+
+        Ok(Some(files))
+    }
+}
+
+fn avatar_files(row: &Row) -> Result<Vec<String>> {
+    let file_name: Option<String> = row.try_get("avatar_file_name")?;
+    let thumbnail_name: Option<String> = row.try_get("avatar_thumbnail_name")?;
+
+    Ok(file_name.into_iter().chain(thumbnail_name).collect())
+}
+
+/// Public profile of a user, avatar files are served from `/media/`.
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct UserProfile {
+    pub(crate) user_id: i32,
+    pub(crate) username: String,
+    pub(crate) display_name: Option<String>,
+    pub(crate) bio: Option<String>,
+    pub(crate) avatar_file_name: Option<String>,
+    pub(crate) avatar_thumbnail_name: Option<String>,
+    pub(crate) role: Role,
+    pub(crate) created_at: NaiveDateTime,
+}
+
+impl TryFrom<Row> for UserProfile {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self> {
+        Ok(Self {
+            user_id: row.try_get("user_id")?,
+            username: row.try_get("username")?,
+            display_name: row.try_get("display_name")?,
+            bio: row.try_get("bio")?,
+            avatar_file_name: row.try_get("avatar_file_name")?,
+            avatar_thumbnail_name: row.try_get("avatar_thumbnail_name")?,
+            role: row.try_get::<_, &str>("role")?.parse()?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
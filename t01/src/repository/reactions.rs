@@ -0,0 +1,212 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use deadpool_postgres::GenericClient;
+use log::info;
+use std::collections::{BTreeMap, HashMap};
+
+use super::{notifications, publish_likes_changed, DatabasePost, PostgresRepository};
+use crate::model::{NotificationKind, ReactionKind};
+
+/// Number of reactions of every kind, zero counts included.
+pub(crate) type ReactionCounts = BTreeMap<ReactionKind, i64>;
+
+#[async_trait]
+pub(crate) trait ReactionRepository: Send + Sync {
+    /// Adds the reaction of the user, reacting twice with the same kind changes nothing.
+    /// `None` if the post does not exist.
+    async fn add_reaction(
+        &self,
+        user_id: i32,
+        post_id: i32,
+        kind: ReactionKind,
+    ) -> Result<Option<ReactionCounts>>;
+
+    /// `None` if the post does not exist.
+    async fn remove_reaction(
+        &self,
+        user_id: i32,
+        post_id: i32,
+        kind: ReactionKind,
+    ) -> Result<Option<ReactionCounts>>;
+}
+
+#[async_trait]
+impl ReactionRepository for PostgresRepository {
+    async fn add_reaction(
+        &self,
+        user_id: i32,
+        post_id: i32,
+        kind: ReactionKind,
+    ) -> Result<Option<ReactionCounts>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for adding reaction '{}' to post (id = {post_id}) by user (id = {user_id}) started", kind.as_str());
+
+        let transaction = connection.transaction().await?;
+
+        let Some(author_id) = post_author(&transaction, post_id).await? else {
+            return Ok(None);
+        };
+
+        let query = "
+            insert into reactions (user_id, post_id, kind)
+            values ($1, $2, $3)
+            on conflict (user_id, post_id, kind) do nothing
+            returning reaction_id;
+        ";
+        let added = transaction
+            .query_opt(query, &[&user_id, &post_id, &kind.as_str()])
+            .await?
+            .is_some();
+
+        // only likes are worth a notification
+        if added && kind == ReactionKind::Like {
+            notifications::notify(
+                &transaction,
+                author_id,
+                user_id,
+                NotificationKind::Like,
+                Some(post_id),
+                None,
+            )
+            .await?;
+            publish_likes_changed(&transaction, post_id).await?;
+        }
+
+        let counts = count_reactions(&transaction, post_id).await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for adding reaction '{}' to post (id = {post_id}) by user (id = {user_id}) successfully ended", kind.as_str());
+
+        Ok(Some(counts))
+    }
+
+    async fn remove_reaction(
+        &self,
+        user_id: i32,
+        post_id: i32,
+        kind: ReactionKind,
+    ) -> Result<Option<ReactionCounts>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for removing reaction '{}' from post (id = {post_id}) by user (id = {user_id}) started", kind.as_str());
+
+        let transaction = connection.transaction().await?;
+
+        let Some(author_id) = post_author(&transaction, post_id).await? else {
+            return Ok(None);
+        };
+
+        let query = "
+            delete from reactions
+            where user_id = $1 and post_id = $2 and kind = $3
+            returning reaction_id;
+        ";
+        let removed = transaction
+            .query_opt(query, &[&user_id, &post_id, &kind.as_str()])
+            .await?
+            .is_some();
+
+        if removed && kind == ReactionKind::Like {
+            notifications::withdraw(
+                &transaction,
+                author_id,
+                user_id,
+                NotificationKind::Like,
+                Some(post_id),
+            )
+            .await?;
+            publish_likes_changed(&transaction, post_id).await?;
+        }
+
+        let counts = count_reactions(&transaction, post_id).await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for removing reaction '{}' from post (id = {post_id}) by user (id = {user_id}) successfully ended", kind.as_str());
+
+        Ok(Some(counts))
+    }
+}
+
+/// Author of the post, `None` if it does not exist, is not published, is in the trash or
+/// is a repost.
+async fn post_author(client: &impl GenericClient, post_id: i32) -> Result<Option<i32>> {
+    let query = "
+        select user_id
+        from posts
+        where post_id = $1 and deleted_at is null and status = 'published' and repost_of_id is null;
+    ";
+    client
+        .query_opt(query, &[&post_id])
+        .await?
+        .map(|row| row.try_get("user_id"))
+        .transpose()
+        .map_err(Into::into)
+}
+
+async fn count_reactions(client: &impl GenericClient, post_id: i32) -> Result<ReactionCounts> {
+    let mut post_counts = query_counts(client, &[post_id]).await?;
+
+    Ok(post_counts.remove(&post_id).unwrap_or_else(empty_counts))
+}
+
+/// Fills `reactions` of the posts with a single query.
+pub(super) async fn load_reactions(
+    client: &impl GenericClient,
+    posts: &mut [DatabasePost],
+) -> Result<()> {
+    if posts.is_empty() {
+        return Ok(());
+    }
+
+    let post_ids = posts.iter().map(|post| post.post_id).collect::<Vec<_>>();
+    let mut counts = query_counts(client, &post_ids).await?;
+
+    for post in posts {
+        post.reactions = counts.remove(&post.post_id).unwrap_or_else(empty_counts);
+    }
+
+    Ok(())
+}
+
+async fn query_counts(
+    client: &impl GenericClient,
+    post_ids: &[i32],
+) -> Result<HashMap<i32, ReactionCounts>> {
+    let query = "
+        select post_id, kind, count(*) as count
+        from reactions
+        where post_id = any($1)
+        group by post_id, kind;
+    ";
+    let rows = client.query(query, &[&post_ids]).await?;
+
+    let mut counts: HashMap<i32, ReactionCounts> = HashMap::new();
+    for row in rows {
+        let post_id: i32 = row.try_get("post_id")?;
+        let kind: String = row.try_get("kind")?;
+        // reactions taken out of the set are kept in the table, but not shown
+        let Some(kind) = kind
+            .parse::<ReactionKind>()
+            .ok()
+            .filter(|kind| ReactionKind::enabled().contains(kind))
+        else {
+            continue;
+        };
+        counts
+            .entry(post_id)
+            .or_insert_with(empty_counts)
+            .insert(kind, row.try_get("count")?);
+    }
+
+    Ok(counts)
+}
+
+pub(super) fn empty_counts() -> ReactionCounts {
+    ReactionKind::enabled()
+        .iter()
+        .map(|&kind| (kind, 0))
+        .collect()
+}
@@ -0,0 +1,250 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use deadpool_postgres::GenericClient;
+use log::info;
+
+use super::{publish, PostgresRepository, Publication};
+use crate::events::FeedEvent;
+
+#[async_trait]
+pub(crate) trait RepostRepository: Send + Sync {
+    /// Reposts the post, reposting twice changes nothing. A repost of a repost shares the
+    /// original post. `None` if the post does not exist.
+    async fn repost(&self, user_id: i32, post_id: i32) -> Result<Option<RepostState>>;
+
+    /// Deletes the repost of the user for good. `None` if the post does not exist.
+    async fn remove_repost(&self, user_id: i32, post_id: i32) -> Result<Option<RepostState>>;
+
+    /// Creates a post embedding the quoted one, a quote of a repost embeds the original
+    /// post. `None` if the quoted post does not exist.
+    async fn create_quote(
+        &self,
+        user_id: i32,
+        quote_of_id: i32,
+        title: &str,
+        content: &str,
+        publication: Publication,
+    ) -> Result<Option<i32>>;
+}
+
+#[async_trait]
+impl RepostRepository for PostgresRepository {
+    async fn repost(&self, user_id: i32, post_id: i32) -> Result<Option<RepostState>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for reposting post (id = {post_id}) by user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let Some(original_id) = original_post(&transaction, post_id).await? else {
+            return Ok(None);
+        };
+
+        // a repost in the trash is brought back rather than duplicated
+        let query = "
+            select post_id, deleted_at is not null as is_deleted
+            from posts
+            where user_id = $1 and repost_of_id = $2
+            for update;
+        ";
+        let repost_id = match transaction
+            .query_opt(query, &[&user_id, &original_id])
+            .await?
+        {
+            Some(row) => {
+                let repost_id: i32 = row.try_get("post_id")?;
+                if row.try_get("is_deleted")? {
+                    let query = "
+                        update posts
+                        set deleted_at = null
+                        where post_id = $1;
+                    ";
+                    transaction.execute(query, &[&repost_id]).await?;
+                    publish(
+                        &transaction,
+                        &FeedEvent::PostCreated {
+                            post_id: repost_id,
+                            user_id,
+                        },
+                    )
+                    .await?;
+                }
+                repost_id
+            }
+            None => {
+                let query = "
+                    insert into posts (user_id, title, content, repost_of_id)
+                    values ($1, '', '', $2)
+                    returning post_id;
+                ";
+                let repost_id: i32 = transaction
+                    .query_one(query, &[&user_id, &original_id])
+                    .await?
+                    .try_get(0)?;
+                publish(
+                    &transaction,
+                    &FeedEvent::PostCreated {
+                        post_id: repost_id,
+                        user_id,
+                    },
+                )
+                .await?;
+                repost_id
+            }
+        };
+
+        let reposts_count = count_reposts(&transaction, original_id).await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for reposting post (id = {post_id}) by user (id = {user_id}) successfully ended");
+
+        Ok(Some(RepostState {
+            repost_id: Some(repost_id),
+            reposts_count,
+        }))
+    }
+
+    async fn remove_repost(&self, user_id: i32, post_id: i32) -> Result<Option<RepostState>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for removing repost of post (id = {post_id}) by user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let Some(original_id) = original_post(&transaction, post_id).await? else {
+            return Ok(None);
+        };
+
+        let query = "
+            delete from posts
+            where user_id = $1 and repost_of_id = $2
+            returning post_id;
+        ";
+        if let Some(row) = transaction
+            .query_opt(query, &[&user_id, &original_id])
+            .await?
+        {
+            let repost_id: i32 = row.try_get("post_id")?;
+            publish(&transaction, &FeedEvent::PostDeleted { post_id: repost_id }).await?;
+        }
+
+        let reposts_count = count_reposts(&transaction, original_id).await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for removing repost of post (id = {post_id}) by user (id = {user_id}) successfully ended");
+
+        Ok(Some(RepostState {
+            repost_id: None,
+            reposts_count,
+        }))
+    }
+
+    async fn create_quote(
+        &self,
+        user_id: i32,
+        quote_of_id: i32,
+        title: &str,
+        content: &str,
+        publication: Publication,
+    ) -> Result<Option<i32>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for quoting post (id = {quote_of_id}) by user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let Some(original_id) = original_post(&transaction, quote_of_id).await? else {
+            return Ok(None);
+        };
+
+        let query = "
+            insert into posts (user_id, title, content, quote_of_id, status, publish_at)
+            values ($1, $2, $3, $4, $5, $6)
+            returning post_id;
+        ";
+        let post_id: i32 = transaction
+            .query_one(
+                query,
+                &[
+                    &user_id,
+                    &title,
+                    &content,
+                    &original_id,
+                    &publication.status().as_str(),
+                    &publication.publish_at(),
+                ],
+            )
+            .await?
+            .try_get(0)?;
+
+        if publication == Publication::Now {
+            publish(&transaction, &FeedEvent::PostCreated { post_id, user_id }).await?;
+        }
+
+        transaction.commit().await?;
+
+        info!("Transaction for quoting post (id = {quote_of_id}) by user (id = {user_id}) successfully ended");
+
+        Ok(Some(post_id))
+    }
+}
+
+/// Id of the post that is shared when `post_id` is shared: the reposted post for reposts,
+/// the post itself otherwise. `None` if the post does not exist, is not published or is in
+/// the trash. The post is locked, so it cannot be moved to the trash meanwhile.
+pub(super) async fn original_post(
+    client: &impl GenericClient,
+    post_id: i32,
+) -> Result<Option<i32>> {
+    let query = "
+        select coalesce(o.post_id, p.post_id) as original_id
+        from posts p
+        left join posts o on o.post_id = p.repost_of_id
+        where p.post_id = $1 and p.deleted_at is null and p.status = 'published' and o.deleted_at is null
+        for update of p;
+    ";
+    client
+        .query_opt(query, &[&post_id])
+        .await?
+        .map(|row| row.try_get("original_id"))
+        .transpose()
+        .map_err(Into::into)
+}
+
+async fn count_reposts(client: &impl GenericClient, post_id: i32) -> Result<i64> {
+    let query = "
+        select count(*)
+        from posts
+        where repost_of_id = $1 and deleted_at is null;
+    ";
+    Ok(client.query_one(query, &[&post_id]).await?.try_get(0)?)
+}
+
+/// Tells the clients that the reposts of the post are gone along with it.
+pub(super) async fn publish_reposts_deleted(
+    client: &impl GenericClient,
+    post_id: i32,
+) -> Result<()> {
+    let query = "
+        select post_id
+        from posts
+        where repost_of_id = $1 and deleted_at is null;
+    ";
+    for row in client.query(query, &[&post_id]).await? {
+        let repost_id: i32 = row.try_get("post_id")?;
+        publish(client, &FeedEvent::PostDeleted { post_id: repost_id }).await?;
+    }
+
+    Ok(())
+}
+
+/// Repost of the current user after a change.
+#[derive(Debug)]
+pub(crate) struct RepostState {
+    /// Id of the repost, `None` once it is removed.
+    pub(crate) repost_id: Option<i32>,
+    /// Number of reposts of the original post.
+    pub(crate) reposts_count: i64,
+}
@@ -0,0 +1,146 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use log::info;
+use serde::Serialize;
+use tokio_postgres::Row;
+use utoipa::ToSchema;
+
+use super::PostgresRepository;
+
+#[async_trait]
+pub(crate) trait RevisionRepository: Send + Sync {
+    /// Updates title and/or content of the post, keeping the previous version in
+    /// `post_revisions`.
+    async fn edit_post(
+        &self,
+        post_id: i32,
+        user_id: i32,
+        title: Option<&str>,
+        content: Option<&str>,
+    ) -> Result<PostEditResult>;
+
+    /// Previous versions of the post, the most recent first.
+    async fn get_post_revisions(&self, post_id: i32) -> Result<Vec<DatabaseRevision>>;
+}
+
+#[async_trait]
+impl RevisionRepository for PostgresRepository {
+    async fn edit_post(
+        &self,
+        post_id: i32,
+        user_id: i32,
+        title: Option<&str>,
+        content: Option<&str>,
+    ) -> Result<PostEditResult> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for editing post (id = {post_id}) by user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select user_id, title, content
+            from posts
+            where post_id = $1 and deleted_at is null and repost_of_id is null
+            for update;
+        ";
+        let Some(row) = transaction.query_opt(query, &[&post_id]).await? else {
+            return Ok(PostEditResult::NotFound);
+        };
+
+        let post_user_id: i32 = row.try_get("user_id")?;
+        if post_user_id != user_id {
+            return Ok(PostEditResult::NotOwned);
+        }
+
+        let old_title: String = row.try_get("title")?;
+        let old_content: String = row.try_get("content")?;
+
+        let new_title = title.unwrap_or(&old_title);
+        let new_content = content.unwrap_or(&old_content);
+
+        if new_title == old_title && new_content == old_content {
+            return Ok(PostEditResult::Unchanged);
+        }
+
+        let query = "
+            insert into post_revisions (post_id, title, content)
+            values ($1, $2, $3);
+        ";
+        transaction
+            .execute(query, &[&post_id, &old_title, &old_content])
+            .await?;
+
+        let query = "
+            update posts
+            set title = $2, content = $3, edited_at = current_timestamp
+            where post_id = $1;
+        ";
+        transaction
+            .execute(query, &[&post_id, &new_title, &new_content])
+            .await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for editing post (id = {post_id}) by user (id = {user_id}) successfully ended");
+
+        Ok(PostEditResult::Edited)
+    }
+
+    async fn get_post_revisions(&self, post_id: i32) -> Result<Vec<DatabaseRevision>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for listing revisions of post (id = {post_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select revision_id, post_id, title, content, created_at
+            from post_revisions
+            where post_id = $1
+            order by revision_id desc;
+        ";
+        let rows = transaction.query(query, &[&post_id]).await?;
+
+        let revisions = rows
+            .into_iter()
+            .map(DatabaseRevision::try_from)
+            .collect::<Result<Vec<_>>>()?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for listing revisions of post (id = {post_id}) successfully ended");
+
+        Ok(revisions)
+    }
+}
+
+pub(crate) enum PostEditResult {
+    Edited,
+    Unchanged,
+    NotFound,
+    NotOwned,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct DatabaseRevision {
+    pub(crate) revision_id: i32,
+    pub(crate) post_id: i32,
+    pub(crate) title: String,
+    pub(crate) content: String,
+    pub(crate) created_at: chrono::NaiveDateTime,
+}
+
+impl TryFrom<Row> for DatabaseRevision {
+    type Error = anyhow::Error;
+
+    fn try_from(row: Row) -> Result<Self> {
+        Ok(Self {
+            revision_id: row.try_get("revision_id")?,
+            post_id: row.try_get("post_id")?,
+            title: row.try_get("title")?,
+            content: row.try_get("content")?,
+            created_at: row.try_get("created_at")?,
+        })
+    }
+}
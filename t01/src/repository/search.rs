@@ -0,0 +1,163 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use log::info;
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+
+use super::{DatabasePost, PostgresRepository, POST_SELECT};
+use crate::utils::escape_html;
+
+/// Delimiters of matched words in `ts_headline` output. Private use characters can not
+/// clash with markup, so the snippet is escaped first and the delimiters are turned
+/// into `<mark>` afterwards.
+pub(super) const MATCH_START: char = '\u{E000}';
+pub(super) const MATCH_END: char = '\u{E001}';
+
+#[async_trait]
+pub(crate) trait SearchRepository: Send + Sync {
+    /// Posts matching the `websearch_to_tsquery` syntax query, the most relevant first.
+    async fn search_posts(&self, query: &str, limit: i64) -> Result<Vec<SearchedPost>>;
+
+    /// Users whose username starts with `prefix`, case insensitive.
+    async fn search_users(&self, prefix: &str, limit: i64) -> Result<Vec<SearchedUser>>;
+}
+
+#[async_trait]
+impl SearchRepository for PostgresRepository {
+    async fn search_posts(&self, query: &str, limit: i64) -> Result<Vec<SearchedPost>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for searching posts started");
+
+        let transaction = connection.transaction().await?;
+
+        let headline_options =
+            format!("StartSel={MATCH_START}, StopSel={MATCH_END}, MaxWords=35, MinWords=15");
+        let search_query = "
+            select
+                s.post_id,
+                s.rank,
+                ts_headline('russian', s.title, s.query, $3) as title_snippet,
+                ts_headline('russian', s.content, s.query, $3) as content_snippet
+            from (
+                select p.post_id, p.title, p.content, ts_rank_cd(p.search, q) as rank, q as query
+                from posts p, websearch_to_tsquery('russian', $1) q
+                where p.search @@ q and p.deleted_at is null and p.status = 'published'
+                order by rank desc, p.post_id desc
+                limit $2
+            ) s
+            order by s.rank desc, s.post_id desc;
+        ";
+        let rows = transaction
+            .query(search_query, &[&query, &limit, &headline_options])
+            .await?;
+
+        let mut matches = Vec::with_capacity(rows.len());
+        for row in rows {
+            let post_id: i32 = row.try_get("post_id")?;
+            let rank: f32 = row.try_get("rank")?;
+            let title_snippet: String = row.try_get("title_snippet")?;
+            let content_snippet: String = row.try_get("content_snippet")?;
+            matches.push((post_id, rank, title_snippet, content_snippet));
+        }
+
+        let post_ids = matches
+            .iter()
+            .map(|(post_id, ..)| *post_id)
+            .collect::<Vec<_>>();
+        let posts_query = format!("{POST_SELECT} where p.post_id = any($1);");
+        let mut posts = transaction
+            .query(&posts_query, &[&post_ids])
+            .await?
+            .into_iter()
+            .map(DatabasePost::try_from)
+            .collect::<Result<Vec<_>>>()?;
+        super::load_details(&transaction, &mut posts).await?;
+        let mut posts = posts
+            .into_iter()
+            .map(|post| (post.post_id, post))
+            .collect::<HashMap<_, _>>();
+
+        transaction.commit().await?;
+
+        let posts = matches
+            .into_iter()
+            .filter_map(|(post_id, rank, title_snippet, content_snippet)| {
+                Some(SearchedPost {
+                    post: posts.remove(&post_id)?,
+                    rank,
+                    title_snippet: highlight(&title_snippet),
+                    content_snippet: highlight(&content_snippet),
+                })
+            })
+            .collect();
+
+        info!("Transaction for searching posts successfully ended");
+
+        Ok(posts)
+    }
+
+    async fn search_users(&self, prefix: &str, limit: i64) -> Result<Vec<SearchedUser>> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for searching users started");
+
+        let transaction = connection.transaction().await?;
+
+        let pattern = format!("{}%", escape_like(&prefix.to_lowercase()));
+        let query = "
+            select user_id, username
+            from users
+            where lower(username) like $1
+            order by username
+            limit $2;
+        ";
+        let rows = transaction.query(query, &[&pattern, &limit]).await?;
+
+        let users = rows
+            .into_iter()
+            .map(|row| {
+                Ok(SearchedUser {
+                    user_id: row.try_get("user_id")?,
+                    username: row.try_get("username")?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for searching users successfully ended");
+
+        Ok(users)
+    }
+}
+
+fn escape_like(pattern: &str) -> String {
+    pattern
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+pub(super) fn highlight(snippet: &str) -> String {
+    escape_html(snippet)
+        .replace(MATCH_START, "<mark>")
+        .replace(MATCH_END, "</mark>")
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct SearchedPost {
+    #[serde(flatten)]
+    pub(crate) post: DatabasePost,
+    pub(crate) rank: f32,
+    /// HTML with matched words wrapped into `<mark>`, everything else escaped.
+    pub(crate) title_snippet: String,
+    pub(crate) content_snippet: String,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub(crate) struct SearchedUser {
+    pub(crate) user_id: i32,
+    pub(crate) username: String,
+}
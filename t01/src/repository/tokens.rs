@@ -0,0 +1,244 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::NaiveDateTime;
+use log::{info, warn};
+
+use super::{
+    moderation::{UserBlock, UserStatus},
+    PostgresRepository,
+};
+use crate::model::Role;
+
+#[async_trait]
+pub(crate) trait TokenRepository: Send + Sync {
+    async fn store_refresh_token(
+        &self,
+        user_id: i32,
+        token_hash: &str,
+        expires_at: NaiveDateTime,
+    ) -> Result<()>;
+
+    /// Revokes the presented refresh token and stores its replacement in one transaction.
+    ///
+    /// Presenting an already revoked token means it was copied, so every refresh token
+    /// of its owner is revoked as well.
+    async fn rotate_refresh_token(
+        &self,
+        token_hash: &str,
+        new_token_hash: &str,
+        new_expires_at: NaiveDateTime,
+        now: NaiveDateTime,
+    ) -> Result<RefreshTokenRotation>;
+
+    /// Revokes the access token with the given `jti` and, if present, the refresh token
+    /// of the same user.
+    async fn revoke_session(
+        &self,
+        user_id: i32,
+        jti: &str,
+        access_expires_at: NaiveDateTime,
+        refresh_token_hash: Option<&str>,
+        now: NaiveDateTime,
+    ) -> Result<()>;
+
+    /// Checks that the access token was not revoked and its owner is still allowed in.
+    async fn check_access(
+        &self,
+        user_id: i32,
+        jti: &str,
+        now: NaiveDateTime,
+    ) -> Result<AccessCheck>;
+}
+
+#[async_trait]
+impl TokenRepository for PostgresRepository {
+    async fn store_refresh_token(
+        &self,
+        user_id: i32,
+        token_hash: &str,
+        expires_at: NaiveDateTime,
+    ) -> Result<()> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for storing refresh token of user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            insert into refresh_tokens (user_id, token_hash, expires_at)
+            values ($1, $2, $3);
+        ";
+        transaction
+            .execute(query, &[&user_id, &token_hash, &expires_at])
+            .await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for storing refresh token of user (id = {user_id}) successfully ended");
+
+        Ok(())
+    }
+
+    async fn rotate_refresh_token(
+        &self,
+        token_hash: &str,
+        new_token_hash: &str,
+        new_expires_at: NaiveDateTime,
+        now: NaiveDateTime,
+    ) -> Result<RefreshTokenRotation> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for rotating refresh token started");
+
+        let transaction = connection.transaction().await?;
+
+        let query = "
+            select user_id, expires_at, revoked_at
+            from refresh_tokens
+            where token_hash = $1
+            for update;
+        ";
+        let Some(row) = transaction.query_opt(query, &[&token_hash]).await? else {
+            return Ok(RefreshTokenRotation::NotFound);
+        };
+
+        let user_id: i32 = row.try_get("user_id")?;
+        let expires_at: NaiveDateTime = row.try_get("expires_at")?;
+        let revoked_at: Option<NaiveDateTime> = row.try_get("revoked_at")?;
+
+        if revoked_at.is_some() {
+            warn!("Revoked refresh token of user (id = {user_id}) was reused");
+
+            let query = "
+                update refresh_tokens
+                set revoked_at = $2
+                where user_id = $1 and revoked_at is null;
+            ";
+            transaction.execute(query, &[&user_id, &now]).await?;
+            transaction.commit().await?;
+
+            return Ok(RefreshTokenRotation::Reused);
+        }
+
+        if expires_at <= now {
+            return Ok(RefreshTokenRotation::Expired);
+        }
+
+        let query = "
+            update refresh_tokens
+            set revoked_at = $2
+            where token_hash = $1;
+        ";
+        transaction.execute(query, &[&token_hash, &now]).await?;
+
+        let query = "
+            insert into refresh_tokens (user_id, token_hash, expires_at)
+            values ($1, $2, $3);
+        ";
+        transaction
+            .execute(query, &[&user_id, &new_token_hash, &new_expires_at])
+            .await?;
+
+        transaction.commit().await?;
+
+        info!("Transaction for rotating refresh token of user (id = {user_id}) successfully ended");
+
+        Ok(RefreshTokenRotation::Rotated { user_id })
+    }
+
+    async fn revoke_session(
+        &self,
+        user_id: i32,
+        jti: &str,
+        access_expires_at: NaiveDateTime,
+        refresh_token_hash: Option<&str>,
+        now: NaiveDateTime,
+    ) -> Result<()> {
+        let mut connection = self.pool.get().await?;
+
+        info!("Transaction for revoking session of user (id = {user_id}) started");
+
+        let transaction = connection.transaction().await?;
+
+        // tokens which are already expired are rejected by signature validation anyway
+        let query = "
+            delete from revoked_tokens
+            where expires_at <= $1;
+        ";
+        transaction.execute(query, &[&now]).await?;
+
+        let query = "
+            insert into revoked_tokens (jti, expires_at)
+            values ($1, $2)
+            on conflict (jti) do nothing;
+        ";
+        transaction
+            .execute(query, &[&jti, &access_expires_at])
+            .await?;
+
+        if let Some(refresh_token_hash) = refresh_token_hash {
+            let query = "
+                update refresh_tokens
+                set revoked_at = $3
+                where user_id = $1 and token_hash = $2 and revoked_at is null;
+            ";
+            transaction
+                .execute(query, &[&user_id, &refresh_token_hash, &now])
+                .await?;
+        }
+
+        transaction.commit().await?;
+
+        info!("Transaction for revoking session of user (id = {user_id}) successfully ended");
+
+        Ok(())
+    }
+
+    async fn check_access(
+        &self,
+        user_id: i32,
+        jti: &str,
+        now: NaiveDateTime,
+    ) -> Result<AccessCheck> {
+        let connection = self.pool.get().await?;
+
+        let query = "
+            select
+                u.role,
+                u.banned_at,
+                u.suspended_until,
+                exists(select 1 from revoked_tokens where jti = $2) as revoked
+            from users u
+            where u.user_id = $1;
+        ";
+        let Some(row) = connection.query_opt(query, &[&user_id, &jti]).await? else {
+            return Ok(AccessCheck::Revoked);
+        };
+
+        if row.try_get("revoked")? {
+            return Ok(AccessCheck::Revoked);
+        }
+
+        let status = UserStatus::from_row(&row, now)?;
+        match status.block {
+            Some(block) => Ok(AccessCheck::Blocked(block)),
+            None => Ok(AccessCheck::Allowed { role: status.role }),
+        }
+    }
+}
+
+pub(crate) enum AccessCheck {
+    /// `role` is the current role of the user, which may differ from the one in the token.
+    Allowed {
+        role: Role,
+    },
+    Revoked,
+    Blocked(UserBlock),
+}
+
+pub(crate) enum RefreshTokenRotation {
+    Rotated { user_id: i32 },
+    NotFound,
+    Expired,
+    Reused,
+}
@@ -0,0 +1,273 @@
+use anyhow::Result;
+use image::{DynamicImage, ImageFormat, ImageReader, Limits};
+use log::{info, warn};
+use std::{
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use crate::{error::AppError, i18n::Message};
+
+/// Upper bound of a single uploaded image.
+pub(crate) const MAX_IMAGE_SIZE: usize = 5 * 1024 * 1024;
+/// Larger images are rejected before decoding, so that a small file cannot take
+/// gigabytes of memory once unpacked.
+const MAX_IMAGE_DIMENSION: u32 = 8192;
+const THUMBNAIL_SIZE: u32 = 320;
+
+/// Local directory with uploaded images and their thumbnails.
+#[derive(Clone)]
+pub(crate) struct MediaStorage {
+    dir: Arc<PathBuf>,
+}
+
+/// Image written to the storage, ready to be linked to a post.
+#[derive(Debug)]
+pub(crate) struct StoredImage {
+    pub(crate) file_name: String,
+    pub(crate) thumbnail_name: String,
+    pub(crate) content_type: String,
+    pub(crate) size_bytes: i32,
+    pub(crate) width: i32,
+    pub(crate) height: i32,
+}
+
+impl MediaStorage {
+    pub(crate) async fn initialize(dir: PathBuf) -> Result<Self> {
+        tokio::fs::create_dir_all(&dir).await?;
+        info!("Media storage initialized in '{}'", dir.display());
+
+        Ok(Self { dir: Arc::new(dir) })
+    }
+
+    pub(crate) fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Checks that `bytes` is an image of a supported format, judging by its content
+    /// and not by the name or the declared type, and stores it with a thumbnail.
+    pub(crate) async fn store_image(&self, bytes: Vec<u8>) -> Result<StoredImage, AppError> {
+        if bytes.len() > MAX_IMAGE_SIZE {
+            return Err(AppError::payload_too_large(
+                Message::new("image_too_large").arg("mebibytes", MAX_IMAGE_SIZE / 1024 / 1024),
+            ));
+        }
+
+        let format = match image::guess_format(&bytes) {
+            Ok(
+                format @ (ImageFormat::Png
+                | ImageFormat::Jpeg
+                | ImageFormat::Gif
+                | ImageFormat::WebP),
+            ) => format,
+            _ => {
+                warn!("Upload rejected: unsupported image format");
+                return Err(AppError::bad_request("unsupported_image_format"));
+            }
+        };
+
+        let (bytes, decoded) = tokio::task::spawn_blocking(move || {
+            let decoded = decode_with_thumbnail(&bytes, format);
+            (bytes, decoded)
+        })
+        .await?;
+        let DecodedImage {
+            width,
+            height,
+            thumbnail,
+            thumbnail_format,
+        } = decoded?;
+
+        let name = ulid::Ulid::new().to_string().to_lowercase();
+        let extension = format.extensions_str()[0];
+        let stored = StoredImage {
+            file_name: format!("{name}.{extension}"),
+            thumbnail_name: format!("{name}_thumb.{}", thumbnail_format.extensions_str()[0]),
+            content_type: format.to_mime_type().to_owned(),
+            size_bytes: i32::try_from(bytes.len())?,
+            width: i32::try_from(width)?,
+            height: i32::try_from(height)?,
+        };
+
+        tokio::fs::write(self.dir.join(&stored.file_name), &bytes).await?;
+        if let Err(err) = tokio::fs::write(self.dir.join(&stored.thumbnail_name), &thumbnail).await
+        {
+            self.remove(&[stored.file_name]).await;
+            return Err(err.into());
+        }
+
+        info!("Image '{}' stored", stored.file_name);
+
+        Ok(stored)
+    }
+
+    /// Removes files, failures are only logged: a leftover file does no harm.
+    pub(crate) async fn remove(&self, file_names: &[String]) {
+        for file_name in file_names {
+            if let Err(err) = tokio::fs::remove_file(self.dir.join(file_name)).await {
+                warn!("Failed to remove media file '{file_name}': {err}");
+            }
+        }
+    }
+}
+
+/// Local directory with the personal data export archives, one subdirectory per
+/// user. Unlike the media, the archives are not served as static files: they are
+/// downloaded through the API by their owner only.
+#[derive(Clone)]
+pub(crate) struct ExportStorage {
+    dir: Arc<PathBuf>,
+    retention: chrono::TimeDelta,
+}
+
+impl ExportStorage {
+    pub(crate) async fn initialize(dir: PathBuf, retention: chrono::TimeDelta) -> Result<Self> {
+        tokio::fs::create_dir_all(&dir).await?;
+        info!("Export storage initialized in '{}'", dir.display());
+
+        Ok(Self {
+            dir: Arc::new(dir),
+            retention,
+        })
+    }
+
+    /// How long an archive can be downloaded once it is built.
+    pub(crate) fn retention(&self) -> chrono::TimeDelta {
+        self.retention
+    }
+
+    /// Stores the archive written by `write` on a blocking thread under a new unique name,
+    /// and returns the name. The archive is written to a temporary file first and moved
+    /// in place once complete, so that a failed write leaves no broken archive behind.
+    ///
+    /// A blocking thread cannot be aborted: if the returned future is dropped, e.g. on a
+    /// timeout, the flag passed to `write` is set. The writer should check it between
+    /// entries and give up, the temporary file is then removed by the blocking thread.
+    pub(crate) async fn store_archive<F>(&self, user_id: i32, write: F) -> Result<String>
+    where
+        F: FnOnce(std::fs::File, &AtomicBool) -> Result<()> + Send + 'static,
+    {
+        let user_dir = self.dir.join(user_id.to_string());
+        tokio::fs::create_dir_all(&user_dir).await?;
+
+        let file_name = format!("{}.zip", ulid::Ulid::new());
+        let path = user_dir.join(&file_name);
+        let temporary_path = user_dir.join(format!("{file_name}.part"));
+
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let _cancel_on_drop = CancelOnDrop(cancelled.clone());
+
+        tokio::task::spawn_blocking({
+            let file_name = file_name.clone();
+            move || {
+                let written = std::fs::File::create(&temporary_path)
+                    .map_err(anyhow::Error::from)
+                    .and_then(|file| write(file, &cancelled))
+                    .and_then(|()| {
+                        if cancelled.load(Ordering::Relaxed) {
+                            return Err(anyhow::anyhow!("Storing of the archive was cancelled"));
+                        }
+                        Ok(std::fs::rename(&temporary_path, path)?)
+                    });
+                if written.is_err() {
+                    if let Err(err) = std::fs::remove_file(&temporary_path) {
+                        warn!("Failed to remove unfinished export archive '{file_name}': {err}");
+                    }
+                }
+                written
+            }
+        })
+        .await??;
+
+        info!("Export archive '{file_name}' of user (id = {user_id}) stored");
+
+        Ok(file_name)
+    }
+
+    pub(crate) async fn open_archive(
+        &self,
+        user_id: i32,
+        file_name: &str,
+    ) -> Result<tokio::fs::File> {
+        let path = self.dir.join(user_id.to_string()).join(file_name);
+        Ok(tokio::fs::File::open(path).await?)
+    }
+
+    /// Removes the archive, failures are only logged like in [`MediaStorage::remove`].
+    pub(crate) async fn remove_archive(&self, user_id: i32, file_name: &str) {
+        let path = self.dir.join(user_id.to_string()).join(file_name);
+        if let Err(err) = tokio::fs::remove_file(path).await {
+            warn!("Failed to remove export archive '{file_name}': {err}");
+        }
+    }
+
+    /// Removes all archives of the user, whose exports are deleted with the account.
+    pub(crate) async fn remove_user_archives(&self, user_id: i32) {
+        let user_dir = self.dir.join(user_id.to_string());
+        match tokio::fs::remove_dir_all(&user_dir).await {
+            Ok(()) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => {}
+            Err(err) => warn!("Failed to remove export archives of user (id = {user_id}): {err}"),
+        }
+    }
+}
+
+/// Sets the flag once dropped, see [`ExportStorage::store_archive`].
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+struct DecodedImage {
+    width: u32,
+    height: u32,
+    thumbnail: Vec<u8>,
+    thumbnail_format: ImageFormat,
+}
+
+/// Decodes the image and encodes its thumbnail: PNG for images with transparency,
+/// JPEG for the rest.
+fn decode_with_thumbnail(bytes: &[u8], format: ImageFormat) -> Result<DecodedImage, AppError> {
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_IMAGE_DIMENSION);
+    limits.max_image_height = Some(MAX_IMAGE_DIMENSION);
+
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+    let image = reader.decode().map_err(|err| {
+        warn!("Upload rejected: image can not be decoded: {err}");
+        AppError::bad_request(Message::new("damaged_image").arg("max", MAX_IMAGE_DIMENSION))
+    })?;
+
+    let thumbnail = image.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    let (thumbnail, thumbnail_format) = if thumbnail.color().has_alpha() {
+        (
+            DynamicImage::ImageRgba8(thumbnail.to_rgba8()),
+            ImageFormat::Png,
+        )
+    } else {
+        (
+            DynamicImage::ImageRgb8(thumbnail.to_rgb8()),
+            ImageFormat::Jpeg,
+        )
+    };
+
+    let mut encoded = Cursor::new(Vec::new());
+    thumbnail
+        .write_to(&mut encoded, thumbnail_format)
+        .map_err(anyhow::Error::from)?;
+
+    Ok(DecodedImage {
+        width: image.width(),
+        height: image.height(),
+        thumbnail: encoded.into_inner(),
+        thumbnail_format,
+    })
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub(crate) struct PasswordHash(String);
 
 impl PasswordHash {
@@ -24,3 +24,19 @@ impl TryFrom<&tokio_postgres::Row> for PasswordHash {
         Ok(Self(row.try_get("password_hash")?))
     }
 }
+
+/// Escapes text to be inserted into HTML markup.
+pub(crate) fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for char in text.chars() {
+        match char {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#x27;"),
+            _ => escaped.push(char),
+        }
+    }
+    escaped
+}
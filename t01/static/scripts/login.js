@@ -32,13 +32,14 @@ function login() {
                 headers: {
                     "Content-Type": "application/json",
                 },
-                body: JSON.stringify({ username: username, password: password }),
+                body: JSON.stringify({ username: username, password: password, session: "cookie" }),
             });
             const data = await response.json();
             if (data.result === "ok") {
-                localStorage.setItem("jwt", data.jwt.token);
-                localStorage.setItem("username", data.jwt.username);
-                localStorage.setItem("user_id", data.jwt.user_id);
+                // tokens stay in HttpOnly cookies, only the profile is kept for rendering
+                localStorage.setItem("username", data.session.username);
+                localStorage.setItem("user_id", data.session.user_id);
+                localStorage.setItem("role", data.session.role);
 
                 const successMessage = document.createElement("div");
                 successMessage.className = "message success-message";
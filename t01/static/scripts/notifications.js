@@ -0,0 +1,119 @@
+document.addEventListener("DOMContentLoaded", (event) => {
+    if (!hasSession()) {
+        return;
+    }
+
+    document.getElementById("load-more").addEventListener("click", (event) => {
+        event.preventDefault();
+        fetchNotifications(nextBefore);
+    });
+
+    fetchNotifications();
+});
+
+let nextBefore = null;
+
+function fetchNotifications(before = null) {
+    const notificationsList = document.getElementById("notifications-list");
+    const loadMoreButton = document.getElementById("load-more");
+
+    const params = new URLSearchParams();
+    if (before) {
+        params.set("before", before);
+    }
+
+    authorizedFetch(`/api/notifications?${params}`).then((response) => response.json())
+        .then((data) => {
+            if (data.result === "err") {
+                notificationsList.innerHTML = `<p class="error">${data.message}</p>`;
+            } else if (data.result === "ok") {
+                renderNotifications(data.notifications, before !== null);
+
+                nextBefore = data.next_before;
+                loadMoreButton.style.display = nextBefore ? "block" : "none";
+
+                markNotificationsRead(data.notifications);
+            }
+        }).catch((error) => {
+            console.error("Error fetching notifications: ", error);
+            alert("Ошибка при попытке получить уведомления. Пожалуйста, попробуйте позже.");
+        });
+}
+
+// notifications are read once they are shown
+function markNotificationsRead(notifications) {
+    const unread = notifications
+        .filter((notification) => !notification.is_read)
+        .map((notification) => notification.notification_id);
+    if (unread.length === 0) {
+        return;
+    }
+
+    authorizedFetch("/api/notifications/read", {
+        method: "POST",
+        headers: {
+            "Content-Type": "application/json",
+        },
+        body: JSON.stringify({ notification_ids: unread }),
+    })
+        .then(() => fetchUnreadNotificationsCount())
+        .catch((error) => {
+            console.error("Error marking notifications as read: ", error);
+        });
+}
+
+function renderNotifications(notifications, append) {
+    const notificationsList = document.getElementById("notifications-list");
+
+    if (!append && notifications.length === 0) {
+        notificationsList.innerHTML = "<p class=\"message\">Пока что уведомлений нет.</p>";
+        return;
+    }
+
+    if (!append) {
+        notificationsList.innerHTML = "";
+    }
+
+    notifications.forEach((notification) => {
+        notificationsList.appendChild(renderNotification(notification));
+    });
+}
+
+function renderNotification(notification) {
+    const notificationElement = document.createElement("div");
+    notificationElement.classList.add("notification");
+    if (!notification.is_read) {
+        notificationElement.classList.add("unread");
+    }
+
+    const actorLink = document.createElement("a");
+    actorLink.href = `/users/${notification.actor_id}`;
+    actorLink.textContent = notification.actor_username;
+
+    const text = document.createElement("p");
+    text.appendChild(actorLink);
+
+    const actions = {
+        like: " оценил(а) ваш пост ",
+        comment: " прокомментировал(а) ваш пост ",
+        reply: " ответил(а) на ваш комментарий к посту ",
+        follow: " подписался(ась) на вас",
+    };
+    text.appendChild(document.createTextNode(actions[notification.kind]));
+
+    if (notification.post_id) {
+        const postLink = document.createElement("a");
+        postLink.href = `/posts/${notification.post_id}`;
+        postLink.textContent = notification.post_title || "";
+        text.appendChild(postLink);
+    }
+
+    const date = document.createElement("span");
+    date.classList.add("notification-date");
+    date.textContent = formatDateGMT3(new Date(notification.created_at));
+
+    notificationElement.appendChild(text);
+    notificationElement.appendChild(date);
+
+    return notificationElement;
+}
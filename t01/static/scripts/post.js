@@ -1,41 +1,236 @@
 document.addEventListener("DOMContentLoaded", function () {
     const postId = window.location.pathname.split("/").pop();
 
-    const jwt = localStorage.getItem("jwt");
-
-    if (!jwt) {
-        window.location.href = "/login";
+    // the post and its comments are rendered by the server, a session only adds the controls
+    if (!hasSession()) {
         return;
     }
 
-    const postContainer = document.querySelector(".post-container");
+    const userId = localStorage.getItem("user_id");
+    const postElement = document.querySelector("#post .post");
+    hydratePost(userId, postElement);
 
-    fetch(`/api/posts/${postId}`, {
-        headers: {
-            "Authorization": `Bearer ${jwt}`
-        }
-    })
-        .then(response => {
-            if (!response.ok) {
-                return response.json().then(err => {
-                    throw new Error(err.message);
+    postOwnerId = postElement.dataset.userId;
+    document.querySelectorAll("#comments-list .comment").forEach((commentElement) => {
+        addCommentControls(postId, commentElement);
+    });
+
+    const revisionsSection = document.getElementById("revisions-section");
+    if (revisionsSection.dataset.edited === "true") {
+        revisionsSection.style.display = "block";
+    }
+
+    document.getElementById("show-revisions").addEventListener("click", (event) => {
+        event.target.remove();
+        fetchRevisions(postId);
+    });
+
+    const commentForm = document.getElementById("comment-form");
+    commentForm.style.display = "";
+    commentForm.addEventListener("submit", (event) => {
+        event.preventDefault();
+
+        const content = document.getElementById("comment-content");
+        createComment(postId, content.value, null, commentForm);
+    });
+});
+
+function fetchRevisions(postId) {
+    const revisionsList = document.getElementById("revisions-list");
+
+    authorizedFetch(`/api/posts/${postId}/revisions`)
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                data.revisions.forEach((revision) => {
+                    const revisionElement = document.createElement("div");
+                    revisionElement.classList.add("comment");
+
+                    const metadata = document.createElement("div");
+                    metadata.classList.add("comment-metadata");
+                    metadata.textContent = `Версия до ${formatDateGMT3(new Date(revision.created_at))}`;
+
+                    const title = document.createElement("p");
+                    title.innerHTML = "<b></b>";
+                    title.firstChild.textContent = revision.title;
+
+                    const content = document.createElement("p");
+                    content.textContent = revision.content;
+
+                    revisionElement.appendChild(metadata);
+                    revisionElement.appendChild(title);
+                    revisionElement.appendChild(content);
+                    revisionsList.appendChild(revisionElement);
                 });
+            } else {
+                throw new Error(data.message);
             }
-            return response.json();
         })
-        .then(data => {
+        .catch((error) => {
+            console.error("Error fetching revisions: ", error);
+            revisionsList.innerHTML = `<p class="error">${error.message}</p>`;
+        });
+}
+
+let postOwnerId = null;
+
+function fetchComments(postId, ownerId) {
+    postOwnerId = ownerId;
+    const commentsList = document.getElementById("comments-list");
+
+    authorizedFetch(`/api/posts/${postId}/comments`)
+        .then((response) => response.json())
+        .then((data) => {
             if (data.result === "ok") {
-                const userId = localStorage.getItem("user_id");
+                commentsList.innerHTML = "";
+                if (data.comments.length === 0) {
+                    commentsList.innerHTML = "<p class=\"message\">Пока что комментариев нет.</p>";
+                    return;
+                }
+                data.comments.forEach((comment) => {
+                    commentsList.appendChild(renderComment(postId, comment));
+                });
+            } else {
+                throw new Error(data.message);
+            }
+        })
+        .catch((error) => {
+            console.error("Error fetching comments: ", error);
+            commentsList.innerHTML = `<p class="error">${error.message}</p>`;
+        });
+}
+
+// builds the same markup as `templates/post.askama.html` and adds the controls
+function renderComment(postId, comment) {
+    const commentElement = document.createElement("div");
+    commentElement.classList.add("comment");
+    commentElement.dataset.commentId = comment.comment_id;
+    commentElement.dataset.userId = comment.user_id;
+
+    const metadata = document.createElement("div");
+    metadata.classList.add("comment-metadata");
+    const author = document.createElement("a");
+    author.href = `/users/${comment.user_id}`;
+    author.textContent = comment.username;
+    metadata.appendChild(author);
+    metadata.append(`, ${formatDateGMT3(new Date(comment.created_at))}`);
+
+    const content = document.createElement("p");
+    content.textContent = comment.content;
 
-                const postElement = renderPost(userId, data.post);
+    const actions = document.createElement("div");
+    actions.classList.add("comment-actions");
 
-                postContainer.appendChild(postElement);
+    const replies = document.createElement("div");
+    replies.classList.add("comment-replies");
+    comment.replies.forEach((reply) => {
+        replies.appendChild(renderComment(postId, reply));
+    });
+
+    commentElement.appendChild(metadata);
+    commentElement.appendChild(content);
+    commentElement.appendChild(actions);
+    commentElement.appendChild(replies);
+
+    addCommentControls(postId, commentElement);
+
+    return commentElement;
+}
+
+function addCommentControls(postId, commentElement) {
+    const userId = localStorage.getItem("user_id");
+    const commentId = Number(commentElement.dataset.commentId);
+    const actions = commentElement.querySelector(":scope > .comment-actions");
+
+    const replyButton = document.createElement("button");
+    replyButton.textContent = "Ответить";
+    replyButton.addEventListener("click", () => {
+        toggleReplyForm(postId, commentId, commentElement, actions);
+    });
+    actions.appendChild(replyButton);
+
+    if (userId === commentElement.dataset.userId || userId === postOwnerId) {
+        const deleteButton = document.createElement("button");
+        deleteButton.textContent = "Удалить";
+        deleteButton.addEventListener("click", () => {
+            deleteComment(postId, commentId);
+        });
+        actions.appendChild(deleteButton);
+    }
+}
+
+function toggleReplyForm(postId, parentCommentId, commentElement, actions) {
+    const existingForm = commentElement.querySelector(":scope > .comment-form");
+    if (existingForm) {
+        existingForm.remove();
+        return;
+    }
+
+    const form = document.createElement("form");
+    form.classList.add("comment-form");
+
+    const textarea = document.createElement("textarea");
+    textarea.rows = 2;
+    textarea.name = "content";
+    textarea.required = true;
+
+    const submit = document.createElement("button");
+    submit.type = "submit";
+    submit.textContent = "Ответить";
+
+    form.appendChild(textarea);
+    form.appendChild(submit);
+    form.addEventListener("submit", (event) => {
+        event.preventDefault();
+        createComment(postId, textarea.value, parentCommentId, form);
+    });
+
+    actions.after(form);
+}
+
+function createComment(postId, content, parentCommentId, form) {
+    return authorizedFetch(`/api/posts/${postId}/comments`, {
+        method: "POST",
+        headers: {
+            "Content-Type": "application/json",
+        },
+        body: JSON.stringify({ content, parent_comment_id: parentCommentId }),
+    })
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                form.reset();
+                clearFieldErrors(form);
+                fetchComments(postId, postOwnerId);
+            } else if (data.errors) {
+                showFieldErrors(form, data.errors);
             } else {
                 throw new Error(data.message);
             }
         })
-        .catch(error => {
-            console.error("Error:", error);
-            postContainer.innerHTML = `<p class="error">${error.message}</p>`;
+        .catch((error) => {
+            console.error("Error creating comment: ", error);
+            alert(error.message);
         });
-});
+}
+
+function deleteComment(postId, commentId) {
+    authorizedFetch(`/api/posts/${postId}/comments/${commentId}`, {
+        method: "DELETE",
+        headers: {
+            "Content-Type": "application/json",
+        },
+    })
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                fetchComments(postId, postOwnerId);
+            } else {
+                throw new Error(data.message);
+            }
+        })
+        .catch((error) => {
+            console.error("Error deleting comment: ", error);
+            alert(error.message);
+        });
+}
@@ -1,13 +1,42 @@
 document.addEventListener("DOMContentLoaded", () => {
-    const postsContainer = document.getElementById("posts-container");
-    const jwt = localStorage.getItem("jwt");
+    const loadMoreButton = document.getElementById("load-more");
 
-    if (!jwt) {
-        postsContainer.innerHTML = "<p class='message'>Вам необходимо <a href='/login'>авторизоваться</a> чтобы просматривать и создавать посты.</p>";
+    // without a session the server-rendered pages are navigated by plain links
+    if (!hasSession()) {
+        document.getElementById("posts-sort").addEventListener("change", () => {
+            document.getElementById("posts-filter").submit();
+        });
+        document.getElementById("search-form").style.display = "none";
         return;
     }
 
-    fetchPosts();
+    const userId = localStorage.getItem("user_id");
+    document.querySelectorAll("#posts-list .post").forEach((postElement) => {
+        hydratePost(userId, postElement);
+    });
+    nextCursor = loadMoreButton.dataset.cursor || null;
+
+    document.getElementById("create-post-form").style.display = "block";
+    document.getElementById("posts-source").style.display = "";
+
+    document.getElementById("posts-sort").addEventListener("change", () => {
+        fetchPosts();
+    });
+
+    document.getElementById("posts-source").addEventListener("change", () => {
+        fetchPosts();
+    });
+
+    loadMoreButton.addEventListener("click", (event) => {
+        event.preventDefault();
+        fetchPosts(nextCursor);
+    });
+
+    document.getElementById("search-form").addEventListener("submit", (event) => {
+        event.preventDefault();
+
+        search(document.getElementById("search-query").value);
+    });
 
     const postForm = document.getElementById("post-form");
     postForm.addEventListener("submit", (event) => {
@@ -15,20 +44,106 @@ document.addEventListener("DOMContentLoaded", () => {
 
         const title = document.getElementById("title").value;
         const content = document.getElementById("content").value;
+        const images = document.getElementById("images").files;
+        const quoteOfId = Number(postForm.dataset.quoteOfId) || null;
+        const draft = document.getElementById("draft").checked;
+        // the input holds local time without a zone, the server expects UTC
+        const publishAtValue = document.getElementById("publish-at").value;
+        const publishAt = publishAtValue ? new Date(publishAtValue).toISOString() : null;
 
-        createPost(title, content);
+        createPost(title, content, images, quoteOfId, draft, publishAt);
     });
+
+    subscribeToFeed();
 });
 
-function fetchPosts() {
-    const jwt = localStorage.getItem("jwt");
+let nextCursor = null;
+
+// live updates of the shown posts, see `GET /api/stream`
+function subscribeToFeed() {
+    const events = new EventSource("/api/stream");
+
+    events.addEventListener("post_created", (event) => {
+        const { post_id } = JSON.parse(event.data);
+        showNewPost(post_id);
+    });
+
+    events.addEventListener("post_deleted", (event) => {
+        const { post_id } = JSON.parse(event.data);
+        const postElement = document.querySelector(`#posts-list .post[data-post-id="${post_id}"]`);
+        if (postElement) {
+            postElement.remove();
+        }
+    });
+
+    // reposts show the likes of the reposted post
+    events.addEventListener("likes_changed", (event) => {
+        const { post_id, likes_count } = JSON.parse(event.data);
+        const postElements = document.querySelectorAll(
+            `#posts-list .post[data-post-id="${post_id}"], #posts-list .post[data-repost-of-id="${post_id}"]`,
+        );
+        postElements.forEach((postElement) => {
+            postElement.querySelector(".like-count").textContent = `${likes_count} 👍`;
+        });
+    });
+
+    // the server ends the stream when the access token expires and the reconnect is refused
+    events.addEventListener("error", () => {
+        if (events.readyState !== EventSource.CLOSED) {
+            return;
+        }
+        refreshSession().then((refreshed) => {
+            if (refreshed) {
+                subscribeToFeed();
+            }
+        });
+    });
+}
+
+// only the list of all posts sorted by date has an obvious place for a new post
+function showNewPost(postId) {
+    const postsList = document.getElementById("posts-list");
+    if (document.getElementById("posts-source").value !== "/api/posts"
+        || document.getElementById("posts-sort").value !== "newest"
+        || postsList.querySelector(`.post[data-post-id="${postId}"]`)) {
+        return;
+    }
+
+    authorizedFetch(`/api/posts/${postId}`).then((response) => response.json())
+        .then((data) => {
+            // the list may have been reloaded meanwhile
+            if (data.result !== "ok" || postsList.querySelector(`.post[data-post-id="${postId}"]`)) {
+                return;
+            }
+
+            const message = postsList.querySelector(".message");
+            if (message) {
+                message.remove();
+            }
+
+            const userId = localStorage.getItem("user_id");
+            postsList.prepend(renderPost(userId, data.post));
+        }).catch((error) => {
+            console.error("Error fetching new post:", error);
+        });
+}
+
+// loads the first page, or the page after `cursor` appended to the already shown posts
+function fetchPosts(cursor = null) {
     const postsList = document.getElementById("posts-list");
     const createPostForm = document.getElementById("create-post-form");
+    const loadMoreButton = document.getElementById("load-more");
+
+    const params = new URLSearchParams({ sort: document.getElementById("posts-sort").value });
+    if (cursor) {
+        params.set("cursor", cursor);
+    }
 
-    fetch("/api/posts", {
+    const source = document.getElementById("posts-source").value;
+
+    authorizedFetch(`${source}?${params}`, {
         method: "GET",
         headers: {
-            "Authorization": `Bearer ${jwt}`,
             "Content-Type": "application/json",
         },
     })
@@ -38,7 +153,10 @@ function fetchPosts() {
                 postsList.innerHTML = `<p class="error">${data.message}</p>`;
             } else if (data.result === "ok") {
                 createPostForm.style.display = "block";
-                renderPosts(data.posts);
+                renderPosts(data.posts, cursor !== null);
+
+                nextCursor = data.next_cursor;
+                loadMoreButton.style.display = nextCursor ? "block" : "none";
             }
         })
         .catch((error) => {
@@ -47,24 +165,35 @@ function fetchPosts() {
         });
 }
 
-function createPost(title, content) {
-    const jwt = localStorage.getItem("jwt");
-
-    fetch("/api/posts", {
+function createPost(title, content, images, quoteOfId, draft, publishAt) {
+    authorizedFetch("/api/posts", {
         method: "POST",
         headers: {
-            "Authorization": `Bearer ${jwt}`,
             "Content-Type": "application/json",
         },
-        body: JSON.stringify({ title, content }),
+        body: JSON.stringify({
+            title,
+            content,
+            quote_of_id: quoteOfId,
+            draft,
+            publish_at: publishAt,
+        }),
     })
         .then((response) => response.json())
         .then((data) => {
-            if (data.result === "err") {
+            const postForm = document.getElementById("post-form");
+            if (data.result === "err" && data.errors) {
+                showFieldErrors(postForm, data.errors);
+            } else if (data.result === "err") {
                 alert(`Error: ${data.message}`);
             } else if (data.result === "ok") {
-                document.getElementById("post-form").reset();
-                fetchPosts();
+                clearFieldErrors(postForm);
+                uploadImages(data.post_id, images).finally(() => {
+                    postForm.reset();
+                    delete postForm.dataset.quoteOfId;
+                    document.getElementById("quote-of").textContent = "";
+                    fetchPosts();
+                });
             }
         })
         .catch((error) => {
@@ -73,18 +202,107 @@ function createPost(title, content) {
         });
 }
 
-function renderPosts(posts) {
+// the post is created first, images are attached to it with a separate request
+function uploadImages(postId, images) {
+    if (images.length === 0) {
+        return Promise.resolve();
+    }
+
+    const formData = new FormData();
+    Array.from(images).forEach((image) => {
+        formData.append("images", image);
+    });
+
+    // the browser sets the multipart boundary itself
+    return authorizedFetch(`/api/posts/${postId}/attachments`, {
+        method: "POST",
+        body: formData,
+    })
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.result === "err") {
+                alert(`Пост опубликован, но изображения не загружены: ${data.message}`);
+            }
+        })
+        .catch((error) => {
+            console.error("Error uploading images:", error);
+            alert("Пост опубликован, но изображения не загружены. Пожалуйста, попробуйте позже.");
+        });
+}
+
+function renderPosts(posts, append) {
     const postsList = document.getElementById("posts-list");
 
-    if (posts.length === 0) {
+    if (!append && posts.length === 0) {
         postsList.innerHTML = "<p class=\"message\">Пока что нет ни одного опубликованного поста.</p>";
         return;
     }
 
     const userId = localStorage.getItem("user_id");
-    postsList.innerHTML = "";
+    if (!append) {
+        postsList.innerHTML = "";
+    }
     posts.forEach((post) => {
         const postElement = renderPost(userId, post);
         postsList.appendChild(postElement);
     });
 }
+
+function search(query) {
+    const searchResults = document.getElementById("search-results");
+
+    if (!query.trim()) {
+        searchResults.innerHTML = "";
+        return;
+    }
+
+    const params = new URLSearchParams({ q: query });
+
+    authorizedFetch(`/api/search?${params}`)
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                renderSearchResults(data.users, data.posts);
+            } else {
+                throw new Error(data.message);
+            }
+        })
+        .catch((error) => {
+            console.error("Error searching: ", error);
+            searchResults.innerHTML = `<p class="error">${error.message}</p>`;
+        });
+}
+
+function renderSearchResults(users, posts) {
+    const searchResults = document.getElementById("search-results");
+    searchResults.innerHTML = "";
+
+    if (users.length === 0 && posts.length === 0) {
+        searchResults.innerHTML = "<p class=\"message\">Ничего не найдено.</p>";
+        return;
+    }
+
+    if (users.length > 0) {
+        const usersElement = document.createElement("div");
+        usersElement.classList.add("search-users");
+        usersElement.append("Пользователи: ");
+        users.forEach((user) => {
+            const userLink = document.createElement("a");
+            userLink.href = `/users/${user.user_id}`;
+            userLink.textContent = user.username;
+            usersElement.appendChild(userLink);
+        });
+        searchResults.appendChild(usersElement);
+    }
+
+    const userId = localStorage.getItem("user_id");
+    posts.forEach((post) => {
+        const postElement = renderPost(userId, post);
+
+        // snippets are escaped on the server, only matched words are wrapped into <mark>
+        postElement.querySelector("h2 a").innerHTML = post.title_snippet;
+        postElement.querySelector(".post-content").innerHTML = post.content_snippet;
+
+        searchResults.appendChild(postElement);
+    });
+}
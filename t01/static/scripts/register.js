@@ -28,6 +28,7 @@ function register() {
         // clear any existing messages
         const existingMessages = registerFormParentNode.querySelectorAll(".message");
         existingMessages.forEach(message => message.remove());
+        clearFieldErrors(registerForm);
 
         try {
             const response = await fetch("/api/register", {
@@ -48,6 +49,8 @@ function register() {
 
                 usernameElement.value = "";
                 passwordElement.value = "";
+            } else if (data.errors) {
+                showFieldErrors(registerForm, data.errors);
             } else {
                 const errorMessage = document.createElement("div");
                 errorMessage.className = "message error-message";
@@ -1,13 +1,19 @@
 document.addEventListener("DOMContentLoaded", () => {
     const rightLinks = document.getElementById("right-links");
 
-    const jwt = localStorage.getItem("jwt");
     const username = localStorage.getItem("username");
     const userId = localStorage.getItem("user_id");
 
-    if (jwt) {
+    if (hasSession()) {
+        const notificationsLink = document.getElementById("notifications-link");
         rightLinks.innerHTML = "";
 
+        if (notificationsLink) {
+            notificationsLink.style.display = "";
+            rightLinks.appendChild(notificationsLink);
+            fetchUnreadNotificationsCount();
+        }
+
         if (username) {
             const usernameElement = document.createElement("a");
             usernameElement.textContent = username;
@@ -25,62 +31,325 @@ document.addEventListener("DOMContentLoaded", () => {
         logoutLink.textContent = "Выйти";
         rightLinks.appendChild(logoutLink);
 
-        logoutLink.addEventListener("click", (event) => {
+        logoutLink.addEventListener("click", async (event) => {
             event.preventDefault();
-            localStorage.removeItem("jwt");
-            localStorage.removeItem("username");
-            localStorage.removeItem("user_id");
+
+            try {
+                await authorizedFetch("/api/logout", {
+                    method: "POST",
+                    headers: {
+                        "Content-Type": "application/json",
+                    },
+                    body: JSON.stringify({}),
+                });
+            } catch (error) {
+                console.error("Error during logout:", error);
+            }
+
+            clearSession();
 
             window.location.href = "/";
         });
     }
 });
 
+function fetchUnreadNotificationsCount() {
+    authorizedFetch("/api/notifications?limit=1").then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                const badge = document.getElementById("notifications-badge");
+                badge.textContent = data.unread_count;
+                badge.style.display = data.unread_count > 0 ? "" : "none";
+            }
+        }).catch((error) => {
+            console.error("Error fetching notifications count: ", error);
+        });
+}
+
+// the session itself lives in HttpOnly cookies set by `/api/login`
+function hasSession() {
+    return localStorage.getItem("user_id") !== null;
+}
+
+function clearSession() {
+    // tokens stored by bearer sessions of older versions
+    localStorage.removeItem("jwt");
+    localStorage.removeItem("refresh_token");
+    localStorage.removeItem("username");
+    localStorage.removeItem("user_id");
+    localStorage.removeItem("role");
+}
+
+function csrfToken() {
+    const cookie = document.cookie
+        .split("; ")
+        .find((cookie) => cookie.startsWith("csrf_token="));
+    return cookie ? cookie.substring("csrf_token=".length) : "";
+}
+
+async function refreshSession() {
+    const response = await fetch("/api/token/refresh", {
+        method: "POST",
+        headers: {
+            "Content-Type": "application/json",
+            "X-CSRF-Token": csrfToken(),
+        },
+        body: JSON.stringify({}),
+    });
+    const data = await response.json();
+    if (data.result !== "ok") {
+        clearSession();
+        return false;
+    }
+
+    localStorage.setItem("role", data.session.role);
+    return true;
+}
+
+// fetch with the CSRF token attached, the session is refreshed once if it has expired
+async function authorizedFetch(url, options = {}) {
+    const withToken = () => fetch(url, {
+        ...options,
+        headers: {
+            ...options.headers,
+            "X-CSRF-Token": csrfToken(),
+        },
+    });
+
+    const response = await withToken();
+    if (response.status !== 401 || !(await refreshSession())) {
+        return response;
+    }
+    return withToken();
+}
+
+// emoji of the reactions other than likes, in sync with `ReactionKind`
+const REACTION_EMOJI = {
+    heart: "❤️",
+    laugh: "😂",
+    wow: "😮",
+    sad: "😢",
+};
+
+// builds the same markup as `templates/partials/post.askama.html` and adds the controls
 function renderPost(userId, post) {
+    // a repost shows the reposted post under the name of the one who reposted it
+    const card = post.repost_of_id ? post.original : post;
+
     const postElement = document.createElement("div");
     postElement.classList.add("post");
+    postElement.dataset.postId = post.post_id;
+    postElement.dataset.userId = post.user_id;
+
+    if (post.repost_of_id) {
+        postElement.dataset.repostOfId = card.post_id;
+
+        const attribution = document.createElement("div");
+        attribution.classList.add("repost-attribution");
+        attribution.innerHTML = `🔁 <a href="/users/${post.user_id}">${post.username}</a> сделал(а) репост`;
+        postElement.appendChild(attribution);
+    }
 
     const postTitleLink = document.createElement("a");
-    postTitleLink.href = `/posts/${post.post_id}`;
-    postTitleLink.textContent = post.title;
+    postTitleLink.href = `/posts/${card.post_id}`;
+    postTitleLink.textContent = card.title;
 
     const postTitle = document.createElement("h2");
     postTitle.appendChild(postTitleLink);
 
-    const postContent = document.createElement("p");
-    postContent.textContent = post.content;
+    // sanitized on the server
+    const postContent = document.createElement("div");
+    postContent.classList.add("post-content");
+    postContent.innerHTML = card.content_html;
 
     const postMetadata = document.createElement("div");
     postMetadata.classList.add("post-metadata");
     postMetadata.innerHTML = `
-            <span>Автор: <a href="/users/${post.user_id}">${post.username}</a></span>
-            <span>${formatDateGMT3(new Date(post.created_at))}</span>
+            <span>Автор: <a href="/users/${card.user_id}">${card.username}</a></span>
+            <span>${formatDateGMT3(new Date(card.created_at))}</span>
         `;
+    if (card.edited_at) {
+        const editedMark = document.createElement("span");
+        editedMark.textContent = `Изменено: ${formatDateGMT3(new Date(card.edited_at))}`;
+        postMetadata.appendChild(editedMark);
+    }
+    // only the author sees drafts and scheduled posts, in `GET /api/me/drafts`
+    if (card.status === "draft") {
+        const statusMark = document.createElement("span");
+        statusMark.classList.add("post-status");
+        statusMark.textContent = "Черновик";
+        postMetadata.appendChild(statusMark);
+    } else if (card.status === "scheduled") {
+        const statusMark = document.createElement("span");
+        statusMark.classList.add("post-status");
+        statusMark.textContent = `Запланирован на ${formatDateGMT3(new Date(card.publish_at))}`;
+        postMetadata.appendChild(statusMark);
+    }
+
+    const postAttachments = document.createElement("div");
+    postAttachments.classList.add("post-attachments");
+    (card.attachments || []).forEach((attachment) => {
+        const attachmentLink = document.createElement("a");
+        attachmentLink.href = `/media/${attachment.file_name}`;
+        attachmentLink.target = "_blank";
+
+        const thumbnail = document.createElement("img");
+        thumbnail.src = `/media/${attachment.thumbnail_name}`;
+        thumbnail.alt = card.title;
+        thumbnail.loading = "lazy";
+
+        attachmentLink.appendChild(thumbnail);
+        postAttachments.appendChild(attachmentLink);
+    });
 
     const likesSection = document.createElement("div");
     likesSection.classList.add("likes-section");
 
-    const likeButton = document.createElement("button");
-    likeButton.textContent = "Поставить 👍";
-    likeButton.classList.add("like-button");
-
     const likeCount = document.createElement("span");
-    likeCount.textContent = `${post.likes_count} 👍`;
+    likeCount.textContent = `${card.likes_count} 👍`;
     likeCount.classList.add("like-count");
 
-    likesSection.appendChild(likeButton);
+    const repostCount = document.createElement("span");
+    repostCount.textContent = `${card.reposts_count} 🔁`;
+    repostCount.classList.add("repost-count");
+
+    const commentsLink = document.createElement("a");
+    commentsLink.href = `/posts/${card.post_id}`;
+    commentsLink.textContent = `${card.comments_count} 💬`;
+    commentsLink.classList.add("like-count");
+
     likesSection.appendChild(likeCount);
+    Object.entries(card.reactions ?? {}).forEach(([kind, count]) => {
+        if (kind === "like" || count === 0) {
+            return;
+        }
+
+        const reactionCount = document.createElement("span");
+        reactionCount.textContent = `${count} ${REACTION_EMOJI[kind] ?? kind}`;
+        reactionCount.title = kind;
+        reactionCount.classList.add("reaction-count");
+        likesSection.appendChild(reactionCount);
+    });
+    likesSection.appendChild(repostCount);
+    likesSection.appendChild(commentsLink);
 
     postElement.appendChild(postTitle);
     postElement.appendChild(postContent);
+    if (card.quote_of_id) {
+        postElement.appendChild(renderQuote(card.original));
+    }
+    if (postAttachments.childElementCount > 0) {
+        postElement.appendChild(postAttachments);
+    }
     postElement.appendChild(postMetadata);
     postElement.appendChild(likesSection);
 
+    addPostControls(userId, post, postElement);
+
+    return postElement;
+}
+
+// the quoted post embedded in a quote post, `null` while it is in the trash
+function renderQuote(quoted) {
+    const quote = document.createElement("blockquote");
+    quote.classList.add("post-quote");
+
+    if (!quoted) {
+        quote.textContent = "Цитируемый пост недоступен.";
+        return quote;
+    }
+
+    const quotedTitle = document.createElement("a");
+    quotedTitle.href = `/posts/${quoted.post_id}`;
+    quotedTitle.textContent = quoted.title;
+
+    // sanitized on the server
+    const quotedContent = document.createElement("div");
+    quotedContent.classList.add("post-content");
+    quotedContent.innerHTML = quoted.content_html;
+
+    const quotedAuthor = document.createElement("span");
+    quotedAuthor.innerHTML = `Автор: <a href="/users/${quoted.user_id}">${quoted.username}</a>`;
+
+    quote.appendChild(quotedTitle);
+    quote.appendChild(quotedContent);
+    quote.appendChild(quotedAuthor);
+
+    return quote;
+}
+
+// adds the controls to a post rendered by the server
+function hydratePost(userId, postElement) {
+    const post = {
+        post_id: Number(postElement.dataset.postId),
+        user_id: Number(postElement.dataset.userId),
+        repost_of_id: Number(postElement.dataset.repostOfId) || null,
+        title: postElement.querySelector("h2").textContent,
+    };
+
+    addPostControls(userId, post, postElement);
+}
+
+function addPostControls(userId, post, postElement) {
+    // likes and reposts of a repost go to the reposted post
+    const sharedPostId = post.repost_of_id ?? post.post_id;
+    const likeCount = postElement.querySelector(".like-count");
+    const repostCount = postElement.querySelector(".repost-count");
+
+    const likeButton = document.createElement("button");
+    likeButton.textContent = "Поставить 👍";
+    likeButton.classList.add("like-button");
     likeButton.addEventListener("click", () => {
-        toggleLike(post.post_id, likeButton, likeCount);
+        toggleLike(sharedPostId, likeButton, likeCount);
     });
 
-    if (userId === post.user_id.toString()) {
+    const repostButton = document.createElement("button");
+    repostButton.textContent = "Репост 🔁";
+    repostButton.classList.add("like-button");
+    repostButton.addEventListener("click", () => {
+        repost(sharedPostId, repostCount);
+    });
+
+    // server-rendered posts do not know the bookmarks of the user
+    let bookmarked = (post.repost_of_id ? post.original?.bookmarked : post.bookmarked) ?? false;
+    const bookmarkButton = document.createElement("button");
+    bookmarkButton.textContent = bookmarked ? "Убрать из закладок" : "В закладки";
+    bookmarkButton.classList.add("like-button");
+    bookmarkButton.addEventListener("click", () => {
+        toggleBookmark(sharedPostId, bookmarked).then((isBookmarked) => {
+            bookmarked = isBookmarked;
+            bookmarkButton.textContent = bookmarked ? "Убрать из закладок" : "В закладки";
+        });
+    });
+
+    const likesSection = postElement.querySelector(".likes-section");
+    likesSection.prepend(bookmarkButton);
+    likesSection.prepend(repostButton);
+    likesSection.prepend(likeButton);
+
+    // quotes are written in the form for new posts, which only the list of posts has
+    const postForm = document.getElementById("post-form");
+    if (postForm) {
+        const quoteButton = document.createElement("button");
+        quoteButton.textContent = "Цитировать";
+        quoteButton.classList.add("like-button");
+        quoteButton.addEventListener("click", () => {
+            postForm.dataset.quoteOfId = sharedPostId;
+            document.getElementById("quote-of").textContent = `Цитата поста «${post.title}»`;
+            postForm.scrollIntoView();
+        });
+        likesSection.prepend(quoteButton);
+    }
+
+    if (userId === post.user_id.toString() && post.repost_of_id) {
+        const removeButton = document.createElement("button");
+        removeButton.textContent = "Отменить репост";
+        removeButton.classList.add("delete-button");
+        removeButton.addEventListener("click", () => {
+            removeRepost(sharedPostId, postElement);
+        });
+        postElement.appendChild(removeButton);
+    } else if (userId === post.user_id.toString()) {
         const deleteButton = document.createElement("button");
         deleteButton.textContent = "Удалить";
         deleteButton.classList.add("delete-button");
@@ -88,18 +357,129 @@ function renderPost(userId, post) {
             deletePost(post.post_id, postElement);
         });
         postElement.appendChild(deleteButton);
+
+        const editButton = document.createElement("button");
+        editButton.textContent = "Редактировать";
+        editButton.classList.add("delete-button");
+        editButton.addEventListener("click", () => {
+            toggleEditForm(post, postElement);
+        });
+        postElement.appendChild(editButton);
+
+        if (post.status && post.status !== "published") {
+            const publishButton = document.createElement("button");
+            publishButton.textContent = "Опубликовать сейчас";
+            publishButton.classList.add("delete-button");
+            publishButton.addEventListener("click", () => {
+                publishDraft(post.post_id, postElement);
+            });
+            postElement.appendChild(publishButton);
+        }
+    } else if (["moderator", "admin"].includes(localStorage.getItem("role"))) {
+        const moderateButton = document.createElement("button");
+        moderateButton.textContent = "Удалить (модерация)";
+        moderateButton.classList.add("delete-button");
+        moderateButton.addEventListener("click", () => {
+            const reason = prompt("Причина удаления:");
+            if (reason !== null) {
+                moderateDeletePost(post.post_id, reason, postElement);
+            }
+        });
+        postElement.appendChild(moderateButton);
     }
+}
 
-    return postElement;
+function repost(postId, repostCount) {
+    authorizedFetch(`/api/posts/${postId}/repost`, {
+        method: "POST",
+        headers: {
+            "Content-Type": "application/json",
+        },
+    })
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                repostCount.textContent = `${data.reposts_count} 🔁`;
+            } else {
+                throw new Error(data.message);
+            }
+        })
+        .catch((error) => {
+            console.error("Error reposting post: ", error);
+            alert(error.message);
+        });
 }
 
-function toggleLike(postId, likeButton, likeCount) {
-    const jwt = localStorage.getItem("jwt");
+// the published post leaves the list of drafts
+function publishDraft(postId, postElement) {
+    authorizedFetch(`/api/me/drafts/${postId}/publish`, {
+        method: "POST",
+        headers: {
+            "Content-Type": "application/json",
+        },
+        body: JSON.stringify({}),
+    })
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                postElement.remove();
+            } else {
+                throw new Error(data.message);
+            }
+        })
+        .catch((error) => {
+            console.error("Error publishing draft: ", error);
+            alert(error.message);
+        });
+}
+
+// resolves to whether the post is bookmarked after the request
+function toggleBookmark(postId, bookmarked) {
+    return authorizedFetch(`/api/posts/${postId}/bookmark`, {
+        method: bookmarked ? "DELETE" : "POST",
+        headers: {
+            "Content-Type": "application/json",
+        },
+    })
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                return data.bookmarked;
+            }
+            throw new Error(data.message);
+        })
+        .catch((error) => {
+            console.error("Error toggling bookmark: ", error);
+            alert(error.message);
+            return bookmarked;
+        });
+}
 
-    fetch(`/api/posts/${postId}/likes`, {
+function removeRepost(postId, postElement) {
+    authorizedFetch(`/api/posts/${postId}/repost`, {
+        method: "DELETE",
+        headers: {
+            "Content-Type": "application/json",
+        },
+    })
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                postElement.remove();
+            } else {
+                throw new Error(data.message);
+            }
+        })
+        .catch((error) => {
+            console.error("Error removing repost: ", error);
+            alert(error.message);
+        });
+}
+
+function toggleLike(postId, likeButton, likeCount) {
+    authorizedFetch(`/api/posts/${postId}/likes`, {
         method: "POST",
         headers: {
-            "Authorization": `Bearer ${jwt}`,
             "Content-Type": "application/json",
         },
     })
@@ -118,13 +498,111 @@ function toggleLike(postId, likeButton, likeCount) {
         });
 }
 
-function deletePost(postId, postElement) {
-    const jwt = localStorage.getItem("jwt");
+// shows messages of a 422 response under the inputs named after the invalid fields
+function showFieldErrors(form, errors) {
+    clearFieldErrors(form);
 
-    fetch(`/api/posts/${postId}`, {
+    Object.entries(errors).forEach(([field, messages]) => {
+        const input = form.querySelector(`[name="${field}"]`);
+        if (!input) {
+            return;
+        }
+
+        const errorElement = document.createElement("div");
+        errorElement.className = "field-error";
+        errorElement.textContent = messages.join(" ");
+        input.after(errorElement);
+    });
+}
+
+function clearFieldErrors(form) {
+    form.querySelectorAll(".field-error").forEach((element) => element.remove());
+}
+
+function toggleEditForm(post, postElement) {
+    const existingForm = postElement.querySelector(".edit-post-form");
+    if (existingForm) {
+        existingForm.remove();
+        return;
+    }
+
+    // server-rendered posts contain only the rendered HTML, not the Markdown source
+    if (post.content === undefined) {
+        authorizedFetch(`/api/posts/${post.post_id}`).then((response) => response.json())
+            .then((data) => {
+                if (data.result !== "ok") {
+                    throw new Error(data.message);
+                }
+                post.title = data.post.title;
+                post.content = data.post.content;
+                toggleEditForm(post, postElement);
+            })
+            .catch((error) => {
+                console.error("Error fetching post: ", error);
+                alert(error.message);
+            });
+        return;
+    }
+
+    const form = document.createElement("form");
+    form.classList.add("edit-post-form");
+
+    const titleInput = document.createElement("input");
+    titleInput.type = "text";
+    titleInput.name = "title";
+    titleInput.value = post.title;
+    titleInput.required = true;
+
+    const contentInput = document.createElement("textarea");
+    contentInput.rows = 5;
+    contentInput.name = "content";
+    contentInput.value = post.content;
+    contentInput.required = true;
+
+    const saveButton = document.createElement("button");
+    saveButton.type = "submit";
+    saveButton.textContent = "Сохранить";
+
+    form.appendChild(titleInput);
+    form.appendChild(contentInput);
+    form.appendChild(saveButton);
+    form.addEventListener("submit", (event) => {
+        event.preventDefault();
+        editPost(post.post_id, titleInput.value, contentInput.value, postElement, form);
+    });
+
+    postElement.appendChild(form);
+}
+
+function editPost(postId, title, content, postElement, form) {
+    authorizedFetch(`/api/posts/${postId}`, {
+        method: "PATCH",
+        headers: {
+            "Content-Type": "application/json",
+        },
+        body: JSON.stringify({ title, content }),
+    })
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                const userId = localStorage.getItem("user_id");
+                postElement.replaceWith(renderPost(userId, data.post));
+            } else if (data.errors) {
+                showFieldErrors(form, data.errors);
+            } else {
+                throw new Error(data.message);
+            }
+        })
+        .catch((error) => {
+            console.error("Error editing post: ", error);
+            alert(error.message);
+        });
+}
+
+function deletePost(postId, postElement) {
+    authorizedFetch(`/api/posts/${postId}`, {
         method: "DELETE",
         headers: {
-            "Authorization": `Bearer ${jwt}`,
             "Content-Type": "application/json",
         },
     })
@@ -142,6 +620,28 @@ function deletePost(postId, postElement) {
         });
 }
 
+function moderateDeletePost(postId, reason, postElement) {
+    authorizedFetch(`/api/moderation/posts/${postId}`, {
+        method: "DELETE",
+        headers: {
+            "Content-Type": "application/json",
+        },
+        body: JSON.stringify({ reason: reason || null }),
+    })
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                postElement.remove();
+            } else {
+                throw new Error(data.message);
+            }
+        })
+        .catch((error) => {
+            console.error("Error moderating post: ", error);
+            alert(error.message);
+        });
+}
+
 function formatDateGMT3(date) {
     const gmt3Date = new Date(date.getTime() + 3 * 60 * 60 * 1000);
 
@@ -1,45 +1,281 @@
 document.addEventListener("DOMContentLoaded", (event) => {
+    const loadMoreButton = document.getElementById("load-more");
+
+    // without a session the server-rendered pages are navigated by plain links
+    if (!hasSession()) {
+        return;
+    }
+
+    const userId = localStorage.getItem("user_id");
+    document.querySelectorAll("#user-posts-list .post").forEach((postElement) => {
+        hydratePost(userId, postElement);
+    });
+    nextCursor = loadMoreButton.dataset.cursor || null;
+
+    const profileUserId = document.getElementById("user-container").dataset.userId;
+    if (userId !== profileUserId) {
+        fetchFollowState(profileUserId);
+    } else {
+        initializeProfileSettings(userId);
+    }
+
+    loadMoreButton.addEventListener("click", (event) => {
+        event.preventDefault();
+        fetchUserPosts(nextCursor);
+    });
+});
+
+let nextCursor = null;
+
+function fetchUserPosts(cursor = null) {
     const userContainer = document.getElementById("user-container");
+    const loadMoreButton = document.getElementById("load-more");
 
-    const jwt = localStorage.getItem("jwt");
-    const userId = window.location.pathname.split("/").pop();
+    const userId = userContainer.dataset.userId;
+    // the cursor only continues the order it was taken in
+    const params = new URLSearchParams({ sort: userContainer.dataset.sort });
+    if (cursor) {
+        params.set("cursor", cursor);
+    }
 
-    fetch(`/api/users/${userId}`, {
-        headers: {
-            "Authorization": `Bearer ${jwt}`
-        }
-    }).then((response) => response.json())
+    authorizedFetch(`/api/users/${userId}?${params}`).then((response) => response.json())
         .then((data) => {
             if (data.result === "err") {
                 userContainer.innerHTML = `<p class="error">${data.message}</p>`;
             } else if (data.result === "ok") {
-                renderUserPosts(data.username, data.posts);
+                renderUserPosts(data.posts, cursor !== null);
+
+                nextCursor = data.next_cursor;
+                loadMoreButton.style.display = nextCursor ? "block" : "none";
             }
         }).catch((error) => {
             console.error("Error fetching user posts: ", error);
             alert("Ошибка при попытке получить посты пользователя. Пожалуйста, попробуйте позже.");
         });
-});
+}
 
+// whether the viewer follows the user is only known with a session
+function fetchFollowState(profileUserId) {
+    authorizedFetch(`/api/users/${profileUserId}?limit=1`).then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                renderFollowButton(profileUserId, data.is_followed);
+            } else {
+                throw new Error(data.message);
+            }
+        }).catch((error) => {
+            console.error("Error fetching follow state: ", error);
+        });
+}
+
+function renderFollowButton(profileUserId, isFollowed) {
+    const userProfile = document.getElementById("user-profile");
+    const followersCount = document.getElementById("followers-count");
+
+    const followButton = document.createElement("button");
+    followButton.classList.add("like-button");
+    followButton.textContent = isFollowed ? "Отписаться" : "Подписаться";
+
+    followButton.addEventListener("click", () => {
+        authorizedFetch(`/api/users/${profileUserId}/follow`, {
+            method: isFollowed ? "DELETE" : "POST",
+            headers: {
+                "Content-Type": "application/json",
+            },
+        })
+            .then((response) => response.json())
+            .then((data) => {
+                if (data.result === "ok") {
+                    isFollowed = data.is_followed;
+                    followButton.textContent = isFollowed ? "Отписаться" : "Подписаться";
+                    followersCount.textContent = `Подписчики: ${data.followers_count}`;
+                } else {
+                    throw new Error(data.message);
+                }
+            })
+            .catch((error) => {
+                console.error("Error toggling follow: ", error);
+                alert(error.message);
+            });
+    });
 
-function renderUserPosts(username, posts) {
+    userProfile.appendChild(followButton);
+}
+
+function renderUserPosts(posts, append) {
     const userPostsList = document.getElementById("user-posts-list");
 
-    if (posts.length === 0) {
+    if (!append && posts.length === 0) {
         userPostsList.innerHTML = "<p class=\"message\">Пока что постов нет.</p>";
         return;
     }
 
     const userId = localStorage.getItem("user_id");
-    userPostsList.innerHTML = "";
 
-    const usernameElement = document.createElement("div");
-    usernameElement.innerHTML = `<h2>Посты пользователя ${username}</h2>`;
-    usernameElement.className = "username-container";
-    userPostsList.appendChild(usernameElement);
+    if (!append) {
+        userPostsList.innerHTML = "";
+    }
 
     posts.forEach((post) => {
         const postElement = renderPost(userId, post);
         userPostsList.appendChild(postElement);
     });
 }
+
+// forms for the owner of the page, the profile itself is rendered by the server
+function initializeProfileSettings(userId) {
+    document.getElementById("profile-settings").style.display = "block";
+
+    const profileForm = document.getElementById("profile-form");
+    profileForm.addEventListener("submit", (event) => {
+        event.preventDefault();
+
+        sendProfileRequest(`/api/users/${userId}/profile`, profileForm, {
+            method: "PATCH",
+            headers: {
+                "Content-Type": "application/json",
+            },
+            body: JSON.stringify({
+                display_name: document.getElementById("display-name").value,
+                bio: document.getElementById("bio").value,
+            }),
+        }).then((data) => data && window.location.reload());
+    });
+
+    const avatarForm = document.getElementById("avatar-form");
+    avatarForm.addEventListener("submit", (event) => {
+        event.preventDefault();
+
+        const formData = new FormData();
+        formData.append("avatar", document.getElementById("avatar").files[0]);
+
+        // the browser sets the multipart boundary itself
+        sendProfileRequest("/api/me/avatar", avatarForm, {
+            method: "PUT",
+            body: formData,
+        }).then((data) => data && window.location.reload());
+    });
+
+    document.getElementById("delete-avatar").addEventListener("click", () => {
+        sendProfileRequest("/api/me/avatar", avatarForm, {
+            method: "DELETE",
+        }).then((data) => data && window.location.reload());
+    });
+
+    const passwordForm = document.getElementById("password-form");
+    passwordForm.addEventListener("submit", (event) => {
+        event.preventDefault();
+
+        sendProfileRequest("/api/me/password", passwordForm, {
+            method: "POST",
+            headers: {
+                "Content-Type": "application/json",
+            },
+            body: JSON.stringify({
+                current_password: document.getElementById("current-password").value,
+                new_password: document.getElementById("new-password").value,
+            }),
+        }).then((data) => {
+            if (!data) {
+                return;
+            }
+            alert("Пароль изменён. Войдите с новым паролем.");
+            clearSession();
+            window.location.href = "/login";
+        });
+    });
+
+    const exportForm = document.getElementById("export-form");
+    exportForm.addEventListener("submit", (event) => {
+        event.preventDefault();
+
+        sendProfileRequest("/api/me/export", exportForm, {
+            method: "POST",
+            headers: {
+                "Content-Type": "application/json",
+            },
+        }).then((data) => {
+            if (data) {
+                showExportStatus(data.export);
+            }
+        });
+    });
+
+    const deleteAccountForm = document.getElementById("delete-account-form");
+    deleteAccountForm.addEventListener("submit", (event) => {
+        event.preventDefault();
+
+        if (!confirm("Удалить аккаунт без возможности восстановления?")) {
+            return;
+        }
+
+        sendProfileRequest("/api/me", deleteAccountForm, {
+            method: "DELETE",
+            headers: {
+                "Content-Type": "application/json",
+            },
+            body: JSON.stringify({
+                password: document.getElementById("delete-password").value,
+            }),
+        }).then((data) => {
+            if (!data) {
+                return;
+            }
+            clearSession();
+            window.location.href = "/";
+        });
+    });
+}
+
+// the archive is built in the background, the export is polled until it is finished
+function showExportStatus(exportState) {
+    const exportStatus = document.getElementById("export-status");
+
+    if (exportState.status === "pending") {
+        exportStatus.textContent = "Архив готовится…";
+        setTimeout(() => {
+            authorizedFetch(`/api/me/export/${exportState.export_id}`)
+                .then((response) => response.json())
+                .then((data) => {
+                    if (data.result === "ok") {
+                        showExportStatus(data.export);
+                    } else {
+                        exportStatus.textContent = data.message;
+                    }
+                })
+                .catch((error) => {
+                    console.error("Error fetching export: ", error);
+                });
+        }, 2000);
+    } else if (exportState.status === "ready") {
+        // the session cookies authorize the download
+        exportStatus.innerHTML = `<a href="/api/me/export/${exportState.export_id}/download">Скачать архив</a>`
+            + ` (доступен до ${formatDateGMT3(new Date(exportState.expires_at))})`;
+    } else {
+        exportStatus.textContent = "Не удалось подготовить архив. Попробуйте ещё раз.";
+    }
+}
+
+// resolves with the response on success and with null on failure, errors are shown in the form
+function sendProfileRequest(url, form, options) {
+    return authorizedFetch(url, options)
+        .then((response) => response.json())
+        .then((data) => {
+            if (data.result === "ok") {
+                clearFieldErrors(form);
+                return data;
+            }
+
+            if (data.errors) {
+                showFieldErrors(form, data.errors);
+            } else {
+                alert(`Ошибка: ${data.message}`);
+            }
+            return null;
+        })
+        .catch((error) => {
+            console.error("Error updating profile: ", error);
+            alert("Произошла ошибка. Пожалуйста, попробуйте позже.");
+            return null;
+        });
+}